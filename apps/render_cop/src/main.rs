@@ -55,17 +55,18 @@ fn create_nodes() -> Result<HashMap<Noise, HoudiniNode>> {
     Ok(map)
 }
 
-fn render_node(node: &HoudiniNode, input: f32) -> (Vec<u8>, u128) {
+fn render_node(node: &HoudiniNode, input: f32) -> (image::Handle, u128) {
     let Parameter::Float(parm) = node.parameter("input").expect("Input Parm") else {
         panic!("Parameter input not found");
     };
     parm.set(0, input).unwrap();
-    let mut buffer = Vec::new();
     let _start = std::time::Instant::now();
-    node.session
-        .render_cop_to_memory(node, &mut buffer, "C", "PNG")
+    let pixels = node
+        .session
+        .render_cop_to_pixels(node, "C")
         .expect("COP Render");
-    (buffer, _start.elapsed().as_millis())
+    let handle = image::Handle::from_pixels(pixels.width(), pixels.height(), pixels.to_rgba8());
+    (handle, _start.elapsed().as_millis())
 }
 
 impl Sandbox for App {
@@ -78,7 +79,7 @@ impl Sandbox for App {
             Ok(App {
                 input: 0.0,
                 asset_map,
-                image: image::Handle::from_memory(img),
+                image: img,
                 buffer: vec![],
                 noise: Some(Noise::Alligator),
                 num_cooks: 1,
@@ -103,7 +104,7 @@ impl Sandbox for App {
         let (image, ms) = render_node(node, self.input);
         self.num_cooks += 1;
         self.render_time = ms;
-        self.image = image::Handle::from_memory(image);
+        self.image = image;
     }
 
     fn view(&self) -> Element<'_, Self::Message> {
@@ -18,10 +18,8 @@ fn raw_hapi_function_names() -> HashSet<Item> {
         "HAPI_CreateCustomSession",
         "HAPI_SetCustomString",
         "HAPI_RemoveCustomString",
-        "HAPI_GetHandleInfo",
         "HAPI_BindCustomImplementation",
         "HAPI_GetImageFilePath",
-        "HAPI_GetHandleBindingInfo",
         "HAPI_GetWorkitemResultInfo"
     ];
     let raw = Path::new("../../src/ffi/bindings.rs");
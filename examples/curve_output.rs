@@ -1,5 +1,4 @@
-use hapi_rs::attribute::*;
-use hapi_rs::geometry::{AttributeOwner, CurveOrders, CurveType, Geometry, PartInfo, PartType};
+use hapi_rs::geometry::{Geometry, PartInfo, PartType};
 use hapi_rs::node::{NodeFlags, NodeType, ObjectInfo};
 use hapi_rs::session::{quick_session, SessionOptions};
 use hapi_rs::Result;
@@ -37,54 +36,24 @@ fn print_curve_info(geo: &Geometry, obj: &ObjectInfo, part: &PartInfo) -> Result
         part_id
     );
 
-    let curve = geo.curve_info(part_id)?;
-
-    println!(
-        "Curve mesh type = {}",
-        match curve.curve_type() {
-            CurveType::Linear => "Linear",
-            CurveType::Nurbs => "Nurbs",
-            CurveType::Bezier => "Bezier",
-            _ => "Unknown",
-        }
-    );
-
-    let counts = geo.curve_counts(part_id, 0, curve.curve_count())?;
-    println!("Curve count: {}", curve.curve_count());
-    let mut knot_offset = 0;
-    for (curve_idx, vertices) in counts.iter().enumerate() {
-        println!("Curve {} of {}", curve_idx, counts.len());
-        println!("Num of vertices : {}", vertices);
-        let mut order = curve.order();
-        if order == CurveOrders::Varying as i32 || order == CurveOrders::Invalid as i32 {
-            let orders = geo.curve_orders(part_id, curve_idx as i32, 1)?;
-            order = orders[0];
-        };
-        println!("Curve Order: {}", order as u32);
-
-        if *vertices < order {
-            println!("Not enough vertices on curve {}", curve_idx);
-            knot_offset += *vertices + order;
+    let curves = geo.read_curves(part_id)?;
+    println!("Curve count: {}", curves.curves.len());
+    for (curve_idx, curve) in curves.curves.iter().enumerate() {
+        println!("Curve {} of {}", curve_idx, curves.curves.len());
+        println!("Curve type: {:?}", curve.kind);
+        println!("Num of vertices : {}", curve.points.len());
+        println!("Curve Order: {}", curve.order);
+        if let Err(e) = curve.validate() {
+            println!("Invalid curve {}: {}", curve_idx, e);
             continue;
         }
-        let attrib = geo
-            .get_attribute(part_id, AttributeOwner::Point, "P")?
-            .unwrap();
-        let attrib = attrib.downcast::<NumericAttr<f32>>().unwrap();
-        let positions = attrib.get(part_id)?;
-        for cv in 0..attrib.info().count() {
-            let idx = (cv * attrib.info().tuple_size()) as usize;
-            println!("CV {}: {:?}", cv + 1, &positions[idx..idx + 3])
+        for (cv, position) in curve.points.iter().enumerate() {
+            println!("CV {}: {:?}", cv + 1, position)
         }
-
-        if curve.has_knots() {
-            geo.curve_knots(part_id, knot_offset, *vertices + order)?
-                .iter()
-                .enumerate()
-                .for_each(|(i, v)| println!("knot {}: {}", i + 1, v))
+        for (i, knot) in curve.knots.iter().enumerate() {
+            println!("knot {}: {}", i + 1, knot)
         }
-
-        knot_offset += *vertices + order;
+        println!("Preview: {:?}", curve.sample(8).unwrap_or_default());
     }
     Ok(())
 }
@@ -7,7 +7,18 @@ use crate::{node::HoudiniNode, Result};
 use duplicate::duplicate_item;
 use std::ffi::CStr;
 
-pub trait AttribAccess: Sized + 'static {
+mod private {
+    pub trait Sealed {}
+
+    macro_rules! sealed {
+        ($($t:ty),*) => { $(impl Sealed for $t {})* };
+    }
+
+    sealed!(u8, i8, i16, i32, i64, f32, f64);
+}
+
+/// Numeric attribute storage types. Sealed, implemented for the numbers HAPI stores.
+pub trait AttribAccess: private::Sealed + Sized + 'static {
     fn storage() -> StorageType;
     fn storage_array() -> StorageType;
     fn get(
@@ -17,6 +28,17 @@ pub trait AttribAccess: Sized + 'static {
         part_id: i32,
         buffer: &mut Vec<Self>,
     ) -> Result<()>;
+    /// Read `len` tuples starting at tuple `start` into the buffer, which must hold
+    /// `len * tuple_size` values.
+    fn get_slice(
+        name: &CStr,
+        node: &HoudiniNode,
+        info: &AttributeInfo,
+        part_id: i32,
+        buffer: &mut [Self],
+        start: i32,
+        len: i32,
+    ) -> Result<()>;
     fn set(
         name: &CStr,
         node: &HoudiniNode,
@@ -26,6 +48,16 @@ pub trait AttribAccess: Sized + 'static {
         start: i32,
         len: i32,
     ) -> Result<()>;
+    /// Set a single tuple value to `len` elements starting at `start`.
+    fn set_unique(
+        name: &CStr,
+        node: &HoudiniNode,
+        info: &AttributeInfo,
+        part_id: i32,
+        data: &[Self],
+        start: i32,
+        len: i32,
+    ) -> Result<()>;
     fn get_array(
         name: &CStr,
         node: &HoudiniNode,
@@ -53,6 +85,7 @@ _storage [StorageType::Uint8]
 _storage_array [StorageType::Uint8Array]
 _get [HAPI_GetAttributeUInt8Data]
 _set [HAPI_SetAttributeUInt8Data]
+_set_unique [HAPI_SetAttributeUInt8UniqueData]
 _get_array [HAPI_GetAttributeUInt8ArrayData]
 _set_array [HAPI_SetAttributeUInt8ArrayData]
 ]
@@ -62,6 +95,7 @@ _storage [StorageType::Int8]
 _storage_array [StorageType::Int8Array]
 _get [HAPI_GetAttributeInt8Data]
 _set [HAPI_SetAttributeInt8Data]
+_set_unique [HAPI_SetAttributeInt8UniqueData]
 _get_array [HAPI_GetAttributeInt8ArrayData]
 _set_array [HAPI_SetAttributeInt8ArrayData]
 ]
@@ -71,6 +105,7 @@ _storage [StorageType::Int16]
 _storage_array [StorageType::Int16Array]
 _get [HAPI_GetAttributeInt16Data]
 _set [HAPI_SetAttributeInt16Data]
+_set_unique [HAPI_SetAttributeInt16UniqueData]
 _get_array [HAPI_GetAttributeInt16ArrayData]
 _set_array [HAPI_SetAttributeInt16ArrayData]
 ]
@@ -80,6 +115,7 @@ _storage [StorageType::Int]
 _storage_array [StorageType::IntArray]
 _get [HAPI_GetAttributeIntData]
 _set [HAPI_SetAttributeIntData]
+_set_unique [HAPI_SetAttributeIntUniqueData]
 _get_array [HAPI_GetAttributeIntArrayData]
 _set_array [HAPI_SetAttributeIntArrayData]
 ]
@@ -89,6 +125,7 @@ _storage [StorageType::Int64]
 _storage_array [StorageType::Int64Array]
 _get [HAPI_GetAttributeInt64Data]
 _set [HAPI_SetAttributeInt64Data]
+_set_unique [HAPI_SetAttributeInt64UniqueData]
 _get_array [HAPI_GetAttributeInt64ArrayData]
 _set_array [HAPI_SetAttributeInt64ArrayData]
 ]
@@ -98,6 +135,7 @@ _storage [StorageType::Float]
 _storage_array [StorageType::FloatArray]
 _get [HAPI_GetAttributeFloatData]
 _set [HAPI_SetAttributeFloatData]
+_set_unique [HAPI_SetAttributeFloatUniqueData]
 _get_array [HAPI_GetAttributeFloatArrayData]
 _set_array [HAPI_SetAttributeFloatArrayData]
 ]
@@ -107,6 +145,7 @@ _storage [StorageType::Float64]
 _storage_array [StorageType::Float64Array]
 _get [HAPI_GetAttributeFloat64Data]
 _set [HAPI_SetAttributeFloat64Data]
+_set_unique [HAPI_SetAttributeFloat64UniqueData]
 _get_array [HAPI_GetAttributeFloat64ArrayData]
 _set_array [HAPI_SetAttributeFloat64ArrayData]
 ]
@@ -148,6 +187,37 @@ impl AttribAccess for _val_type {
             .check_err(&node.session, || stringify!(Calling _get))
         }
     }
+    fn get_slice(
+        name: &CStr,
+        node: &HoudiniNode,
+        info: &AttributeInfo,
+        part: i32,
+        buffer: &mut [Self],
+        start: i32,
+        len: i32,
+    ) -> Result<()> {
+        debug_assert!(node.is_valid()?);
+        assert_eq!(
+            buffer.len(),
+            (len * info.inner.tupleSize) as usize,
+            "buffer size doesn't match the requested range"
+        );
+        unsafe {
+            let attr_info = &info.inner as *const _ as *mut HAPI_AttributeInfo;
+            raw::_get(
+                node.session.ptr(),
+                node.handle.0,
+                part,
+                name.as_ptr(),
+                attr_info,
+                -1,
+                buffer.as_mut_ptr(),
+                start,
+                len,
+            )
+            .check_err(&node.session, || stringify!(Calling _get))
+        }
+    }
     fn set(
         name: &CStr,
         node: &HoudiniNode,
@@ -172,6 +242,31 @@ impl AttribAccess for _val_type {
             .check_err(&node.session, || stringify!(Calling _set))
         }
     }
+    fn set_unique(
+        name: &CStr,
+        node: &HoudiniNode,
+        info: &AttributeInfo,
+        part: i32,
+        data: &[_val_type],
+        start: i32,
+        len: i32,
+    ) -> Result<()> {
+        debug_assert!(node.is_valid()?);
+        unsafe {
+            raw::_set_unique(
+                node.session.ptr(),
+                node.handle.0,
+                part,
+                name.as_ptr(),
+                &info.inner,
+                data.as_ptr(),
+                data.len() as i32,
+                start,
+                len,
+            )
+            .check_err(&node.session, || stringify!(Calling _set_unique))
+        }
+    }
     fn get_array(
         name: &CStr,
         node: &HoudiniNode,
@@ -307,6 +402,84 @@ pub(crate) fn _rust_fn(
     }
 }
 
+pub(crate) fn get_attribute_string_handles(
+    node: &HoudiniNode,
+    part_id: i32,
+    name: &CStr,
+    attr_info: &HAPI_AttributeInfo,
+) -> Result<Vec<StringHandle>> {
+    debug_assert!(node.is_valid()?);
+    let mut handles = vec![StringHandle(0); attr_info.count as usize];
+    unsafe {
+        raw::HAPI_GetAttributeStringData(
+            node.session.ptr(),
+            node.handle.0,
+            part_id,
+            name.as_ptr(),
+            attr_info as *const _ as *mut HAPI_AttributeInfo,
+            handles.as_mut_ptr() as *mut HAPI_StringHandle,
+            0,
+            handles.len() as i32,
+        )
+        .check_err(&node.session, || "Calling HAPI_GetAttributeStringData")?;
+    }
+    Ok(handles)
+}
+
+pub(crate) fn set_attribute_indexed_string_data(
+    node: &HoudiniNode,
+    part_id: i32,
+    name: &CStr,
+    attr_info: &HAPI_AttributeInfo,
+    strings: &mut [*const i8],
+    indices: &[i32],
+) -> Result<()> {
+    debug_assert!(node.is_valid()?);
+    unsafe {
+        raw::HAPI_SetAttributeIndexedStringData(
+            node.session.ptr(),
+            node.handle.0,
+            part_id,
+            name.as_ptr(),
+            attr_info as *const _,
+            strings.as_mut_ptr(),
+            strings.len() as i32,
+            indices.as_ptr(),
+            0,
+            indices.len() as i32,
+        )
+        .check_err(&node.session, || {
+            "Calling HAPI_SetAttributeIndexedStringData"
+        })
+    }
+}
+
+pub(crate) fn set_attribute_string_unique_data(
+    node: &HoudiniNode,
+    part_id: i32,
+    name: &CStr,
+    attr_info: &HAPI_AttributeInfo,
+    value: &CStr,
+) -> Result<()> {
+    debug_assert!(node.is_valid()?);
+    unsafe {
+        raw::HAPI_SetAttributeStringUniqueData(
+            node.session.ptr(),
+            node.handle.0,
+            part_id,
+            name.as_ptr(),
+            attr_info as *const _,
+            value.as_ptr(),
+            1,
+            0,
+            attr_info.count,
+        )
+        .check_err(&node.session, || {
+            "Calling HAPI_SetAttributeStringUniqueData"
+        })
+    }
+}
+
 #[duplicate_item(
 [
 _rust_fn [get_attribute_string_array_data]
@@ -0,0 +1,165 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use super::{AttribAccess, AttributeInfo, NumericAttr};
+use crate::errors::{HapiError, Result};
+
+// Split `count` elements into (start, length) ranges of at most `size` elements.
+fn chunk_ranges(count: i32, size: i32) -> impl Iterator<Item = (i32, i32)> {
+    let size = size.max(1);
+    (0..count)
+        .step_by(size as usize)
+        .map(move |start| (start, size.min(count - start)))
+}
+
+impl<T: AttribAccess> NumericAttr<T> {
+    /// Read `length` elements starting at element `start`. The result holds
+    /// `length * tuple_size` values.
+    pub fn get_range(&self, part_id: i32, start: i32, length: i32) -> Result<Vec<T>>
+    where
+        T: Copy + Default,
+    {
+        self.check_range(part_id, start, length)?;
+        let mut buffer = vec![T::default(); length as usize * self.0.info.tuple_size() as usize];
+        self.read_slice(part_id, start, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Read elements starting at element `start` into the buffer. The number of elements read
+    /// is the buffer length divided by the tuple size.
+    pub fn read_range_into(&self, part_id: i32, start: i32, buffer: &mut [T]) -> Result<()> {
+        debug_assert_eq!(self.0.info.storage(), T::storage());
+        let tuple_size = self.0.info.tuple_size() as usize;
+        if tuple_size == 0 || !buffer.len().is_multiple_of(tuple_size) {
+            return Err(HapiError::internal(format!(
+                "Buffer length {} is not a multiple of the tuple size {}",
+                buffer.len(),
+                tuple_size
+            )));
+        }
+        let length = i32::try_from(buffer.len() / tuple_size)
+            .map_err(|_| HapiError::internal("Buffer is too large"))?;
+        self.check_range(part_id, start, length)?;
+        self.read_slice(part_id, start, buffer)
+    }
+
+    // Fails if the elements are not all in the attribute of the part.
+    fn check_range(&self, part_id: i32, start: i32, length: i32) -> Result<()> {
+        let info = AttributeInfo::new(&self.0.node, part_id, self.0.info.owner(), &self.0.name)?;
+        let count = info.count();
+        if start < 0
+            || length < 0
+            || !matches!(start.checked_add(length), Some(end) if end <= count)
+        {
+            return Err(HapiError::internal(format!(
+                "Range of {length} elements at {start} is out of bounds for {count} elements"
+            )));
+        }
+        Ok(())
+    }
+
+    // Read a range without checks, the length is the buffer length divided by the tuple size.
+    fn read_slice(&self, part_id: i32, start: i32, buffer: &mut [T]) -> Result<()> {
+        let tuple_size = self.0.info.tuple_size().max(1) as usize;
+        let length = (buffer.len() / tuple_size) as i32;
+        T::get_slice(
+            &self.0.name,
+            &self.0.node,
+            &self.0.info,
+            part_id,
+            buffer,
+            start,
+            length,
+        )
+    }
+
+    /// Iterate over the attribute in chunks of at most `chunk_size` elements, so only one
+    /// chunk is held in memory at a time. Each item is the first element index and the values.
+    pub fn chunks(&self, part_id: i32, chunk_size: usize) -> Result<AttributeChunks<'_, T>> {
+        let info = AttributeInfo::new(&self.0.node, part_id, self.0.info.owner(), &self.0.name)?;
+        Ok(AttributeChunks {
+            attr: self,
+            part_id,
+            tuple_size: info.tuple_size(),
+            ranges: Box::new(chunk_ranges(
+                info.count(),
+                chunk_size.min(i32::MAX as usize) as i32,
+            )),
+        })
+    }
+}
+
+/// Iterator returned by [`NumericAttr::chunks`].
+pub struct AttributeChunks<'a, T: AttribAccess> {
+    attr: &'a NumericAttr<T>,
+    part_id: i32,
+    tuple_size: i32,
+    ranges: Box<dyn Iterator<Item = (i32, i32)>>,
+}
+
+impl<T: AttribAccess + Copy + Default> Iterator for AttributeChunks<'_, T> {
+    type Item = Result<(usize, Vec<T>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (start, length) = self.ranges.next()?;
+        let mut buffer = vec![T::default(); (length * self.tuple_size) as usize];
+        Some(
+            self.attr
+                .read_slice(self.part_id, start, &mut buffer)
+                .map(|_| (start as usize, buffer)),
+        )
+    }
+}
+
+/// Read several attributes or parts on up to `max_threads` threads. Each request is an
+/// attribute and the part to read it from, results are returned in the same order.
+///
+/// Calls into a session which is not [threaded](crate::session::SessionOptionsBuilder::threaded)
+/// are serialized with the session lock, so to read truly in parallel use a threaded session,
+/// or spread the requests over a pool of sessions that cooked the same asset.
+pub fn read_parallel<T>(
+    requests: &[(&NumericAttr<T>, i32)],
+    max_threads: usize,
+) -> Result<Vec<Vec<T>>>
+where
+    T: AttribAccess + Send + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<Vec<T>>>>> =
+        Mutex::new((0..requests.len()).map(|_| None).collect());
+    let workers = max_threads.clamp(1, requests.len().max(1));
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some((attr, part_id)) = requests.get(index) else {
+                    break;
+                };
+                let session = &attr.0.node.session;
+                let _lock = (!session.inner.options.threaded).then(|| session.lock());
+                let mut buffer = vec![];
+                let result = attr.read_into(*part_id, &mut buffer).map(|_| buffer);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("all requests processed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_ranges_cover_count() {
+        let ranges: Vec<_> = chunk_ranges(10, 4).collect();
+        assert_eq!(ranges, [(0, 4), (4, 4), (8, 2)]);
+        assert_eq!(chunk_ranges(0, 4).count(), 0);
+        assert_eq!(chunk_ranges(3, 0).count(), 3);
+    }
+}
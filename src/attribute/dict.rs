@@ -0,0 +1,104 @@
+//! Typed access to dictionary attributes with `serde_json`
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use super::{DictionaryArrayAttr, DictionaryAttr};
+use crate::errors::{HapiError, Result};
+
+fn parse<T: DeserializeOwned>(index: usize, text: &str) -> Result<T> {
+    serde_json::from_str(text).map_err(|e| {
+        HapiError::internal(format!("Could not parse dictionary element {index}: {e}"))
+    })
+}
+
+// Houdini dictionaries must be JSON objects, validate before sending.
+fn to_json<T: Serialize>(index: usize, value: &T) -> Result<String> {
+    let error =
+        |msg: String| HapiError::internal(format!("Invalid dictionary element {index}: {msg}"));
+    match serde_json::to_value(value).map_err(|e| error(e.to_string()))? {
+        value @ Value::Object(_) => Ok(value.to_string()),
+        other => Err(error(format!("expected a JSON object, got {other}"))),
+    }
+}
+
+impl DictionaryAttr {
+    /// Get dictionary values as [`serde_json::Value`].
+    pub fn get_values(&self, part_id: i32) -> Result<Vec<Value>> {
+        self.get_as(part_id)
+    }
+
+    /// Get dictionary values deserialized into `T`.
+    pub fn get_as<T: DeserializeOwned>(&self, part_id: i32) -> Result<Vec<T>> {
+        self.get(part_id)?
+            .iter_str()
+            .enumerate()
+            .map(|(i, text)| parse(i, text))
+            .collect()
+    }
+
+    /// Set dictionary values from any type that serializes to a JSON object.
+    pub fn set_values<T: Serialize>(&self, part_id: i32, values: &[T]) -> Result<()> {
+        let values = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| to_json(i, v))
+            .collect::<Result<Vec<_>>>()?;
+        self.set(part_id, &values)
+    }
+}
+
+impl DictionaryArrayAttr {
+    /// Get dictionary arrays as [`serde_json::Value`].
+    pub fn get_values(&self, part_id: i32) -> Result<Vec<Vec<Value>>> {
+        self.get_as(part_id)
+    }
+
+    /// Get dictionary arrays deserialized into `T`.
+    pub fn get_as<T: DeserializeOwned>(&self, part_id: i32) -> Result<Vec<Vec<T>>> {
+        let mut index = 0;
+        let mut out = vec![];
+        for array in self.get(part_id)?.iter() {
+            let array = array?
+                .iter_str()
+                .map(|text| {
+                    index += 1;
+                    parse(index - 1, text)
+                })
+                .collect::<Result<Vec<T>>>()?;
+            out.push(array);
+        }
+        Ok(out)
+    }
+
+    /// Set dictionary arrays from any type that serializes to a JSON object.
+    /// Element indices in errors refer to the flattened arrays.
+    pub fn set_values<T: Serialize>(&self, values: &[Vec<T>]) -> Result<()> {
+        let sizes: Vec<i32> = values.iter().map(|a| a.len() as i32).collect();
+        let values = values
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, v)| to_json(i, v))
+            .collect::<Result<Vec<_>>>()?;
+        self.set(&values, &sizes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dictionary_validation() {
+        let value = serde_json::json!({"a": 1});
+        assert_eq!(to_json(0, &value).unwrap(), r#"{"a":1}"#);
+        let err = to_json(3, &[1, 2]).unwrap_err().to_string();
+        assert!(err.contains("element 3"));
+        let parsed: Value = parse(0, r#"{"b": [1, 2]}"#).unwrap();
+        assert_eq!(parsed["b"][1], 2);
+        assert!(parse::<Value>(5, "{oops")
+            .unwrap_err()
+            .to_string()
+            .contains("element 5"));
+    }
+}
@@ -20,16 +20,23 @@
 //! ```
 mod array;
 mod bindings;
+mod chunks;
+#[cfg(feature = "serde_json")]
+mod dict;
+mod tuple;
 
-use crate::errors::Result;
+use crate::errors::{HapiError, Result};
 pub use crate::ffi::enums::StorageType;
 pub use crate::ffi::AttributeInfo;
 use crate::node::HoudiniNode;
-use crate::stringhandle::StringArray;
+use crate::stringhandle::{get_string_array, StringArray};
 pub use array::*;
 pub use bindings::AttribAccess;
+pub use chunks::{read_parallel, AttributeChunks};
+pub use tuple::AttribTuple;
 use std::any::Any;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
 
@@ -142,6 +149,27 @@ impl<T: AttribAccess> NumericAttr<T> {
             self.0.info.count(),
         )
     }
+    /// Set all elements to the same tuple value without sending `count` copies of it.
+    /// Errors if the value length doesn't match the attribute tuple size.
+    pub fn set_unique(&self, part_id: i32, value: &[T]) -> Result<()> {
+        debug_assert_eq!(self.0.info.storage(), T::storage());
+        if value.len() != self.0.info.tuple_size() as usize {
+            return Err(HapiError::internal(format!(
+                "Value length {} doesn't match the tuple size {}",
+                value.len(),
+                self.0.info.tuple_size()
+            )));
+        }
+        T::set_unique(
+            &self.0.name,
+            &self.0.node,
+            &self.0.info,
+            part_id,
+            value,
+            0,
+            self.0.info.count(),
+        )
+    }
 }
 
 impl StringAttr {
@@ -171,6 +199,79 @@ impl StringAttr {
             ptrs.as_mut(),
         )
     }
+    /// Read the attribute as a list of unique strings and a per-element index into it.
+    /// This is much cheaper than [`StringAttr::get`] for name or path attributes with
+    /// mostly repeating values.
+    pub fn get_indexed(&self, part_id: i32) -> Result<(Vec<String>, Vec<i32>)> {
+        debug_assert!(self.0.node.is_valid()?);
+        let handles = bindings::get_attribute_string_handles(
+            &self.0.node,
+            part_id,
+            self.0.name.as_c_str(),
+            &self.0.info.inner,
+        )?;
+        let mut unique_handles = vec![];
+        let mut handle_index = HashMap::new();
+        let handle_indices: Vec<usize> = handles
+            .iter()
+            .map(|h| {
+                *handle_index.entry(h.0).or_insert_with(|| {
+                    unique_handles.push(*h);
+                    unique_handles.len() - 1
+                })
+            })
+            .collect();
+        // Different handles can still resolve to the same string
+        let mut strings = vec![];
+        let mut string_index = HashMap::new();
+        let remap: Vec<i32> = get_string_array(&unique_handles, &self.0.node.session)?
+            .into_iter()
+            .map(|s| {
+                *string_index.entry(s.clone()).or_insert_with(|| {
+                    strings.push(s);
+                    strings.len() as i32 - 1
+                })
+            })
+            .collect();
+        let indices = handle_indices.into_iter().map(|i| remap[i]).collect();
+        Ok((strings, indices))
+    }
+    /// Set values from a list of unique strings and a per-element index into it.
+    pub fn set_indexed(&self, part_id: i32, strings: &[&str], indices: &[i32]) -> Result<()> {
+        debug_assert!(self.0.node.is_valid()?);
+        if let Some(bad) = indices
+            .iter()
+            .find(|&&i| i < 0 || i as usize >= strings.len())
+        {
+            return Err(HapiError::internal(format!(
+                "String index {bad} out of range for {} strings",
+                strings.len()
+            )));
+        }
+        let cstr: std::result::Result<Vec<CString>, std::ffi::NulError> =
+            strings.iter().map(|s| CString::new(*s)).collect();
+        let cstr = cstr?;
+        let mut ptrs: Vec<*const i8> = cstr.iter().map(|cs| cs.as_ptr()).collect();
+        bindings::set_attribute_indexed_string_data(
+            &self.0.node,
+            part_id,
+            self.0.name.as_c_str(),
+            &self.0.info.inner,
+            ptrs.as_mut(),
+            indices,
+        )
+    }
+    /// Set all elements to the same string.
+    pub fn set_unique(&self, part_id: i32, value: &str) -> Result<()> {
+        let value = CString::new(value)?;
+        bindings::set_attribute_string_unique_data(
+            &self.0.node,
+            part_id,
+            self.0.name.as_c_str(),
+            &self.0.info.inner,
+            &value,
+        )
+    }
 }
 
 impl StringArrayAttr {
@@ -0,0 +1,161 @@
+use super::{AttribAccess, AttributeInfo, NumericAttr};
+use crate::errors::{HapiError, Result};
+
+/// A fixed size tuple of numeric attribute values, such as `[f32; 3]` for positions.
+///
+/// # Safety
+/// Like `bytemuck::Pod`, implementors must be plain data made of exactly `SIZE` values of
+/// `Component` without padding, for which any bit pattern (including all zeroes) is valid.
+/// A `#[repr(C)]` struct like `struct Vec3 { x: f32, y: f32, z: f32 }` qualifies.
+pub unsafe trait AttribTuple: Copy + 'static {
+    type Component: AttribAccess;
+    const SIZE: usize;
+}
+
+// SAFETY: AttribAccess is sealed and only implemented for primitive numbers
+unsafe impl<T: AttribAccess + Copy, const N: usize> AttribTuple for [T; N] {
+    type Component = T;
+    const SIZE: usize = N;
+}
+
+fn components_mut<U: AttribTuple>(tuples: &mut [U]) -> &mut [U::Component] {
+    // SAFETY: AttribTuple guarantees U is SIZE components without padding
+    unsafe {
+        std::slice::from_raw_parts_mut(
+            tuples.as_mut_ptr() as *mut U::Component,
+            tuples.len() * U::SIZE,
+        )
+    }
+}
+
+fn components<U: AttribTuple>(tuples: &[U]) -> &[U::Component] {
+    // SAFETY: Same as above
+    unsafe {
+        std::slice::from_raw_parts(
+            tuples.as_ptr() as *const U::Component,
+            tuples.len() * U::SIZE,
+        )
+    }
+}
+
+fn check_tuple_size<U: AttribTuple>(info: &AttributeInfo) -> Result<()> {
+    if info.tuple_size() as usize != U::SIZE {
+        return Err(HapiError::internal(format!(
+            "Attribute tuple size is {}, requested tuples of {}",
+            info.tuple_size(),
+            U::SIZE
+        )));
+    }
+    Ok(())
+}
+
+impl<T: AttribAccess> NumericAttr<T> {
+    /// Get attribute values as tuples, e.g `attr.get_tuples::<[f32; 3]>(0)` for positions.
+    /// Errors if the tuple size doesn't match [`AttributeInfo::tuple_size`].
+    pub fn get_tuples<U: AttribTuple<Component = T>>(&self, part_id: i32) -> Result<Vec<U>> {
+        let info = AttributeInfo::new(&self.0.node, part_id, self.0.info.owner(), &self.0.name)?;
+        check_tuple_size::<U>(&info)?;
+        // SAFETY: AttribTuple guarantees all zeroes is a valid value
+        let mut buffer = vec![unsafe { std::mem::zeroed::<U>() }; info.count() as usize];
+        T::get_slice(
+            &self.0.name,
+            &self.0.node,
+            &info,
+            part_id,
+            components_mut(&mut buffer),
+            0,
+            info.count(),
+        )?;
+        Ok(buffer)
+    }
+
+    /// Read attribute values directly into a caller provided buffer, for example a mapped
+    /// GPU staging buffer, without intermediate allocations.
+    /// The buffer must hold at least [`AttributeInfo::count`] tuples. Returns the number of
+    /// tuples written.
+    pub fn read_into_slice<U: AttribTuple<Component = T>>(
+        &self,
+        part_id: i32,
+        buffer: &mut [U],
+    ) -> Result<usize> {
+        let info = AttributeInfo::new(&self.0.node, part_id, self.0.info.owner(), &self.0.name)?;
+        check_tuple_size::<U>(&info)?;
+        let count = info.count() as usize;
+        if buffer.len() < count {
+            return Err(HapiError::internal(format!(
+                "Buffer of {} tuples is too small for attribute with {} elements",
+                buffer.len(),
+                count
+            )));
+        }
+        T::get_slice(
+            &self.0.name,
+            &self.0.node,
+            &info,
+            part_id,
+            components_mut(&mut buffer[..count]),
+            0,
+            info.count(),
+        )?;
+        Ok(count)
+    }
+
+    /// Set attribute values from tuples.
+    pub fn set_tuples<U: AttribTuple<Component = T>>(
+        &self,
+        part_id: i32,
+        values: &[U],
+    ) -> Result<()> {
+        check_tuple_size::<U>(&self.0.info)?;
+        T::set(
+            &self.0.name,
+            &self.0.node,
+            &self.0.info,
+            part_id,
+            components(values),
+            0,
+            values.len() as i32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Vec3 {
+        x: f32,
+        y: f32,
+        z: f32,
+    }
+
+    unsafe impl AttribTuple for Vec3 {
+        type Component = f32;
+        const SIZE: usize = 3;
+    }
+
+    #[test]
+    fn tuple_components() {
+        let mut tuples = [[1u8, 2], [3, 4]];
+        assert_eq!(components(&tuples), &[1, 2, 3, 4]);
+        components_mut(&mut tuples)[3] = 5;
+        assert_eq!(tuples[1], [3, 5]);
+
+        let mut points = [Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }; 2];
+        components_mut(&mut points).copy_from_slice(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(
+            points[1],
+            Vec3 {
+                x: 4.0,
+                y: 5.0,
+                z: 6.0
+            }
+        );
+    }
+}
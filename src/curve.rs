@@ -0,0 +1,589 @@
+//! Typed model for linear, Bezier and NURBS curve parts
+//!
+//! A curve part stores curve types, vertex counts, orders and knots separately from the
+//! point positions. [`Curves`] holds all of it per curve, [`Geometry::read_curves`] reads a
+//! curve part and [`Geometry::write_curves`] validates and uploads one in the right order.
+//!
+//! Curves can be evaluated in Rust for previews, see [`Curve::sample`].
+//!
+//! ```ignore
+//! use hapi_rs::curve::{Curve, Curves};
+//! let curve = Curve::nurbs(4, vec![[-4.0, 0.0, 4.0], [-4.0, 0.0, -4.0], [4.0, 0.0, -4.0], [4.0, 0.0, 4.0]])
+//!     .with_knots(vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0]);
+//! let geo = session.create_input_node("curve")?;
+//! geo.write_curves(&Curves::new(vec![curve]))?;
+//! ```
+use crate::errors::{HapiError, Result};
+use crate::ffi::enums::{AttributeOwner, CurveOrders, CurveType, PartType};
+use crate::ffi::{CurveInfo, PartInfo};
+use crate::geometry::Geometry;
+use crate::mesh::{AttributeValues, MeshAttribute};
+
+/// A single curve.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    pub kind: CurveType,
+    /// Degree + 1. Linear curves have order 2.
+    pub order: i32,
+    /// Closed curve.
+    pub periodic: bool,
+    /// Control vertex positions.
+    pub points: Vec<[f32; 3]>,
+    /// Empty, or `points.len() + order` knots.
+    pub knots: Vec<f32>,
+    /// Point, vertex and primitive attributes of this curve. Point and vertex attributes have
+    /// a tuple per control vertex, primitive attributes a single tuple.
+    pub attributes: Vec<MeshAttribute>,
+}
+
+impl Curve {
+    pub fn new(kind: CurveType, order: i32, points: Vec<[f32; 3]>) -> Self {
+        Curve {
+            kind,
+            order,
+            periodic: false,
+            points,
+            knots: vec![],
+            attributes: vec![],
+        }
+    }
+
+    pub fn linear(points: Vec<[f32; 3]>) -> Self {
+        Self::new(CurveType::Linear, 2, points)
+    }
+
+    pub fn bezier(order: i32, points: Vec<[f32; 3]>) -> Self {
+        Self::new(CurveType::Bezier, order, points)
+    }
+
+    pub fn nurbs(order: i32, points: Vec<[f32; 3]>) -> Self {
+        Self::new(CurveType::Nurbs, order, points)
+    }
+
+    pub fn with_periodic(mut self, periodic: bool) -> Self {
+        self.periodic = periodic;
+        self
+    }
+
+    pub fn with_knots(mut self, knots: Vec<f32>) -> Self {
+        self.knots = knots;
+        self
+    }
+
+    pub fn with_attribute(
+        mut self,
+        name: impl Into<String>,
+        owner: AttributeOwner,
+        tuple_size: i32,
+        values: AttributeValues,
+    ) -> Self {
+        self.attributes.push(MeshAttribute {
+            name: name.into(),
+            owner,
+            tuple_size,
+            values,
+        });
+        self
+    }
+
+    fn degree(&self) -> usize {
+        (self.order - 1).max(1) as usize
+    }
+
+    /// Number of elements of the given owner class on this curve.
+    pub fn element_count(&self, owner: AttributeOwner) -> usize {
+        match owner {
+            AttributeOwner::Point | AttributeOwner::Vertex => self.points.len(),
+            AttributeOwner::Prim => 1,
+            _ => 0,
+        }
+    }
+
+    /// Check order, point count, knots and attribute sizes.
+    pub fn validate(&self) -> Result<()> {
+        let err = |msg: String| Err(HapiError::internal(msg));
+        let n = self.points.len();
+        match self.kind {
+            CurveType::Linear if self.order != 2 => {
+                return err(format!("Linear curve order must be 2, got {}", self.order))
+            }
+            CurveType::Linear | CurveType::Nurbs | CurveType::Bezier => {}
+            other => return err(format!("Invalid curve type: {other:?}")),
+        }
+        if self.order < 2 {
+            return err(format!(
+                "Curve order must be at least 2, got {}",
+                self.order
+            ));
+        }
+        if n < self.order as usize {
+            return err(format!(
+                "Curve of order {} needs at least {} points, got {n}",
+                self.order, self.order
+            ));
+        }
+        if self.kind == CurveType::Bezier {
+            let segments = if self.periodic { n } else { n - 1 };
+            if segments % self.degree() != 0 {
+                return err(format!(
+                    "Bezier curve of order {} can't have {n} points",
+                    self.order
+                ));
+            }
+        }
+        if !self.knots.is_empty() {
+            let expected = n + self.order as usize;
+            if self.knots.len() != expected {
+                return err(format!(
+                    "Curve has {} knots, expected {expected}",
+                    self.knots.len()
+                ));
+            }
+            if self.knots.windows(2).any(|w| w[1] < w[0]) {
+                return err("Curve knots must be non-decreasing".to_string());
+            }
+        }
+        for attr in &self.attributes {
+            let expected = self.element_count(attr.owner) * attr.tuple_size.max(0) as usize;
+            if attr.tuple_size < 1 || attr.values.len() != expected {
+                return err(format!(
+                    "Curve attribute \"{}\" has {} values, expected {expected}",
+                    attr.name,
+                    attr.values.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate the curve at `u` in `[0, 1]` over its whole domain.
+    /// Returns `None` if the curve is invalid. NURBS curves are evaluated without weights,
+    /// periodic NURBS curves use uniform knots.
+    pub fn evaluate(&self, u: f32) -> Option<[f32; 3]> {
+        self.validate().ok()?;
+        Some(self.eval(u.clamp(0.0, 1.0)))
+    }
+
+    /// Evaluate `count` evenly spaced positions from the start to the end of the curve.
+    pub fn sample(&self, count: usize) -> Option<Vec<[f32; 3]>> {
+        self.validate().ok()?;
+        let step = 1.0 / (count.max(2) - 1) as f32;
+        Some((0..count).map(|i| self.eval(i as f32 * step)).collect())
+    }
+
+    // Control points with the start repeated for closed curves.
+    fn wrapped_points(&self, extra: usize) -> Vec<[f32; 3]> {
+        let mut points = self.points.clone();
+        if self.periodic {
+            points.extend(self.points.iter().cycle().take(extra));
+        }
+        points
+    }
+
+    fn eval(&self, u: f32) -> [f32; 3] {
+        match self.kind {
+            CurveType::Bezier => {
+                let points = self.wrapped_points(1);
+                let degree = self.degree();
+                let segments = (points.len() - 1) / degree;
+                let (i, t) = segment(u, segments);
+                de_casteljau(&points[i * degree..=(i + 1) * degree], t)
+            }
+            CurveType::Nurbs => {
+                let degree = self.degree();
+                let points = self.wrapped_points(degree);
+                let knots = if self.periodic || self.knots.is_empty() {
+                    uniform_knots(points.len(), degree, !self.periodic)
+                } else {
+                    self.knots.clone()
+                };
+                de_boor(&points, &knots, degree, u)
+            }
+            _ => {
+                let points = self.wrapped_points(1);
+                let (i, t) = segment(u, points.len() - 1);
+                lerp(points[i], points[i + 1], t)
+            }
+        }
+    }
+}
+
+fn lerp(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+// Split `u` in [0, 1] into a segment index and the parameter within it.
+fn segment(u: f32, segments: usize) -> (usize, f32) {
+    let s = u * segments as f32;
+    let i = (s.floor() as usize).min(segments - 1);
+    (i, s - i as f32)
+}
+
+fn de_casteljau(points: &[[f32; 3]], t: f32) -> [f32; 3] {
+    let mut points = points.to_vec();
+    for n in (1..points.len()).rev() {
+        for i in 0..n {
+            points[i] = lerp(points[i], points[i + 1], t);
+        }
+    }
+    points[0]
+}
+
+// Uniform knots, clamped to the end points for open curves.
+fn uniform_knots(count: usize, degree: usize, clamped: bool) -> Vec<f32> {
+    let total = count + degree + 1;
+    if !clamped {
+        return (0..total).map(|i| i as f32).collect();
+    }
+    let spans = (count - degree) as f32;
+    (0..total)
+        .map(|i| (i.saturating_sub(degree) as f32).min(spans) / spans)
+        .collect()
+}
+
+fn de_boor(points: &[[f32; 3]], knots: &[f32], degree: usize, u: f32) -> [f32; 3] {
+    let n = points.len();
+    let (lo, hi) = (knots[degree], knots[n]);
+    let t = lo + (hi - lo) * u;
+    let mut k = degree;
+    while k + 1 < n && knots[k + 1] <= t {
+        k += 1;
+    }
+    let mut d: Vec<[f32; 3]> = points[k - degree..=k].to_vec();
+    for r in 1..=degree {
+        for j in (r..=degree).rev() {
+            let left = knots[j + k - degree];
+            let right = knots[j + 1 + k - r];
+            let alpha = if right > left {
+                (t - left) / (right - left)
+            } else {
+                0.0
+            };
+            d[j] = lerp(d[j - 1], d[j], alpha);
+        }
+    }
+    d[degree]
+}
+
+/// All curves of a curve part. Houdini stores one curve type, periodicity and knot
+/// presence per part, orders may vary.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Curves {
+    pub curves: Vec<Curve>,
+}
+
+impl Curves {
+    pub fn new(curves: Vec<Curve>) -> Self {
+        Curves { curves }
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.curves.iter().map(|c| c.points.len()).sum()
+    }
+
+    /// Validate each curve and check that they can share a part.
+    pub fn validate(&self) -> Result<()> {
+        let Some(first) = self.curves.first() else {
+            return Err(HapiError::internal("No curves"));
+        };
+        for (i, curve) in self.curves.iter().enumerate() {
+            curve
+                .validate()
+                .map_err(|e| HapiError::internal(format!("Curve {i}: {e}")))?;
+            let err = |what: &str| {
+                Err(HapiError::internal(format!(
+                    "Curve {i}: {what} differs from the first curve"
+                )))
+            };
+            if curve.kind != first.kind {
+                return err("type");
+            }
+            if curve.periodic != first.periodic {
+                return err("periodic flag");
+            }
+            if curve.knots.is_empty() != first.knots.is_empty() {
+                return err("knot presence");
+            }
+            if curve.attributes.len() != first.attributes.len()
+                || curve
+                    .attributes
+                    .iter()
+                    .zip(&first.attributes)
+                    .any(|(a, b)| {
+                        a.name != b.name
+                            || a.owner != b.owner
+                            || a.tuple_size != b.tuple_size
+                            || a.values.storage() != b.values.storage()
+                    })
+            {
+                return err("attribute layout");
+            }
+        }
+        Ok(())
+    }
+
+    // Attribute values of all curves joined in curve order.
+    fn joined_attributes(&self) -> Vec<MeshAttribute> {
+        let mut joined = self.curves[0].attributes.clone();
+        for curve in &self.curves[1..] {
+            for (dst, src) in joined.iter_mut().zip(&curve.attributes) {
+                extend_values(&mut dst.values, &src.values);
+            }
+        }
+        joined
+    }
+}
+
+fn extend_values(dst: &mut AttributeValues, src: &AttributeValues) {
+    match (dst, src) {
+        (AttributeValues::Int(d), AttributeValues::Int(s)) => d.extend_from_slice(s),
+        (AttributeValues::Int64(d), AttributeValues::Int64(s)) => d.extend_from_slice(s),
+        (AttributeValues::Float(d), AttributeValues::Float(s)) => d.extend_from_slice(s),
+        (AttributeValues::Double(d), AttributeValues::Double(s)) => d.extend_from_slice(s),
+        (AttributeValues::String(d), AttributeValues::String(s)) => d.extend_from_slice(s),
+        _ => unreachable!("attribute storage is validated"),
+    }
+}
+
+impl Geometry {
+    pub fn set_curve_orders(&self, part_id: i32, orders: &[i32]) -> Result<()> {
+        debug_assert!(self.node.is_valid()?);
+        crate::ffi::set_curve_orders(&self.node, part_id, orders)
+    }
+
+    /// Read a curve part into [`Curves`], including point, vertex and primitive attributes.
+    /// Curve parts have one point per vertex.
+    pub fn read_curves(&self, part_id: i32) -> Result<Curves> {
+        debug_assert!(
+            self.node.get_info()?.total_cook_count() > 0,
+            "Node not cooked"
+        );
+        let part = self
+            .part_info(part_id)?
+            .ok_or_else(|| HapiError::internal(format!("No part {part_id}")))?;
+        if part.part_type() != PartType::Curve {
+            return Err(HapiError::internal(format!(
+                "Part {part_id} is not a curve: {:?}",
+                part.part_type()
+            )));
+        }
+        let info = self.curve_info(part_id)?;
+        let count = info.curve_count();
+        let counts = self.curve_counts(part_id, 0, count)?;
+        let orders = match info.order() {
+            o if o == CurveOrders::Varying as i32 || o == CurveOrders::Invalid as i32 => {
+                self.curve_orders(part_id, 0, count)?
+            }
+            order => vec![order; count as usize],
+        };
+        let knots = if info.has_knots() {
+            self.curve_knots(part_id, 0, info.knot_count())?
+        } else {
+            vec![]
+        };
+        let positions = self.get_position_attribute(part_id)?.get(part_id)?;
+        let total: i32 = counts.iter().sum();
+        if positions.len() != total as usize * 3 {
+            return Err(HapiError::internal(format!(
+                "Curve part has {} points for {total} vertices",
+                positions.len() / 3
+            )));
+        }
+        let mut attributes = vec![];
+        for owner in [
+            AttributeOwner::Point,
+            AttributeOwner::Vertex,
+            AttributeOwner::Prim,
+        ] {
+            for name in self.get_attribute_names(owner, Some(&part))?.iter_str() {
+                if owner == AttributeOwner::Point && name == "P" {
+                    continue;
+                }
+                if let Some(attr) = self.read_mesh_attribute(part_id, owner, name)? {
+                    attributes.push(attr);
+                }
+            }
+        }
+
+        let mut curves = Vec::with_capacity(count as usize);
+        let (mut point, mut knot) = (0usize, 0usize);
+        for (index, (&vertices, &order)) in counts.iter().zip(&orders).enumerate() {
+            let n = vertices as usize;
+            let mut curve =
+                Curve::new(info.curve_type(), order, vec![]).with_periodic(info.periodic());
+            curve.points = positions[point * 3..(point + n) * 3]
+                .chunks_exact(3)
+                .map(|p| [p[0], p[1], p[2]])
+                .collect();
+            if info.has_knots() {
+                let end = (knot + n + order as usize).min(knots.len());
+                curve.knots = knots[knot..end].to_vec();
+                knot = end;
+            }
+            for attr in &attributes {
+                let tuple = attr.tuple_size as usize;
+                let range = match attr.owner {
+                    AttributeOwner::Prim => index * tuple..(index + 1) * tuple,
+                    _ => point * tuple..(point + n) * tuple,
+                };
+                curve.attributes.push(MeshAttribute {
+                    values: attr.values.slice(range),
+                    ..attr.clone()
+                });
+            }
+            point += n;
+            curves.push(curve);
+        }
+        Ok(Curves { curves })
+    }
+
+    /// Upload [`Curves`] into part 0 of this (input) geometry and commit it.
+    pub fn write_curves(&self, curves: &Curves) -> Result<()> {
+        debug_assert!(self.node.is_valid()?);
+        curves.validate()?;
+        let first = &curves.curves[0];
+        let point_count = curves.point_count();
+        let part = PartInfo::default()
+            .with_part_type(PartType::Curve)
+            .with_face_count(curves.curves.len() as i32)
+            .with_vertex_count(point_count as i32)
+            .with_point_count(point_count as i32);
+        self.set_part_info(&part)?;
+        let part_id = part.part_id();
+
+        let orders: Vec<i32> = curves.curves.iter().map(|c| c.order).collect();
+        let uniform = orders.iter().all(|o| *o == first.order);
+        let knots: Vec<f32> = curves.curves.iter().flat_map(|c| c.knots.clone()).collect();
+        let info = CurveInfo::default()
+            .with_curve_type(first.kind)
+            .with_curve_count(curves.curves.len() as i32)
+            .with_vertex_count(point_count as i32)
+            .with_knot_count(knots.len() as i32)
+            .with_order(if uniform {
+                first.order
+            } else {
+                CurveOrders::Varying as i32
+            })
+            .with_periodic(first.periodic)
+            .with_has_knots(!knots.is_empty());
+        self.set_curve_info(part_id, &info)?;
+        let counts: Vec<i32> = curves
+            .curves
+            .iter()
+            .map(|c| c.points.len() as i32)
+            .collect();
+        self.set_curve_counts(part_id, &counts)?;
+        if !uniform {
+            self.set_curve_orders(part_id, &orders)?;
+        }
+        if !knots.is_empty() {
+            self.set_curve_knots(part_id, &knots)?;
+        }
+        let positions: Vec<f32> = curves
+            .curves
+            .iter()
+            .flat_map(|c| c.points.iter().flatten().copied())
+            .collect();
+        let p_info = crate::ffi::AttributeInfo::default()
+            .with_owner(AttributeOwner::Point)
+            .with_count(point_count as i32)
+            .with_tuple_size(3)
+            .with_storage(crate::ffi::enums::StorageType::Float);
+        self.add_numeric_attribute::<f32>("P", part_id, p_info)?
+            .set(part_id, &positions)?;
+        for attr in curves.joined_attributes() {
+            let count = match attr.owner {
+                AttributeOwner::Prim => curves.curves.len(),
+                _ => point_count,
+            };
+            self.add_mesh_attribute(part_id, &attr, count)?;
+        }
+        self.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+        a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    #[test]
+    fn curve_validation() {
+        let points = vec![[0.0; 3]; 4];
+        assert!(Curve::nurbs(4, points.clone()).validate().is_ok());
+        assert!(Curve::nurbs(5, points.clone()).validate().is_err());
+        assert!(Curve::nurbs(4, points.clone())
+            .with_knots(vec![0.0; 7])
+            .validate()
+            .is_err());
+        assert!(Curve::nurbs(4, points.clone())
+            .with_knots(vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.5, 1.0])
+            .validate()
+            .is_err());
+        assert!(Curve::bezier(3, points.clone()).validate().is_err());
+        assert!(Curve::bezier(4, points.clone()).validate().is_ok());
+        assert!(Curve::linear(points)
+            .with_attribute(
+                "id",
+                AttributeOwner::Prim,
+                1,
+                AttributeValues::Int(vec![1, 2])
+            )
+            .validate()
+            .is_err());
+        let curves = Curves::new(vec![
+            Curve::linear(vec![[0.0; 3]; 2]),
+            Curve::linear(vec![[0.0; 3]; 3]).with_periodic(true),
+        ]);
+        assert!(curves.validate().is_err());
+    }
+
+    #[test]
+    fn curve_evaluation() {
+        let linear = Curve::linear(vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]]);
+        assert_eq!(linear.evaluate(0.75), Some([1.0, 0.5, 0.0]));
+        let closed = linear.clone().with_periodic(true);
+        assert!(close(closed.evaluate(1.0).unwrap(), [0.0; 3]));
+
+        let points = vec![
+            [0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0],
+        ];
+        let bezier = Curve::bezier(4, points.clone());
+        assert!(close(bezier.evaluate(0.5).unwrap(), [0.5, 0.75, 0.0]));
+        // A single span clamped B-spline is the same Bezier curve
+        let nurbs = Curve::nurbs(4, points.clone());
+        let explicit = nurbs
+            .clone()
+            .with_knots(vec![0.0, 0.0, 0.0, 0.0, 2.0, 2.0, 2.0, 2.0]);
+        for u in [0.0, 0.3, 0.5, 1.0] {
+            let expected = bezier.evaluate(u).unwrap();
+            assert!(close(nurbs.evaluate(u).unwrap(), expected));
+            assert!(close(explicit.evaluate(u).unwrap(), expected));
+        }
+        let samples = nurbs.sample(5).unwrap();
+        assert_eq!(samples.len(), 5);
+        assert!(close(samples[4], [1.0, 0.0, 0.0]));
+        // Closed uniform cubic: start and end meet
+        let ring = Curve::nurbs(4, points).with_periodic(true);
+        assert!(close(
+            ring.evaluate(0.0).unwrap(),
+            ring.evaluate(1.0).unwrap()
+        ));
+        assert!(Curve::nurbs(4, vec![[0.0; 3]; 2]).sample(3).is_none());
+    }
+
+    #[test]
+    fn curve_attribute_slices() {
+        let values = AttributeValues::Float(vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(values.slice(1..3), AttributeValues::Float(vec![2.0, 3.0]));
+        let mut joined = values.clone();
+        extend_values(&mut joined, &AttributeValues::Float(vec![5.0]));
+        assert_eq!(joined.len(), 5);
+    }
+}
@@ -513,6 +513,42 @@ pub fn get_asset_info(node: &HoudiniNode) -> Result<raw::HAPI_AssetInfo> {
     }
 }
 
+pub fn get_handle_info(node: &HoudiniNode, count: i32) -> Result<Vec<raw::HAPI_HandleInfo>> {
+    unsafe {
+        let mut infos = vec![raw::HAPI_HandleInfo_Create(); count as usize];
+        raw::HAPI_GetHandleInfo(
+            node.session.ptr(),
+            node.handle.0,
+            infos.as_mut_ptr(),
+            0,
+            count,
+        )
+        .check_err(&node.session, || "Calling HAPI_GetHandleInfo")?;
+        Ok(infos)
+    }
+}
+
+pub fn get_handle_binding_info(
+    session: &Session,
+    node: NodeHandle,
+    handle_index: i32,
+    count: i32,
+) -> Result<Vec<raw::HAPI_HandleBindingInfo>> {
+    unsafe {
+        let mut infos = vec![raw::HAPI_HandleBindingInfo_Create(); count as usize];
+        raw::HAPI_GetHandleBindingInfo(
+            session.ptr(),
+            node.0,
+            handle_index,
+            infos.as_mut_ptr(),
+            0,
+            count,
+        )
+        .check_err(session, || "Calling HAPI_GetHandleBindingInfo")?;
+        Ok(infos)
+    }
+}
+
 pub fn get_asset_count(library_id: i32, session: &Session) -> Result<i32> {
     unsafe {
         let mut num_assets = uninit!();
@@ -1571,6 +1607,20 @@ pub fn get_volume_info(node: &HoudiniNode, id: i32) -> Result<raw::HAPI_VolumeIn
     }
 }
 
+pub fn get_volume_visual_info(node: &HoudiniNode, part: i32) -> Result<raw::HAPI_VolumeVisualInfo> {
+    unsafe {
+        let mut info = uninit!();
+        super::raw::HAPI_GetVolumeVisualInfo(
+            node.session.ptr(),
+            node.handle.0,
+            part,
+            info.as_mut_ptr(),
+        )
+        .check_err(&node.session, || "Calling HAPI_GetVolumeVisualInfo")?;
+        Ok(info.assume_init())
+    }
+}
+
 pub fn set_volume_info(node: &HoudiniNode, part: i32, info: &raw::HAPI_VolumeInfo) -> Result<()> {
     unsafe {
         super::raw::HAPI_SetVolumeInfo(node.session.ptr(), node.handle.0, part, info)
@@ -1798,6 +1848,40 @@ pub fn get_volume_bounds(node: &HoudiniNode, id: i32) -> Result<crate::volume::V
     }
 }
 
+pub fn get_heightfield_data(node: &HoudiniNode, part: i32, values: &mut [f32]) -> Result<()> {
+    unsafe {
+        raw::HAPI_GetHeightFieldData(
+            node.session.ptr(),
+            node.handle.0,
+            part,
+            values.as_mut_ptr(),
+            0,
+            values.len() as i32,
+        )
+        .check_err(&node.session, || "Calling HAPI_GetHeightFieldData")
+    }
+}
+
+pub fn set_heightfield_data(
+    node: &HoudiniNode,
+    part: i32,
+    name: &CStr,
+    values: &[f32],
+) -> Result<()> {
+    unsafe {
+        raw::HAPI_SetHeightFieldData(
+            node.session.ptr(),
+            node.handle.0,
+            part,
+            name.as_ptr(),
+            values.as_ptr(),
+            0,
+            values.len() as i32,
+        )
+        .check_err(&node.session, || "Calling HAPI_SetHeightFieldData")
+    }
+}
+
 pub fn create_heightfield_input(
     node: &HoudiniNode,
     parent: Option<NodeHandle>,
@@ -2826,6 +2910,48 @@ pub fn get_group_names_on_instance_part(
     }
 }
 
+pub fn get_group_membership_on_instance_part(
+    session: &Session,
+    node: NodeHandle,
+    part_id: i32,
+    group_type: raw::GroupType,
+    group_name: &CStr,
+    length: i32,
+) -> Result<(bool, Vec<i32>)> {
+    unsafe {
+        let mut all_equal = uninit!();
+        let mut array = vec![0; length as usize];
+        raw::HAPI_GetGroupMembershipOnPackedInstancePart(
+            session.ptr(),
+            node.0,
+            part_id,
+            group_type,
+            group_name.as_ptr(),
+            all_equal.as_mut_ptr(),
+            array.as_mut_ptr(),
+            0,
+            length,
+        )
+        .check_err(session, || {
+            "Calling HAPI_GetGroupMembershipOnPackedInstancePart"
+        })?;
+        Ok((all_equal.assume_init() > 0, array))
+    }
+}
+
+pub fn get_instanced_object_ids(
+    session: &Session,
+    object: NodeHandle,
+    count: i32,
+) -> Result<Vec<NodeHandle>> {
+    unsafe {
+        let mut ids = vec![-1; count as usize];
+        raw::HAPI_GetInstancedObjectIds(session.ptr(), object.0, ids.as_mut_ptr(), 0, count)
+            .check_err(session, || "Calling HAPI_GetInstancedObjectIds")?;
+        Ok(ids.into_iter().map(NodeHandle).collect())
+    }
+}
+
 pub fn get_instanced_part_transforms(
     session: &Session,
     node: NodeHandle,
@@ -417,6 +417,57 @@ impl AssetInfo {
     get!(help_url->helpURLSH->Result<String>);
 }
 
+/// [Documentation](https://www.sidefx.com/docs/hengine/struct_h_a_p_i___handle_info.html)
+#[derive(Debug, Clone)]
+pub struct HandleInfo {
+    pub(crate) inner: HAPI_HandleInfo,
+    pub(crate) node: NodeHandle,
+    pub(crate) index: i32,
+    pub session: DebugIgnore<Session>,
+}
+
+impl HandleInfo {
+    get!(name->nameSH->Result<String>);
+    get!(type_name->typeNameSH->Result<String>);
+    get!(bindings_count->bindingsCount->i32);
+
+    /// Index of this handle on the asset node.
+    pub fn index(&self) -> i32 {
+        self.index
+    }
+
+    /// Get the mapping of the handle parameters to the asset parameters.
+    pub fn bindings(&self) -> Result<Vec<HandleBindingInfo>> {
+        let infos = crate::ffi::get_handle_binding_info(
+            &self.session,
+            self.node,
+            self.index,
+            self.bindings_count(),
+        )?;
+        Ok(infos
+            .into_iter()
+            .map(|inner| HandleBindingInfo {
+                inner,
+                session: self.session.clone(),
+            })
+            .collect())
+    }
+}
+
+/// [Documentation](https://www.sidefx.com/docs/hengine/struct_h_a_p_i___handle_binding_info.html)
+#[derive(Debug, Clone)]
+pub struct HandleBindingInfo {
+    pub(crate) inner: HAPI_HandleBindingInfo,
+    pub session: DebugIgnore<Session>,
+}
+
+impl HandleBindingInfo {
+    get!(handle_parm_name->handleParmNameSH->Result<String>);
+    get!(asset_parm_name->assetParmNameSH->Result<String>);
+    get!(asset_parm_id->assetParmId->[handle: ParmHandle]);
+    get!(asset_parm_index->assetParmIndex->i32);
+}
+
 /// [Documentation](https://www.sidefx.com/docs/hengine/struct_h_a_p_i___object_info.html)
 #[derive(Debug)]
 pub struct ObjectInfo<'session> {
@@ -690,6 +741,14 @@ pub struct VolumeInfo {
     pub(crate) inner: HAPI_VolumeInfo,
 }
 
+impl Default for VolumeInfo {
+    fn default() -> Self {
+        Self {
+            inner: unsafe { HAPI_VolumeInfo_Create() },
+        }
+    }
+}
+
 wrap!(
     impl VolumeInfo => HAPI_VolumeInfo;
     [get+session] name->nameSH->[Result<String>];
@@ -709,15 +768,16 @@ wrap!(
 );
 
 impl VolumeInfo {
-    fn transform(&self) -> Transform {
+    /// Transform of the volume, the scale is half the voxel size.
+    pub fn transform(&self) -> Transform {
         Transform {
             inner: self.inner.transform,
         }
     }
-    fn set_transform(&mut self, transform: Transform) {
+    pub fn set_transform(&mut self, transform: Transform) {
         self.inner.transform = transform.inner
     }
-    fn with_transform(mut self, transform: Transform) -> Self {
+    pub fn with_transform(mut self, transform: Transform) -> Self {
         self.inner.transform = transform.inner;
         self
     }
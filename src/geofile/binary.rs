@@ -0,0 +1,405 @@
+//! Decoder for Houdini's binary JSON encoding used by `.bgeo` files
+//!
+//! The stream starts with a magic token, followed by a sequence of typed tokens. Strings can be
+//! defined once as tokens and referenced by id later, numeric arrays are usually stored as
+//! uniform arrays of a single type.
+use std::collections::HashMap;
+
+use super::json::Value;
+use crate::errors::{HapiError, Result};
+
+// Geometry files nest only a few levels deep.
+const MAX_DEPTH: usize = 64;
+
+const JID_NULL: u8 = 0x00;
+const JID_MAP_BEGIN: u8 = 0x7b;
+const JID_MAP_END: u8 = 0x7d;
+const JID_ARRAY_BEGIN: u8 = 0x5b;
+const JID_ARRAY_END: u8 = 0x5d;
+const JID_BOOL: u8 = 0x10;
+const JID_INT8: u8 = 0x11;
+const JID_INT16: u8 = 0x12;
+const JID_INT32: u8 = 0x13;
+const JID_INT64: u8 = 0x14;
+const JID_REAL16: u8 = 0x18;
+const JID_REAL32: u8 = 0x19;
+const JID_REAL64: u8 = 0x1a;
+const JID_UINT8: u8 = 0x21;
+const JID_UINT16: u8 = 0x22;
+const JID_STRING: u8 = 0x27;
+const JID_FALSE: u8 = 0x30;
+const JID_TRUE: u8 = 0x31;
+const JID_TOKENDEF: u8 = 0x2b;
+const JID_TOKENREF: u8 = 0x26;
+const JID_TOKENUNDEF: u8 = 0x2d;
+const JID_UNIFORM_ARRAY: u8 = 0x40;
+const JID_KEY_SEPARATOR: u8 = 0x3a;
+const JID_VALUE_SEPARATOR: u8 = 0x2c;
+const JID_MAGIC: u8 = 0x7f;
+
+const MAGIC: [u8; 4] = *b"NSJb";
+const MAGIC_SWAPPED: [u8; 4] = *b"bJSN";
+
+/// Returns true if the data starts with the binary JSON magic.
+pub(crate) fn is_binary(bytes: &[u8]) -> bool {
+    bytes.first() == Some(&JID_MAGIC)
+}
+
+/// Decode a binary JSON document.
+pub fn parse(bytes: &[u8]) -> Result<Value> {
+    let mut reader = Reader {
+        bytes,
+        pos: 0,
+        swap: false,
+        tokens: HashMap::new(),
+    };
+    if reader.u8()? != JID_MAGIC {
+        return Err(reader.error("missing binary JSON magic"));
+    }
+    match reader.take(4)? {
+        m if m == MAGIC => {}
+        m if m == MAGIC_SWAPPED => reader.swap = true,
+        _ => return Err(reader.error("invalid binary JSON magic")),
+    }
+    let token = reader.token()?;
+    reader.value(token)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    // Data was written on a machine with the other endianness
+    swap: bool,
+    tokens: HashMap<i64, String>,
+}
+
+// Array or map being read, a map keeps the key of its next value.
+enum Container {
+    Array(Vec<Value>),
+    Map(Vec<(String, Value)>, String),
+}
+
+impl Container {
+    fn push(&mut self, value: Value) {
+        match self {
+            Container::Array(items) => items.push(value),
+            Container::Map(items, key) => items.push((std::mem::take(key), value)),
+        }
+    }
+
+    fn finish(self) -> Value {
+        match self {
+            Container::Array(items) => Value::Array(items),
+            Container::Map(items, _) => Value::Object(items),
+        }
+    }
+}
+
+macro_rules! read_num {
+    ($name:ident, $ty:ty) => {
+        fn $name(&mut self) -> Result<$ty> {
+            const N: usize = std::mem::size_of::<$ty>();
+            let mut buf = [0; N];
+            buf.copy_from_slice(self.take(N)?);
+            Ok(if self.swap {
+                <$ty>::from_be_bytes(buf)
+            } else {
+                <$ty>::from_le_bytes(buf)
+            })
+        }
+    };
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, msg: &str) -> HapiError {
+        HapiError::internal(format!("Binary JSON error at byte {}: {msg}", self.pos))
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let slice = self
+            .pos
+            .checked_add(n)
+            .and_then(|end| self.bytes.get(self.pos..end))
+            .ok_or_else(|| self.error("unexpected end of data"))?;
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    read_num!(i16, i16);
+    read_num!(u16, u16);
+    read_num!(i32, i32);
+    read_num!(u32, u32);
+    read_num!(i64, i64);
+    read_num!(u64, u64);
+    read_num!(f32, f32);
+    read_num!(f64, f64);
+
+    // Lengths below 0xf1 are stored in a single byte, otherwise the byte tells the width.
+    fn length(&mut self) -> Result<usize> {
+        let n = match self.u8()? {
+            n if n < 0xf1 => n as u64,
+            0xf2 => self.u16()? as u64,
+            0xf4 => self.u32()? as u64,
+            0xf8 => self.u64()?,
+            _ => return Err(self.error("invalid length encoding")),
+        };
+        usize::try_from(n).map_err(|_| self.error("length out of range"))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.length()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.error("invalid utf-8 in string"))
+    }
+
+    // Next token, handling token definitions and skipping separators.
+    fn token(&mut self) -> Result<u8> {
+        loop {
+            match self.u8()? {
+                JID_TOKENDEF => {
+                    let id = self.length()? as i64;
+                    let name = self.string()?;
+                    self.tokens.insert(id, name);
+                }
+                JID_TOKENUNDEF => {
+                    let id = self.length()? as i64;
+                    self.tokens.remove(&id);
+                }
+                JID_KEY_SEPARATOR | JID_VALUE_SEPARATOR => {}
+                token => return Ok(token),
+            }
+        }
+    }
+
+    // Arrays and maps are kept on an explicit stack, hostile nesting can't overflow the thread stack.
+    fn value(&mut self, mut token: u8) -> Result<Value> {
+        let mut stack: Vec<Container> = vec![];
+        loop {
+            let mut value = match token {
+                JID_ARRAY_BEGIN | JID_MAP_BEGIN => {
+                    if stack.len() >= MAX_DEPTH {
+                        return Err(self.error("nesting too deep"));
+                    }
+                    stack.push(match token {
+                        JID_ARRAY_BEGIN => Container::Array(vec![]),
+                        _ => Container::Map(vec![], String::new()),
+                    });
+                    None
+                }
+                token => Some(self.scalar(token)?),
+            };
+            // Store the value in its container and close finished containers until the next value
+            token = loop {
+                let Some(parent) = stack.last_mut() else {
+                    return Ok(value.expect("value of a closed container"));
+                };
+                if let Some(value) = value.take() {
+                    parent.push(value);
+                }
+                let next = self.token()?;
+                match parent {
+                    Container::Array(_) if next == JID_ARRAY_END => {}
+                    Container::Array(_) => break next,
+                    Container::Map(..) if next == JID_MAP_END => {}
+                    Container::Map(_, key) => {
+                        *key = match next {
+                            JID_STRING => self.string()?,
+                            JID_TOKENREF => self.token_ref()?,
+                            _ => return Err(self.error("expected map key")),
+                        };
+                        break self.token()?;
+                    }
+                }
+                value = stack.pop().map(Container::finish);
+            };
+        }
+    }
+
+    fn scalar(&mut self, token: u8) -> Result<Value> {
+        Ok(match token {
+            JID_NULL => Value::Null,
+            JID_FALSE => Value::Bool(false),
+            JID_TRUE => Value::Bool(true),
+            JID_BOOL => Value::Bool(self.u8()? != 0),
+            JID_INT8 => Value::Int(self.u8()? as i8 as i64),
+            JID_INT16 => Value::Int(self.i16()? as i64),
+            JID_INT32 => Value::Int(self.i32()? as i64),
+            JID_INT64 => Value::Int(self.i64()?),
+            JID_UINT8 => Value::Int(self.u8()? as i64),
+            JID_UINT16 => Value::Int(self.u16()? as i64),
+            JID_REAL16 => Value::Float(f16_to_f32(self.u16()?) as f64),
+            JID_REAL32 => Value::Float(self.f32()? as f64),
+            JID_REAL64 => Value::Float(self.f64()?),
+            JID_STRING => Value::String(self.string()?),
+            JID_TOKENREF => Value::String(self.token_ref()?),
+            JID_UNIFORM_ARRAY => self.uniform_array()?,
+            _ => return Err(self.error(&format!("unknown token 0x{token:02x}"))),
+        })
+    }
+
+    fn token_ref(&mut self) -> Result<String> {
+        let id = self.length()? as i64;
+        self.tokens
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| self.error(&format!("undefined token {id}")))
+    }
+
+    fn uniform_array(&mut self) -> Result<Value> {
+        let kind = self.u8()?;
+        let len = self.length()?;
+        // Guard against allocating for a bogus length
+        let remaining = self.bytes.len() - self.pos;
+        let element_size = match kind {
+            JID_BOOL => 0,
+            JID_INT8 | JID_UINT8 => 1,
+            JID_INT16 | JID_UINT16 | JID_REAL16 => 2,
+            JID_INT32 | JID_REAL32 => 4,
+            JID_INT64 | JID_REAL64 => 8,
+            _ => usize::MAX,
+        };
+        if element_size != usize::MAX && len.saturating_mul(element_size) > remaining {
+            return Err(self.error("uniform array exceeds data"));
+        }
+        macro_rules! ints {
+            ($read:ident) => {
+                (0..len)
+                    .map(|_| self.$read().map(|v| v as i64))
+                    .collect::<Result<Vec<_>>>()
+                    .map(Value::IntArray)
+            };
+        }
+        macro_rules! floats {
+            ($read:ident, $conv:expr) => {
+                (0..len)
+                    .map(|_| self.$read().map($conv))
+                    .collect::<Result<Vec<_>>>()
+                    .map(Value::FloatArray)
+            };
+        }
+        match kind {
+            JID_BOOL => {
+                // Bits packed into 32 bit words
+                let words = (0..len.div_ceil(32))
+                    .map(|_| self.u32())
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Value::Array(
+                    (0..len)
+                        .map(|i| Value::Bool(words[i / 32] & (1 << (i % 32)) != 0))
+                        .collect(),
+                ))
+            }
+            JID_INT8 => ints!(i8),
+            JID_UINT8 => ints!(u8),
+            JID_INT16 => ints!(i16),
+            JID_UINT16 => ints!(u16),
+            JID_INT32 => ints!(i32),
+            JID_INT64 => ints!(i64),
+            JID_REAL16 => floats!(u16, |v| f16_to_f32(v) as f64),
+            JID_REAL32 => floats!(f32, |v| v as f64),
+            JID_REAL64 => floats!(f64, |v| v),
+            JID_STRING | JID_TOKENREF => (0..len)
+                .map(|_| self.scalar(kind))
+                .collect::<Result<Vec<_>>>()
+                .map(Value::Array),
+            _ => Err(self.error(&format!("unsupported uniform array type 0x{kind:02x}"))),
+        }
+    }
+
+    fn i8(&mut self) -> Result<i8> {
+        Ok(self.u8()? as i8)
+    }
+}
+
+/// Convert IEEE half float bits.
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits >> 15) as u32) << 31;
+    let exp = ((bits >> 10) & 0x1f) as u32;
+    let mant = (bits & 0x3ff) as u32;
+    let out = match exp {
+        0 if mant == 0 => sign,
+        0 => {
+            // Subnormal, normalize the mantissa
+            let mut e = 127 - 15 + 1;
+            let mut m = mant;
+            while m & 0x400 == 0 {
+                m <<= 1;
+                e -= 1;
+            }
+            sign | (e << 23) | ((m & 0x3ff) << 13)
+        }
+        0x1f => sign | 0x7f80_0000 | (mant << 13),
+        _ => sign | ((exp + 127 - 15) << 23) | (mant << 13),
+    };
+    f32::from_bits(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_json_tokens() {
+        let mut data = vec![JID_MAGIC];
+        data.extend(MAGIC);
+        data.push(JID_ARRAY_BEGIN);
+        // Token definition followed by a reference to it
+        data.extend([JID_TOKENDEF, 3, 5]);
+        data.extend(b"count");
+        data.extend([JID_TOKENREF, 3, JID_INT16]);
+        data.extend(300i16.to_le_bytes());
+        data.extend([JID_STRING, 2, b'h', b'i', JID_TRUE, JID_REAL16]);
+        data.extend(0x3c00u16.to_le_bytes());
+        data.extend([JID_UNIFORM_ARRAY, JID_REAL32, 2]);
+        data.extend(1.5f32.to_le_bytes());
+        data.extend((-2f32).to_le_bytes());
+        data.extend([JID_UNIFORM_ARRAY, JID_INT8, 3, 1, 0xff, 7]);
+        data.extend([JID_UNIFORM_ARRAY, JID_BOOL, 3]);
+        data.extend(0b101u32.to_le_bytes());
+        data.extend([JID_MAP_BEGIN, JID_TOKENREF, 3, JID_NULL, JID_MAP_END]);
+        data.push(JID_ARRAY_END);
+
+        let value = parse(&data).unwrap();
+        assert_eq!(value.get("count"), Some(&Value::Int(300)));
+        let items = value.as_array().unwrap();
+        assert_eq!(items[2], Value::from("hi"));
+        assert_eq!(items[3], Value::Bool(true));
+        assert_eq!(items[4], Value::Float(1.0));
+        assert_eq!(items[5].to_f64_vec(), Some(vec![1.5, -2.0]));
+        assert_eq!(items[6].to_i64_vec(), Some(vec![1, -1, 7]));
+        assert_eq!(
+            items[7],
+            Value::Array(vec![
+                Value::Bool(true),
+                Value::Bool(false),
+                Value::Bool(true)
+            ])
+        );
+        assert_eq!(items[8], Value::Object(vec![("count".into(), Value::Null)]));
+        assert!(parse(&data[..data.len() - 4]).is_err());
+
+        let mut nested = vec![JID_MAGIC];
+        nested.extend(MAGIC);
+        nested.resize(100_000, JID_ARRAY_BEGIN);
+        assert!(parse(&nested).is_err());
+
+        let mut nested = vec![JID_MAGIC];
+        nested.extend(MAGIC);
+        nested.extend([JID_ARRAY_BEGIN; MAX_DEPTH]);
+        nested.extend([JID_ARRAY_END; MAX_DEPTH]);
+        assert!(parse(&nested).is_ok());
+    }
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x3555), 0.33325195);
+        assert_eq!(f16_to_f32(0x0001), 5.9604645e-8);
+        assert!(f16_to_f32(0x7c00).is_infinite());
+    }
+}
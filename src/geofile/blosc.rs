@@ -0,0 +1,335 @@
+//! Decompression of blosc streams used by `.sc` geometry files
+//!
+//! Houdini writes the file as a sequence of independent blosc chunks, optionally followed by an
+//! index. Only the blosclz and lz4 codecs with byte shuffling are supported, which is what
+//! Houdini uses.
+use crate::errors::{HapiError, Result};
+
+const HEADER_SIZE: usize = 16;
+const VERSION_FORMAT: u8 = 2;
+const MAX_SPLITS: usize = 16;
+const MIN_BUFFERSIZE: usize = 128;
+// Both codecs expand a stream at most ~255 times, larger ratios are corrupt headers.
+const MAX_RATIO: usize = 1024;
+
+const FLAG_SHUFFLE: u8 = 0x1;
+const FLAG_MEMCPYED: u8 = 0x2;
+const FLAG_BITSHUFFLE: u8 = 0x4;
+const FLAG_DONT_SPLIT: u8 = 0x10;
+
+const CODEC_BLOSCLZ: u8 = 0;
+const CODEC_LZ4: u8 = 1;
+
+fn error(msg: impl AsRef<str>) -> HapiError {
+    HapiError::internal(format!("Blosc error: {}", msg.as_ref()))
+}
+
+struct Header {
+    flags: u8,
+    typesize: usize,
+    nbytes: usize,
+    blocksize: usize,
+    cbytes: usize,
+}
+
+fn header(data: &[u8]) -> Option<Header> {
+    let data = data.get(..HEADER_SIZE)?;
+    let u32_at = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap()) as usize;
+    let header = Header {
+        flags: data[2],
+        typesize: data[3] as usize,
+        nbytes: u32_at(4),
+        blocksize: u32_at(8),
+        cbytes: u32_at(12),
+    };
+    let valid = data[0] > 0
+        && data[0] <= VERSION_FORMAT
+        && header.typesize > 0
+        && header.cbytes >= HEADER_SIZE
+        && (header.blocksize > 0 || header.nbytes == 0);
+    valid.then_some(header)
+}
+
+/// Returns true if the data starts with a plausible blosc chunk header.
+pub(crate) fn is_blosc(bytes: &[u8]) -> bool {
+    header(bytes).is_some_and(|h| h.cbytes <= bytes.len())
+}
+
+/// Decompress a sequence of blosc chunks, stopping at the first bytes that are not a chunk.
+pub fn decompress(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = vec![];
+    let mut chunks = 0;
+    while let Some(h) = header(data) {
+        let Some(chunk) = data.get(..h.cbytes) else {
+            break;
+        };
+        decompress_chunk(chunk, &h, &mut out)?;
+        data = &data[h.cbytes..];
+        chunks += 1;
+    }
+    if chunks == 0 {
+        return Err(error("data is not blosc compressed"));
+    }
+    Ok(out)
+}
+
+fn decompress_chunk(chunk: &[u8], h: &Header, out: &mut Vec<u8>) -> Result<()> {
+    let start = out.len();
+    if h.flags & FLAG_MEMCPYED != 0 {
+        let data = chunk
+            .get(HEADER_SIZE..HEADER_SIZE + h.nbytes)
+            .ok_or_else(|| error("truncated chunk"))?;
+        out.extend_from_slice(data);
+        return Ok(());
+    }
+    if h.flags & FLAG_BITSHUFFLE != 0 {
+        return Err(error("bit shuffle is not supported"));
+    }
+    let codec = h.flags >> 5;
+    if codec != CODEC_BLOSCLZ && codec != CODEC_LZ4 {
+        return Err(error(format!("unsupported codec {codec}")));
+    }
+    // The header sizes are not trusted before allocating
+    if h.blocksize > h.nbytes || h.nbytes > h.cbytes.saturating_mul(MAX_RATIO) {
+        return Err(error("invalid chunk sizes"));
+    }
+    let nblocks = h.nbytes.div_ceil(h.blocksize);
+    out.resize(start + h.nbytes, 0);
+    let mut scratch = vec![0u8; h.blocksize];
+    for block in 0..nblocks {
+        let offset = HEADER_SIZE + block * 4;
+        let bstart = chunk
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+            .ok_or_else(|| error("truncated block offsets"))?;
+        let leftover = block == nblocks - 1 && !h.nbytes.is_multiple_of(h.blocksize);
+        let bsize = if leftover {
+            h.nbytes % h.blocksize
+        } else {
+            h.blocksize
+        };
+        let nsplits = if h.flags & FLAG_DONT_SPLIT == 0
+            && h.typesize <= MAX_SPLITS
+            && bsize / h.typesize >= MIN_BUFFERSIZE
+            && !leftover
+        {
+            h.typesize
+        } else {
+            1
+        };
+        let split_size = bsize / nsplits;
+        let dest = &mut scratch[..bsize];
+        let mut src = chunk
+            .get(bstart..)
+            .ok_or_else(|| error("block offset out of range"))?;
+        for split in dest.chunks_mut(split_size).take(nsplits) {
+            let csize = src
+                .get(..4)
+                .map(|b| i32::from_le_bytes(b.try_into().unwrap()))
+                .ok_or_else(|| error("truncated block"))?;
+            let csize = usize::try_from(csize).map_err(|_| error("invalid stream size"))?;
+            let stream = src
+                .get(4..4 + csize)
+                .ok_or_else(|| error("truncated stream"))?;
+            src = &src[4 + csize..];
+            if csize == split.len() {
+                // Stored uncompressed
+                split.copy_from_slice(stream);
+                continue;
+            }
+            let written = match codec {
+                CODEC_BLOSCLZ => blosclz_decompress(stream, split)?,
+                _ => lz4_decompress(stream, split)?,
+            };
+            if written != split.len() {
+                return Err(error("decompressed size mismatch"));
+            }
+        }
+        let target = &mut out[start + block * h.blocksize..start + block * h.blocksize + bsize];
+        if h.flags & FLAG_SHUFFLE != 0 && h.typesize > 1 {
+            unshuffle(h.typesize, dest, target);
+        } else {
+            target.copy_from_slice(dest);
+        }
+    }
+    Ok(())
+}
+
+fn unshuffle(typesize: usize, src: &[u8], dest: &mut [u8]) {
+    let elements = src.len() / typesize;
+    for i in 0..elements {
+        for j in 0..typesize {
+            dest[i * typesize + j] = src[j * elements + i];
+        }
+    }
+    // Trailing bytes are not shuffled
+    let tail = elements * typesize;
+    dest[tail..].copy_from_slice(&src[tail..]);
+}
+
+// Copy `len` bytes from `distance` bytes back, byte by byte since the ranges can overlap.
+fn copy_match(out: &mut [u8], op: usize, distance: usize, len: usize) -> Result<()> {
+    if distance == 0 || distance > op || op + len > out.len() {
+        return Err(error("invalid match"));
+    }
+    for i in op..op + len {
+        out[i] = out[i - distance];
+    }
+    Ok(())
+}
+
+fn blosclz_decompress(input: &[u8], out: &mut [u8]) -> Result<usize> {
+    const MAX_DISTANCE: usize = 8191;
+    let truncated = || error("truncated blosclz stream");
+    let mut ip = 0;
+    let mut op = 0;
+    let next = |ip: &mut usize| -> Result<usize> {
+        let b = *input.get(*ip).ok_or_else(truncated)?;
+        *ip += 1;
+        Ok(b as usize)
+    };
+    let mut ctrl = next(&mut ip)? & 31;
+    loop {
+        if ctrl >= 32 {
+            let mut len = (ctrl >> 5) - 1;
+            let ofs = (ctrl & 31) << 8;
+            if len == 6 {
+                loop {
+                    let code = next(&mut ip)?;
+                    len += code;
+                    if code != 255 {
+                        break;
+                    }
+                }
+            }
+            let code = next(&mut ip)?;
+            let distance = if code == 255 && ofs == 31 << 8 {
+                let far = (next(&mut ip)? << 8) + next(&mut ip)?;
+                far + MAX_DISTANCE + 1
+            } else {
+                ofs + code + 1
+            };
+            copy_match(out, op, distance, len + 3)?;
+            op += len + 3;
+        } else {
+            let len = ctrl + 1;
+            let literal = input.get(ip..ip + len).ok_or_else(truncated)?;
+            out.get_mut(op..op + len)
+                .ok_or_else(|| error("blosclz output overflow"))?
+                .copy_from_slice(literal);
+            ip += len;
+            op += len;
+        }
+        if ip >= input.len() {
+            break;
+        }
+        ctrl = next(&mut ip)?;
+    }
+    Ok(op)
+}
+
+fn lz4_decompress(input: &[u8], out: &mut [u8]) -> Result<usize> {
+    let truncated = || error("truncated lz4 stream");
+    let mut ip = 0;
+    let mut op = 0;
+    let read_len = |ip: &mut usize, mut len: usize| -> Result<usize> {
+        if len == 15 {
+            loop {
+                let b = *input.get(*ip).ok_or_else(truncated)? as usize;
+                *ip += 1;
+                len += b;
+                if b != 255 {
+                    break;
+                }
+            }
+        }
+        Ok(len)
+    };
+    loop {
+        let token = *input.get(ip).ok_or_else(truncated)? as usize;
+        ip += 1;
+        let len = read_len(&mut ip, token >> 4)?;
+        let literal = input.get(ip..ip + len).ok_or_else(truncated)?;
+        out.get_mut(op..op + len)
+            .ok_or_else(|| error("lz4 output overflow"))?
+            .copy_from_slice(literal);
+        ip += len;
+        op += len;
+        if ip >= input.len() {
+            // The last sequence only has literals
+            break;
+        }
+        let distance = input
+            .get(ip..ip + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(truncated)?;
+        ip += 2;
+        let len = read_len(&mut ip, token & 15)? + 4;
+        copy_match(out, op, distance, len)?;
+        op += len;
+    }
+    Ok(op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(flags: u8, typesize: u8, nbytes: usize, blocksize: usize, body: &[u8]) -> Vec<u8> {
+        let mut data = vec![2, 1, flags, typesize];
+        data.extend((nbytes as u32).to_le_bytes());
+        data.extend((blocksize as u32).to_le_bytes());
+        data.extend(((HEADER_SIZE + body.len()) as u32).to_le_bytes());
+        data.extend(body);
+        data
+    }
+
+    #[test]
+    fn blosclz_stream() {
+        // 3 literals "abc", then a match of 5 bytes at distance 3 and a literal "!"
+        let stream = [2, b'a', b'b', b'c', (3 << 5), 2, 0, b'!'];
+        let mut out = [0; 9];
+        assert_eq!(blosclz_decompress(&stream, &mut out).unwrap(), 9);
+        assert_eq!(&out, b"abcabcab!");
+    }
+
+    #[test]
+    fn lz4_stream() {
+        // Literal "ab", match of 6 bytes at distance 2, then literal "c"
+        let stream = [0x22, b'a', b'b', 2, 0, 0x10, b'c'];
+        let mut out = [0; 9];
+        assert_eq!(lz4_decompress(&stream, &mut out).unwrap(), 9);
+        assert_eq!(&out, b"ababababc");
+    }
+
+    #[test]
+    fn shuffled_chunks() {
+        let values: Vec<u32> = (0..64).collect();
+        let raw: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut shuffled = vec![0; raw.len()];
+        for i in 0..64 {
+            for j in 0..4 {
+                shuffled[j * 64 + i] = raw[i * 4 + j];
+            }
+        }
+        // One block stored uncompressed in a single stream
+        let mut body = (HEADER_SIZE as u32 + 4).to_le_bytes().to_vec();
+        body.extend((shuffled.len() as u32).to_le_bytes());
+        body.extend(&shuffled);
+        let mut data = chunk(FLAG_SHUFFLE, 4, raw.len(), raw.len(), &body);
+        // Followed by a memcpyed chunk and trailing garbage
+        data.extend(chunk(FLAG_MEMCPYED, 1, 3, 3, b"xyz"));
+        data.extend([0, 0, 0]);
+        assert!(is_blosc(&data));
+        let out = decompress(&data).unwrap();
+        assert_eq!(&out[..raw.len()], &raw[..]);
+        assert_eq!(&out[raw.len()..], b"xyz");
+        assert!(decompress(b"[1, 2]").is_err());
+
+        // Sizes that would allocate far more than the chunk can hold
+        let huge = chunk(FLAG_SHUFFLE, 4, u32::MAX as usize, 1 << 16, &[]);
+        assert!(decompress(&huge).is_err());
+        let bad_block = chunk(FLAG_SHUFFLE, 4, 16, 64, &[0; 8]);
+        assert!(decompress(&bad_block).is_err());
+    }
+}
@@ -0,0 +1,471 @@
+//! Minimal JSON document model used by the `.geo` format
+//!
+//! Houdini writes most structures as flat arrays of alternating keys and values rather than JSON
+//! objects, see [`Value::get`].
+use std::fmt::Write;
+
+use crate::errors::{HapiError, Result};
+
+/// A JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+    /// Uniform integer array, as found in binary files.
+    IntArray(Vec<i64>),
+    /// Uniform float array, as found in binary files.
+    FloatArray(Vec<f64>),
+}
+
+impl Value {
+    /// Look up a key in an object or in a flat `[key, value, key, value, ...]` array.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(items) => items.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            Value::Array(items) => items
+                .chunks_exact(2)
+                .find(|kv| kv[0].as_str() == Some(key))
+                .map(|kv| &kv[1]),
+            _ => None,
+        }
+    }
+
+    /// Iterate over key-value pairs of an object or a flat key-value array.
+    pub fn entries(&self) -> Box<dyn Iterator<Item = (&str, &Value)> + '_> {
+        match self {
+            Value::Object(items) => Box::new(items.iter().map(|(k, v)| (k.as_str(), v))),
+            Value::Array(items) => Box::new(
+                items
+                    .chunks_exact(2)
+                    .filter_map(|kv| kv[0].as_str().map(|k| (k, &kv[1]))),
+            ),
+            _ => Box::new(std::iter::empty()),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(v) => Some(*v),
+            Value::Float(v) if v.fract() == 0.0 => Some(*v as i64),
+            Value::Bool(v) => Some(*v as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(v) => Some(*v as f64),
+            Value::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(v) => Some(*v),
+            Value::Int(v) => Some(*v != 0),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Array of numbers as integers.
+    pub fn to_i64_vec(&self) -> Option<Vec<i64>> {
+        match self {
+            Value::IntArray(v) => Some(v.clone()),
+            Value::FloatArray(v) => v
+                .iter()
+                .map(|v| (v.fract() == 0.0).then_some(*v as i64))
+                .collect(),
+            _ => self.as_array()?.iter().map(Value::as_i64).collect(),
+        }
+    }
+
+    /// Array of numbers as floats.
+    pub fn to_f64_vec(&self) -> Option<Vec<f64>> {
+        match self {
+            Value::IntArray(v) => Some(v.iter().map(|v| *v as f64).collect()),
+            Value::FloatArray(v) => Some(v.clone()),
+            _ => self.as_array()?.iter().map(Value::as_f64).collect(),
+        }
+    }
+
+    /// Build a flat key-value array.
+    pub fn kv<K: Into<String>>(items: impl IntoIterator<Item = (K, Value)>) -> Value {
+        let mut out = vec![];
+        for (k, v) in items {
+            out.push(Value::String(k.into()));
+            out.push(v);
+        }
+        Value::Array(out)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Int(v)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Bool(v)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        Value::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Parse JSON text.
+pub fn parse(text: &str) -> Result<Value> {
+    let mut parser = Parser {
+        bytes: text.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_ws();
+    if parser.pos != parser.bytes.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+// Deeper arrays and objects are rejected instead of overflowing the stack.
+const MAX_DEPTH: usize = 512;
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, msg: &str) -> HapiError {
+        HapiError::internal(format!("JSON parse error at byte {}: {msg}", self.pos))
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(b' ' | b'\n' | b'\r' | b'\t') = self.bytes.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_ws();
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, token: &[u8]) -> Result<()> {
+        if self.bytes[self.pos..].starts_with(token) {
+            self.pos += token.len();
+            Ok(())
+        } else {
+            Err(self.error("unexpected token"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        self.depth += 1;
+        let value = self.nested_value();
+        self.depth -= 1;
+        value
+    }
+
+    fn nested_value(&mut self) -> Result<Value> {
+        match self.peek() {
+            None => Err(self.error("unexpected end of input")),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = vec![];
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(Value::Array(items));
+                        }
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut items = vec![];
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Ok(Value::Object(items));
+                }
+                loop {
+                    if self.peek() != Some(b'"') {
+                        return Err(self.error("expected object key"));
+                    }
+                    let key = self.string()?;
+                    if self.peek() != Some(b':') {
+                        return Err(self.error("expected ':'"));
+                    }
+                    self.pos += 1;
+                    items.push((key, self.value()?));
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(Value::Object(items));
+                        }
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+            }
+            Some(b'"') => self.string().map(Value::String),
+            Some(b't') => self.expect(b"true").map(|_| Value::Bool(true)),
+            Some(b'f') => self.expect(b"false").map(|_| Value::Bool(false)),
+            Some(b'n') => self.expect(b"null").map(|_| Value::Null),
+            Some(_) => self.number(),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value> {
+        let start = self.pos;
+        let mut is_float = false;
+        while let Some(c) = self.bytes.get(self.pos) {
+            match c {
+                b'0'..=b'9' | b'-' | b'+' => {}
+                b'.' | b'e' | b'E' => is_float = true,
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        // SAFETY: only ASCII bytes were consumed
+        let text = unsafe { std::str::from_utf8_unchecked(&self.bytes[start..self.pos]) };
+        if !is_float {
+            if let Ok(v) = text.parse::<i64>() {
+                return Ok(Value::Int(v));
+            }
+        }
+        text.parse::<f64>()
+            .map(Value::Float)
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        // Opening quote
+        self.pos += 1;
+        let mut out = Vec::new();
+        loop {
+            let Some(c) = self.bytes.get(self.pos).copied() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let Some(esc) = self.bytes.get(self.pos).copied() else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.pos += 1;
+                    match esc {
+                        b'"' | b'\\' | b'/' => out.push(esc),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0c),
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            if (0xD800..0xDC00).contains(&code) {
+                                self.expect(b"\\u")?;
+                                let low = self.hex4()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("invalid low surrogate"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            let ch = char::from_u32(code).unwrap_or('\u{FFFD}');
+                            let mut buf = [0; 4];
+                            out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        String::from_utf8(out).map_err(|_| self.error("invalid utf-8 in string"))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let hex = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u32::from_str_radix(h, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(hex)
+    }
+}
+
+/// Serialize a value as JSON text. Top level arrays are written one item per line.
+pub fn to_string(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push('\n');
+                write_value(item, &mut out);
+            }
+            out.push_str("\n]\n");
+        }
+        other => write_value(other, &mut out),
+    }
+    out
+}
+
+fn write_value(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(v) => out.push_str(if *v { "true" } else { "false" }),
+        Value::Int(v) => write!(out, "{v}").unwrap(),
+        Value::Float(v) => write_float(*v, out),
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(items) => {
+            out.push('{');
+            for (i, (k, v)) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(k, out);
+                out.push(':');
+                write_value(v, out);
+            }
+            out.push('}');
+        }
+        Value::IntArray(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write!(out, "{item}").unwrap();
+            }
+            out.push(']');
+        }
+        Value::FloatArray(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_float(*item, out);
+            }
+            out.push(']');
+        }
+    }
+}
+
+pub(crate) fn write_float(v: f64, out: &mut String) {
+    if !v.is_finite() {
+        // JSON has no representation for these
+        out.push('0');
+    } else if v.fract() == 0.0 && v.abs() < 1e15 {
+        write!(out, "{v:.1}").unwrap();
+    } else {
+        write!(out, "{v}").unwrap();
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_roundtrip() {
+        let text = r#"["a",1,"b",[1.5,-2,3e2],"c",{"d":"x\"yé","e":null,"f":true}]"#;
+        let value = parse(text).unwrap();
+        assert_eq!(value.get("a"), Some(&Value::Int(1)));
+        assert_eq!(
+            value.get("b").unwrap().to_f64_vec(),
+            Some(vec![1.5, -2.0, 300.0])
+        );
+        let c = value.get("c").unwrap();
+        assert_eq!(c.get("d").unwrap().as_str(), Some("x\"yé"));
+        assert_eq!(c.get("f").unwrap().as_bool(), Some(true));
+        assert_eq!(parse(&to_string(&value)).unwrap(), value);
+        assert!(parse("[1,2").is_err());
+        assert!(parse("[1] x").is_err());
+        assert_eq!(parse(r#""\uD83D\uDE00""#).unwrap().as_str(), Some("😀"));
+        assert!(parse(r#""\uD800\u0041""#).is_err());
+        assert!(parse(&"[".repeat(100_000)).is_err());
+    }
+}
@@ -0,0 +1,834 @@
+//! Pure Rust reader and writer for Houdini `.geo` files
+//!
+//! [`GeoFile`] is an in-memory model of a Houdini geometry detail: point references of vertices,
+//! primitives, attributes of all numeric storages and strings, and groups. It can be read from and
+//! written to the ASCII JSON `.geo` format without a running session, which is handy for inspecting,
+//! patching or generating geometry offline and for testing. Binary `.bgeo` files, including blosc
+//! compressed `.bgeo.sc` caches, can be read as well.
+//!
+//! Attribute types and primitives which are not modeled (array and dictionary attributes, volumes,
+//! NURBS, etc.) are kept as raw [`json::Value`]s so they survive a round trip.
+//!
+//! ```ignore
+//! use hapi_rs::geofile::GeoFile;
+//! let mut geo = GeoFile::read_file("box.geo")?;
+//! if let Some(p) = geo.point_attribute_mut("P") {
+//!     p.data.as_f64_mut().unwrap().iter_mut().for_each(|v| *v *= 2.0);
+//! }
+//! geo.write_file("box_scaled.geo")?;
+//! ```
+pub mod binary;
+pub mod blosc;
+pub mod json;
+mod parse;
+mod write;
+
+use std::path::Path;
+
+use crate::errors::{HapiError, Result};
+use crate::ffi::enums::{AttributeOwner, GroupType};
+use crate::geometry::{GeoFormat, Geometry};
+use crate::mesh::{AttributeValues, Mesh, MeshAttribute, MeshChannel, MeshGroup};
+use json::Value;
+
+/// Storage of a numeric attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoStorage {
+    Uint8,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Real16,
+    Real32,
+    Real64,
+}
+
+impl GeoStorage {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "uint8" => GeoStorage::Uint8,
+            "int8" => GeoStorage::Int8,
+            "int16" => GeoStorage::Int16,
+            "int32" => GeoStorage::Int32,
+            "int64" => GeoStorage::Int64,
+            "fpreal16" => GeoStorage::Real16,
+            "fpreal32" => GeoStorage::Real32,
+            "fpreal64" => GeoStorage::Real64,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GeoStorage::Uint8 => "uint8",
+            GeoStorage::Int8 => "int8",
+            GeoStorage::Int16 => "int16",
+            GeoStorage::Int32 => "int32",
+            GeoStorage::Int64 => "int64",
+            GeoStorage::Real16 => "fpreal16",
+            GeoStorage::Real32 => "fpreal32",
+            GeoStorage::Real64 => "fpreal64",
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(
+            self,
+            GeoStorage::Real16 | GeoStorage::Real32 | GeoStorage::Real64
+        )
+    }
+}
+
+/// Attribute values, flattened by tuple size.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoAttributeData {
+    Int {
+        storage: GeoStorage,
+        values: Vec<i64>,
+    },
+    Float {
+        storage: GeoStorage,
+        values: Vec<f64>,
+    },
+    /// Unique strings and per-element indices into them, -1 means no string.
+    String {
+        strings: Vec<String>,
+        indices: Vec<i32>,
+    },
+    /// Attribute type not modeled by this crate, e.g. arrays and dictionaries.
+    Raw { type_name: String, data: Value },
+}
+
+impl GeoAttributeData {
+    pub fn as_f64(&self) -> Option<&[f64]> {
+        match self {
+            GeoAttributeData::Float { values, .. } => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64_mut(&mut self) -> Option<&mut Vec<f64>> {
+        match self {
+            GeoAttributeData::Float { values, .. } => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<&[i64]> {
+        match self {
+            GeoAttributeData::Int { values, .. } => Some(values),
+            _ => None,
+        }
+    }
+
+    /// String value of an element.
+    pub fn string(&self, element: usize) -> Option<&str> {
+        match self {
+            GeoAttributeData::String { strings, indices } => {
+                let idx = *indices.get(element)?;
+                strings.get(usize::try_from(idx).ok()?).map(String::as_str)
+            }
+            _ => None,
+        }
+    }
+
+    /// Number of stored values, `None` for raw data.
+    pub fn value_count(&self) -> Option<usize> {
+        match self {
+            GeoAttributeData::Int { values, .. } => Some(values.len()),
+            GeoAttributeData::Float { values, .. } => Some(values.len()),
+            GeoAttributeData::String { indices, .. } => Some(indices.len()),
+            GeoAttributeData::Raw { .. } => None,
+        }
+    }
+}
+
+/// A geometry attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoAttribute {
+    pub name: String,
+    /// Usually "public", "private" for internal attributes.
+    pub scope: String,
+    /// Type qualifier such as "point", "vector", "normal", "color", "texturecoord".
+    pub type_info: Option<String>,
+    pub tuple_size: usize,
+    pub data: GeoAttributeData,
+}
+
+impl GeoAttribute {
+    pub fn float(
+        name: impl Into<String>,
+        tuple_size: usize,
+        storage: GeoStorage,
+        values: Vec<f64>,
+    ) -> Self {
+        debug_assert!(storage.is_float());
+        GeoAttribute {
+            name: name.into(),
+            scope: "public".to_string(),
+            type_info: None,
+            tuple_size,
+            data: GeoAttributeData::Float { storage, values },
+        }
+    }
+
+    pub fn int(
+        name: impl Into<String>,
+        tuple_size: usize,
+        storage: GeoStorage,
+        values: Vec<i64>,
+    ) -> Self {
+        debug_assert!(!storage.is_float());
+        GeoAttribute {
+            name: name.into(),
+            scope: "public".to_string(),
+            type_info: None,
+            tuple_size,
+            data: GeoAttributeData::Int { storage, values },
+        }
+    }
+
+    /// String attribute from per-element values, deduplicating the strings.
+    pub fn string<S: AsRef<str>>(name: impl Into<String>, values: &[S]) -> Self {
+        let mut strings: Vec<String> = vec![];
+        let mut lookup = std::collections::HashMap::new();
+        let indices = values
+            .iter()
+            .map(|s| {
+                let s = s.as_ref();
+                *lookup.entry(s.to_string()).or_insert_with(|| {
+                    strings.push(s.to_string());
+                    strings.len() as i32 - 1
+                })
+            })
+            .collect();
+        GeoAttribute {
+            name: name.into(),
+            scope: "public".to_string(),
+            type_info: None,
+            tuple_size: 1,
+            data: GeoAttributeData::String { strings, indices },
+        }
+    }
+
+    pub fn with_type_info(mut self, type_info: impl Into<String>) -> Self {
+        self.type_info = Some(type_info.into());
+        self
+    }
+}
+
+/// A primitive.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoPrimitive {
+    /// Polygon or open polyline referencing vertex indices.
+    Polygon { vertices: Vec<u32>, closed: bool },
+    /// Any other primitive type (packed, volume, curves, ...) kept as raw JSON.
+    /// `vertices` are the vertices it references, if those could be found in the data.
+    Other {
+        type_name: String,
+        vertices: Vec<u32>,
+        header: Value,
+        body: Value,
+    },
+}
+
+impl GeoPrimitive {
+    pub fn type_name(&self) -> &str {
+        match self {
+            GeoPrimitive::Polygon { .. } => "Poly",
+            GeoPrimitive::Other { type_name, .. } => type_name,
+        }
+    }
+
+    pub fn vertices(&self) -> &[u32] {
+        match self {
+            GeoPrimitive::Polygon { vertices, .. } => vertices,
+            GeoPrimitive::Other { vertices, .. } => vertices,
+        }
+    }
+}
+
+/// An element group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoGroup {
+    pub name: String,
+    /// Membership flag for every element.
+    pub membership: Vec<bool>,
+}
+
+impl GeoGroup {
+    pub fn members(&self) -> impl Iterator<Item = usize> + '_ {
+        self.membership
+            .iter()
+            .enumerate()
+            .filter_map(|(i, m)| m.then_some(i))
+    }
+}
+
+/// In-memory model of a Houdini geometry file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GeoFile {
+    pub point_count: usize,
+    /// Point index of every vertex.
+    pub vertices: Vec<u32>,
+    pub primitives: Vec<GeoPrimitive>,
+    pub vertex_attributes: Vec<GeoAttribute>,
+    pub point_attributes: Vec<GeoAttribute>,
+    pub primitive_attributes: Vec<GeoAttribute>,
+    pub detail_attributes: Vec<GeoAttribute>,
+    pub point_groups: Vec<GeoGroup>,
+    pub primitive_groups: Vec<GeoGroup>,
+    pub vertex_groups: Vec<GeoGroup>,
+    /// The "info" block with bounds, software version, etc.
+    pub info: Option<Value>,
+}
+
+impl GeoFile {
+    /// Parse an ASCII `.geo` document.
+    pub fn from_json_str(text: &str) -> Result<Self> {
+        parse::from_value(&json::parse(text)?)
+    }
+
+    /// Parse a `.geo`, `.bgeo` or `.bgeo.sc` document, the format is detected from the data.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if binary::is_binary(bytes) {
+            return parse::from_value(&binary::parse(bytes)?);
+        }
+        if blosc::is_blosc(bytes) {
+            let bytes = blosc::decompress(bytes)?;
+            if blosc::is_blosc(&bytes) {
+                return Err(HapiError::internal("Nested blosc compression"));
+            }
+            return Self::from_bytes(&bytes);
+        }
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| HapiError::internal("Geometry data is not valid utf-8 JSON"))?;
+        Self::from_json_str(text)
+    }
+
+    /// Read a `.geo`, `.bgeo` or `.bgeo.sc` file.
+    pub fn read_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Serialize into the ASCII `.geo` format.
+    pub fn to_json_string(&self) -> String {
+        json::to_string(&write::to_value(self))
+    }
+
+    /// Write an ASCII `.geo` file.
+    pub fn write_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_json_string())?;
+        Ok(())
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn primitive_count(&self) -> usize {
+        self.primitives.len()
+    }
+
+    /// Number of elements of an attribute owner.
+    pub fn element_count(&self, owner: AttributeOwner) -> usize {
+        match owner {
+            AttributeOwner::Vertex => self.vertex_count(),
+            AttributeOwner::Point => self.point_count,
+            AttributeOwner::Prim => self.primitive_count(),
+            AttributeOwner::Detail => 1,
+            AttributeOwner::Invalid | AttributeOwner::Max => 0,
+        }
+    }
+
+    pub fn attributes(&self, owner: AttributeOwner) -> &[GeoAttribute] {
+        match owner {
+            AttributeOwner::Vertex => &self.vertex_attributes,
+            AttributeOwner::Point => &self.point_attributes,
+            AttributeOwner::Prim => &self.primitive_attributes,
+            AttributeOwner::Detail => &self.detail_attributes,
+            AttributeOwner::Invalid | AttributeOwner::Max => &[],
+        }
+    }
+
+    /// `None` for [`AttributeOwner::Invalid`] and [`AttributeOwner::Max`].
+    pub fn attributes_mut(&mut self, owner: AttributeOwner) -> Option<&mut Vec<GeoAttribute>> {
+        match owner {
+            AttributeOwner::Vertex => Some(&mut self.vertex_attributes),
+            AttributeOwner::Point => Some(&mut self.point_attributes),
+            AttributeOwner::Prim => Some(&mut self.primitive_attributes),
+            AttributeOwner::Detail => Some(&mut self.detail_attributes),
+            AttributeOwner::Invalid | AttributeOwner::Max => None,
+        }
+    }
+
+    pub fn attribute(&self, owner: AttributeOwner, name: &str) -> Option<&GeoAttribute> {
+        self.attributes(owner).iter().find(|a| a.name == name)
+    }
+
+    pub fn point_attribute_mut(&mut self, name: &str) -> Option<&mut GeoAttribute> {
+        self.point_attributes.iter_mut().find(|a| a.name == name)
+    }
+
+    /// Point positions from the `P` attribute, `None` if it has less than 3 components.
+    pub fn positions(&self) -> Option<Vec<[f32; 3]>> {
+        let p = self.attribute(AttributeOwner::Point, "P")?;
+        if p.tuple_size < 3 {
+            return None;
+        }
+        let values = p.data.as_f64()?;
+        Some(
+            values
+                .chunks_exact(p.tuple_size)
+                .map(|t| [t[0] as f32, t[1] as f32, t[2] as f32])
+                .collect(),
+        )
+    }
+
+    /// Check that element counts and references are consistent.
+    pub fn validate(&self) -> Result<()> {
+        let err = |msg: String| Err(HapiError::internal(msg));
+        if let Some(p) = self
+            .vertices
+            .iter()
+            .find(|p| **p as usize >= self.point_count)
+        {
+            return err(format!("Vertex references missing point {p}"));
+        }
+        for prim in &self.primitives {
+            if let Some(v) = prim
+                .vertices()
+                .iter()
+                .find(|v| **v as usize >= self.vertex_count())
+            {
+                return err(format!("Primitive references missing vertex {v}"));
+            }
+        }
+        for owner in [
+            AttributeOwner::Vertex,
+            AttributeOwner::Point,
+            AttributeOwner::Prim,
+            AttributeOwner::Detail,
+        ] {
+            let count = self.element_count(owner);
+            for attr in self.attributes(owner) {
+                let expected = match attr.data {
+                    GeoAttributeData::String { .. } => count,
+                    _ => count * attr.tuple_size,
+                };
+                if let Some(len) = attr.data.value_count() {
+                    if len != expected {
+                        return err(format!(
+                            "Attribute \"{}\" has {len} values, expected {expected}",
+                            attr.name
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Build a geometry from a [`Mesh`]. Every face vertex becomes a new vertex.
+    pub fn from_mesh(mesh: &Mesh) -> Result<Self> {
+        mesh.validate()?;
+        let mut geo = GeoFile {
+            point_count: mesh.point_count(),
+            vertices: mesh.indices.iter().map(|i| *i as u32).collect(),
+            ..Default::default()
+        };
+        let mut offset = 0u32;
+        for count in &mesh.face_counts {
+            let count = *count as u32;
+            geo.primitives.push(GeoPrimitive::Polygon {
+                vertices: (offset..offset + count).collect(),
+                closed: true,
+            });
+            offset += count;
+        }
+        let floats = |values: &[[f32; 3]]| -> Vec<f64> {
+            values.iter().flatten().map(|v| f32_to_f64(*v)).collect()
+        };
+        geo.point_attributes.push(
+            GeoAttribute::float("P", 3, GeoStorage::Real32, floats(&mesh.positions))
+                .with_type_info("point"),
+        );
+        for (name, type_info, channel) in [
+            ("N", "normal", &mesh.normals),
+            ("uv", "texturecoord", &mesh.uvs),
+            ("Cd", "color", &mesh.colors),
+        ] {
+            if let Some(channel) = channel {
+                geo.attributes_mut(channel.owner)
+                    .ok_or_else(|| HapiError::internal(format!("Invalid owner of \"{name}\"")))?
+                    .push(
+                        GeoAttribute::float(name, 3, GeoStorage::Real32, floats(&channel.values))
+                            .with_type_info(type_info),
+                    );
+            }
+        }
+        for attr in &mesh.attributes {
+            let size = attr.tuple_size as usize;
+            let geo_attr = match &attr.values {
+                AttributeValues::Int(v) => GeoAttribute::int(
+                    &attr.name,
+                    size,
+                    GeoStorage::Int32,
+                    v.iter().map(|v| *v as i64).collect(),
+                ),
+                AttributeValues::Int64(v) => {
+                    GeoAttribute::int(&attr.name, size, GeoStorage::Int64, v.clone())
+                }
+                AttributeValues::Float(v) => GeoAttribute::float(
+                    &attr.name,
+                    size,
+                    GeoStorage::Real32,
+                    v.iter().map(|v| f32_to_f64(*v)).collect(),
+                ),
+                AttributeValues::Double(v) => {
+                    GeoAttribute::float(&attr.name, size, GeoStorage::Real64, v.clone())
+                }
+                AttributeValues::String(v) => GeoAttribute::string(&attr.name, v),
+            };
+            geo.attributes_mut(attr.owner)
+                .ok_or_else(|| HapiError::internal(format!("Invalid owner of \"{}\"", attr.name)))?
+                .push(geo_attr);
+        }
+        for group in &mesh.groups {
+            let (count, groups) = match group.group_type {
+                GroupType::Point => (geo.point_count, &mut geo.point_groups),
+                _ => (geo.primitives.len(), &mut geo.primitive_groups),
+            };
+            let mut membership = vec![false; count];
+            for m in &group.members {
+                membership[*m as usize] = true;
+            }
+            groups.push(GeoGroup {
+                name: group.name.clone(),
+                membership,
+            });
+        }
+        Ok(geo)
+    }
+
+    /// Convert closed polygons into a [`Mesh`]. Fails if the geometry has open polygons
+    /// (polylines) or other primitive types.
+    pub fn to_mesh(&self) -> Result<Mesh> {
+        self.validate()?;
+        let mut mesh = Mesh {
+            positions: self
+                .positions()
+                .ok_or_else(|| HapiError::internal("Geometry has no P attribute"))?,
+            ..Default::default()
+        };
+        // Mesh vertices are in face order, which may differ from the file vertex order.
+        let mut vertex_order = Vec::with_capacity(self.vertices.len());
+        for prim in &self.primitives {
+            match prim {
+                GeoPrimitive::Polygon { closed: false, .. } => {
+                    return Err(HapiError::internal("Can't convert open polygons to mesh"))
+                }
+                GeoPrimitive::Polygon { vertices, .. } => {
+                    mesh.face_counts.push(vertices.len() as i32);
+                    vertex_order.extend(vertices.iter().map(|v| *v as usize));
+                }
+                GeoPrimitive::Other { type_name, .. } => {
+                    return Err(HapiError::internal(format!(
+                        "Can't convert primitive type {type_name} to mesh"
+                    )))
+                }
+            }
+        }
+        mesh.indices = vertex_order
+            .iter()
+            .map(|v| self.vertices[*v] as i32)
+            .collect();
+
+        for (owner, attrs) in [
+            (AttributeOwner::Vertex, &self.vertex_attributes),
+            (AttributeOwner::Point, &self.point_attributes),
+            (AttributeOwner::Prim, &self.primitive_attributes),
+            (AttributeOwner::Detail, &self.detail_attributes),
+        ] {
+            for attr in attrs {
+                let size = attr.tuple_size;
+                // Vertex data must follow the mesh vertex order
+                let reorder = |n: usize| -> Vec<usize> {
+                    match owner {
+                        AttributeOwner::Vertex => vertex_order.clone(),
+                        _ => (0..n).collect(),
+                    }
+                };
+                let count = self.element_count(owner);
+                let channel = match (attr.name.as_str(), owner) {
+                    ("P", AttributeOwner::Point) => continue,
+                    (name @ ("N" | "uv" | "Cd"), owner) if owner != AttributeOwner::Detail => {
+                        attr.data.as_f64().map(|values| (name, values))
+                    }
+                    _ => None,
+                };
+                if let Some((name, values)) = channel {
+                    let values = reorder(count)
+                        .into_iter()
+                        .map(|e| {
+                            let t = &values[e * size..(e + 1) * size];
+                            let mut out = [0.0f32; 3];
+                            for (o, v) in out.iter_mut().zip(t) {
+                                *o = *v as f32;
+                            }
+                            out
+                        })
+                        .collect();
+                    let channel = Some(MeshChannel { owner, values });
+                    match name {
+                        "N" => mesh.normals = channel,
+                        "uv" => mesh.uvs = channel,
+                        _ => mesh.colors = channel,
+                    }
+                    continue;
+                }
+                let elements = reorder(count);
+                let values = match &attr.data {
+                    GeoAttributeData::Int { storage, values } => {
+                        let gathered = gather(values, size, &elements);
+                        if *storage == GeoStorage::Int64 {
+                            AttributeValues::Int64(gathered)
+                        } else {
+                            AttributeValues::Int(gathered.into_iter().map(|v| v as i32).collect())
+                        }
+                    }
+                    GeoAttributeData::Float { storage, values } => {
+                        let gathered = gather(values, size, &elements);
+                        if *storage == GeoStorage::Real64 {
+                            AttributeValues::Double(gathered)
+                        } else {
+                            AttributeValues::Float(gathered.into_iter().map(|v| v as f32).collect())
+                        }
+                    }
+                    GeoAttributeData::String { .. } => AttributeValues::String(
+                        elements
+                            .iter()
+                            .map(|e| attr.data.string(*e).unwrap_or_default().to_string())
+                            .collect(),
+                    ),
+                    GeoAttributeData::Raw { .. } => continue,
+                };
+                mesh.attributes.push(MeshAttribute {
+                    name: attr.name.clone(),
+                    owner,
+                    tuple_size: size as i32,
+                    values,
+                });
+            }
+        }
+        for (group_type, groups) in [
+            (GroupType::Point, &self.point_groups),
+            (GroupType::Prim, &self.primitive_groups),
+        ] {
+            for group in groups {
+                mesh.groups.push(MeshGroup {
+                    name: group.name.clone(),
+                    group_type,
+                    members: group.members().map(|m| m as i32).collect(),
+                });
+            }
+        }
+        Ok(mesh)
+    }
+}
+
+fn gather<T: Copy>(values: &[T], tuple_size: usize, elements: &[usize]) -> Vec<T> {
+    let mut out = Vec::with_capacity(elements.len() * tuple_size);
+    for e in elements {
+        out.extend_from_slice(&values[e * tuple_size..(e + 1) * tuple_size]);
+    }
+    out
+}
+
+// Widen without introducing float noise, so 0.1f32 is written as 0.1.
+pub(crate) fn f32_to_f64(v: f32) -> f64 {
+    if v.is_finite() {
+        v.to_string().parse().unwrap_or(v as f64)
+    } else {
+        v as f64
+    }
+}
+
+impl Geometry {
+    /// Save the node geometry in memory and parse it into a [`GeoFile`].
+    pub fn read_geo_file(&self) -> Result<GeoFile> {
+        let bytes = self.save_to_memory(GeoFormat::Geo)?;
+        // SAFETY: i8 and u8 have the same layout
+        let bytes = unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const u8, bytes.len()) };
+        GeoFile::from_bytes(bytes)
+    }
+
+    /// Load a [`GeoFile`] into this (input) geometry.
+    pub fn load_geo_file(&self, geo: &GeoFile) -> Result<()> {
+        let text = geo.to_json_string();
+        // SAFETY: i8 and u8 have the same layout
+        let bytes = unsafe { std::slice::from_raw_parts(text.as_ptr() as *const i8, text.len()) };
+        self.load_from_memory(bytes, GeoFormat::Geo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Quad + triangle as polygon run, an open polyline and a packed primitive
+    const SAMPLE: &str = r#"[
+        "fileversion","20.0.625","hasindex",false,"pointcount",5,"vertexcount",8,"primitivecount",4,
+        "info",{"software":"Houdini 20.0.625"},
+        "topology",["pointref",["indices",[0,1,2,3,1,4,2,0]]],
+        "attributes",[
+            "pointattributes",[
+                [["scope","public","type","numeric","name","P","options",{"type":{"type":"string","value":"point"}}],
+                 ["size",3,"storage","fpreal32","defaults",["size",1,"storage","fpreal64","values",[0]],
+                  "values",["size",3,"storage","fpreal32","tuples",[[0,0,0],[1,0,0],[1,1,0],[0,1,0],[2,0,0]]]]],
+                [["scope","public","type","numeric","name","id"],
+                 ["size",1,"storage","int32","values",["size",1,"storage","int32","arrays",[[5,6,7,8,9]]]]],
+                [["scope","public","type","numeric","name","w"],
+                 ["size",2,"storage","fpreal32","values",["size",2,"storage","fpreal32","pagesize",2,
+                  "packing",[1,1],"constantpageflags",[[true,false,false],[false,false,true]],
+                  "rawpagedata",[0.5, 1,2, 9,9, 3,4, 10, 5]]]]
+            ],
+            "primitiveattributes",[
+                [["scope","public","type","string","name","name"],
+                 ["size",1,"storage","int32","strings",["a","b"],
+                  "indices",["size",1,"storage","int32","arrays",[[0,1,1,-1]]]]]
+            ],
+            "globalattributes",[
+                [["scope","public","type","arraydata","name","list"],["size",1,"storage","int32","values",[1,2]]]
+            ]
+        ],
+        "primitives",[
+            [["type","Polygon_run"],["startvertex",0,"nprimitives",2,"nvertices_rle",[4,1,3,1]]],
+            [["type","run","runtype","Poly","varyingfields",["vertex"],"uniformfields",{"closed":false}],[[[7]]]],
+            [["type","PackedDisk"],["vertex",0,"filename","foo.bgeo"]]
+        ],
+        "pointgroups",[
+            [["name","corner"],["selection",["unordered",["i8",[1,0,0,0,1]]]]]
+        ],
+        "primitivegroups",[
+            [["name","faces"],["selection",["unordered",["boolRLE",[2,true,2,false]]]]]
+        ]
+    ]"#;
+
+    #[test]
+    fn geo_read_sample() {
+        let geo = GeoFile::from_json_str(SAMPLE).unwrap();
+        geo.validate().unwrap();
+        assert_eq!(geo.point_count, 5);
+        assert_eq!(geo.primitive_count(), 4);
+        assert_eq!(
+            geo.primitives[1],
+            GeoPrimitive::Polygon {
+                vertices: vec![4, 5, 6],
+                closed: true
+            }
+        );
+        assert_eq!(
+            geo.primitives[2],
+            GeoPrimitive::Polygon {
+                vertices: vec![7],
+                closed: false
+            }
+        );
+        assert_eq!(geo.primitives[3].type_name(), "PackedDisk");
+        assert_eq!(geo.primitives[3].vertices(), [0]);
+        let id = geo.attribute(AttributeOwner::Point, "id").unwrap();
+        assert_eq!(id.data.as_i64(), Some(&[5, 6, 7, 8, 9][..]));
+        let w = geo.attribute(AttributeOwner::Point, "w").unwrap();
+        assert_eq!(
+            w.data.as_f64(),
+            Some(&[0.5, 1.0, 0.5, 2.0, 9.0, 3.0, 9.0, 4.0, 10.0, 5.0][..])
+        );
+        let name = geo.attribute(AttributeOwner::Prim, "name").unwrap();
+        assert_eq!(name.data.string(1), Some("b"));
+        assert_eq!(name.data.string(3), None);
+        assert!(matches!(
+            geo.detail_attributes[0].data,
+            GeoAttributeData::Raw { .. }
+        ));
+        assert_eq!(geo.point_groups[0].members().collect::<Vec<_>>(), [0, 4]);
+        assert_eq!(
+            geo.primitive_groups[0].members().collect::<Vec<_>>(),
+            [0, 1]
+        );
+    }
+
+    #[test]
+    fn geo_reject_invalid_counts() {
+        let polygons = |rle: &str| {
+            let text = SAMPLE.replacen("[4,1,3,1]", rle, 1);
+            GeoFile::from_json_str(&text)
+        };
+        assert!(polygons("[4,1,3,-1]").is_err());
+        assert!(polygons("[4,1,3,9223372036854775807]").is_err());
+        assert!(polygons("[-4,1,3,1]").is_err());
+        let groups = SAMPLE.replacen("[2,true,2,false]", "[-2,true,2,false]", 1);
+        assert!(GeoFile::from_json_str(&groups).is_err());
+        // Huge counts must fail before allocating
+        for (from, to) in [
+            ("\"pointcount\",5", "\"pointcount\",1000000000000"),
+            ("\"pagesize\",2", "\"pagesize\",1000000000000"),
+            (
+                "\"size\",2,\"storage\"",
+                "\"size\",4611686018427387904,\"storage\"",
+            ),
+            (
+                "\"nprimitives\",2,\"nvertices_rle\",[4,1,3,1]",
+                "\"nprimitives\",1000000000000,\"nvertices_rle\",[0,1000000000000]",
+            ),
+        ] {
+            assert!(GeoFile::from_json_str(&SAMPLE.replacen(from, to, 1)).is_err());
+        }
+
+        let mut geo = GeoFile::from_json_str(SAMPLE).unwrap();
+        geo.point_attribute_mut("P").unwrap().tuple_size = 2;
+        assert_eq!(geo.positions(), None);
+        assert!(geo.attributes_mut(AttributeOwner::Invalid).is_none());
+    }
+
+    #[test]
+    fn geo_roundtrip() {
+        let geo = GeoFile::from_json_str(SAMPLE).unwrap();
+        let text = geo.to_json_string();
+        let again = GeoFile::from_json_str(&text).unwrap();
+        assert_eq!(geo, again);
+    }
+
+    #[test]
+    fn geo_mesh_conversion() {
+        let mesh = Mesh::new(
+            vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.1, 1.0, 0.0],
+            ],
+            vec![3, 3],
+            vec![0, 1, 2, 0, 2, 3],
+        )
+        .with_uvs(AttributeOwner::Vertex, vec![[0.5, 0.25, 0.0]; 6])
+        .with_attribute(
+            "name",
+            AttributeOwner::Prim,
+            1,
+            AttributeValues::String(vec!["a".into(), "b".into()]),
+        )
+        .with_group("second", GroupType::Prim, vec![1]);
+        let geo = GeoFile::from_mesh(&mesh).unwrap();
+        assert!(geo.to_json_string().contains("0.1,1.0,0.0"));
+        let mut back = GeoFile::from_json_str(&geo.to_json_string()).unwrap();
+        assert_eq!(back.to_mesh().unwrap(), mesh);
+        if let GeoPrimitive::Polygon { closed, .. } = &mut back.primitives[1] {
+            *closed = false;
+        }
+        assert!(back.to_mesh().is_err());
+        let sample = GeoFile::from_json_str(SAMPLE).unwrap();
+        assert!(sample.to_mesh().is_err());
+    }
+}
@@ -0,0 +1,508 @@
+//! Conversion of a parsed `.geo` JSON document into [`GeoFile`]
+use super::json::Value;
+use super::{GeoAttribute, GeoAttributeData, GeoFile, GeoGroup, GeoPrimitive, GeoStorage};
+use crate::errors::{HapiError, Result};
+
+fn error(msg: impl Into<String>) -> HapiError {
+    HapiError::internal(format!("Invalid geo file: {}", msg.into()))
+}
+
+fn required<'a>(value: &'a Value, key: &str) -> Result<&'a Value> {
+    value
+        .get(key)
+        .ok_or_else(|| error(format!("missing \"{key}\"")))
+}
+
+fn usize_of(value: &Value, key: &str) -> Result<usize> {
+    required(value, key)?
+        .as_i64()
+        .and_then(|v| usize::try_from(v).ok())
+        .ok_or_else(|| error(format!("\"{key}\" is not a count")))
+}
+
+pub(super) fn from_value(doc: &Value) -> Result<GeoFile> {
+    let mut geo = GeoFile {
+        point_count: usize_of(doc, "pointcount")?,
+        info: doc.get("info").cloned(),
+        ..Default::default()
+    };
+    let vertex_count = usize_of(doc, "vertexcount")?;
+    let primitive_count = usize_of(doc, "primitivecount")?;
+
+    if let Some(topology) = doc.get("topology") {
+        let indices = required(required(topology, "pointref")?, "indices")?;
+        geo.vertices = indices
+            .to_i64_vec()
+            .ok_or_else(|| error("invalid point references"))?
+            .into_iter()
+            .map(|v| v as u32)
+            .collect();
+    }
+    if geo.vertices.len() != vertex_count {
+        return Err(error(format!(
+            "expected {vertex_count} vertices, found {}",
+            geo.vertices.len()
+        )));
+    }
+
+    if let Some(attributes) = doc.get("attributes") {
+        for (key, count, target) in [
+            ("vertexattributes", vertex_count, &mut geo.vertex_attributes),
+            (
+                "pointattributes",
+                geo.point_count,
+                &mut geo.point_attributes,
+            ),
+            (
+                "primitiveattributes",
+                primitive_count,
+                &mut geo.primitive_attributes,
+            ),
+            ("globalattributes", 1, &mut geo.detail_attributes),
+        ] {
+            let Some(list) = attributes.get(key).and_then(Value::as_array) else {
+                continue;
+            };
+            for attr in list {
+                target.push(parse_attribute(attr, count)?);
+            }
+        }
+    }
+
+    // Positions back the point count, attribute values are bounded by the file data
+    if geo.point_count > 0 && !geo.point_attributes.iter().any(|a| a.name == "P") {
+        return Err(error("points without a \"P\" attribute"));
+    }
+
+    if let Some(primitives) = doc.get("primitives").and_then(Value::as_array) {
+        for prim in primitives {
+            parse_primitive(prim, vertex_count, &mut geo.primitives)?;
+        }
+    }
+    if geo.primitives.len() != primitive_count {
+        return Err(error(format!(
+            "expected {primitive_count} primitives, found {}",
+            geo.primitives.len()
+        )));
+    }
+
+    // All counts are backed by parsed elements at this point
+    for (key, count, target) in [
+        ("pointgroups", geo.point_count, &mut geo.point_groups),
+        (
+            "primitivegroups",
+            primitive_count,
+            &mut geo.primitive_groups,
+        ),
+        ("vertexgroups", vertex_count, &mut geo.vertex_groups),
+    ] {
+        let Some(list) = doc.get(key).and_then(Value::as_array) else {
+            continue;
+        };
+        for group in list {
+            target.push(parse_group(group, count)?);
+        }
+    }
+    Ok(geo)
+}
+
+fn parse_attribute(attr: &Value, count: usize) -> Result<GeoAttribute> {
+    let (header, data) = match attr.as_array() {
+        Some([header, data]) => (header, data),
+        _ => return Err(error("attribute must be a [header, data] pair")),
+    };
+    let name = required(header, "name")?
+        .as_str()
+        .ok_or_else(|| error("attribute name is not a string"))?
+        .to_string();
+    let scope = header
+        .get("scope")
+        .and_then(Value::as_str)
+        .unwrap_or("public")
+        .to_string();
+    let type_name = required(header, "type")?.as_str().unwrap_or_default();
+    let type_info = header
+        .get("options")
+        .and_then(|o| o.get("type"))
+        .and_then(|t| t.get("value"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let tuple_size = data.get("size").and_then(Value::as_i64).unwrap_or(1).max(1) as usize;
+    let data = match type_name {
+        "numeric" => {
+            let storage = storage_of(data)?;
+            let values = required(data, "values")?;
+            numeric_values(values, storage, tuple_size, count)
+                .map_err(|e| error(format!("attribute \"{name}\": {e}")))?
+        }
+        "string" => {
+            let strings = required(data, "strings")?
+                .as_array()
+                .ok_or_else(|| error("strings is not an array"))?
+                .iter()
+                .map(|s| s.as_str().unwrap_or_default().to_string())
+                .collect();
+            let indices =
+                match numeric_values(required(data, "indices")?, GeoStorage::Int32, 1, count)
+                    .map_err(|e| error(format!("attribute \"{name}\": {e}")))?
+                {
+                    GeoAttributeData::Int { values, .. } => {
+                        values.into_iter().map(|v| v as i32).collect()
+                    }
+                    _ => unreachable!(),
+                };
+            GeoAttributeData::String { strings, indices }
+        }
+        other => GeoAttributeData::Raw {
+            type_name: other.to_string(),
+            data: data.clone(),
+        },
+    };
+    Ok(GeoAttribute {
+        name,
+        scope,
+        type_info,
+        tuple_size,
+        data,
+    })
+}
+
+fn storage_of(block: &Value) -> Result<GeoStorage> {
+    let name = required(block, "storage")?.as_str().unwrap_or_default();
+    GeoStorage::from_name(name).ok_or_else(|| error(format!("unknown storage \"{name}\"")))
+}
+
+/// Decode a values block into flat tuples. The block can store values as `tuples`,
+/// as per-component `arrays` or as paged `rawpagedata`.
+pub(super) fn numeric_values(
+    block: &Value,
+    storage: GeoStorage,
+    tuple_size: usize,
+    count: usize,
+) -> std::result::Result<GeoAttributeData, String> {
+    let storage = block
+        .get("storage")
+        .and_then(Value::as_str)
+        .and_then(GeoStorage::from_name)
+        .unwrap_or(storage);
+    if storage.is_float() {
+        decode(block, tuple_size, count, Value::as_f64)
+            .map(|values| GeoAttributeData::Float { storage, values })
+    } else {
+        decode(block, tuple_size, count, Value::as_i64)
+            .map(|values| GeoAttributeData::Int { storage, values })
+    }
+}
+
+// Houdini writes pages of 1024 elements.
+const MAX_PAGE_SIZE: usize = 1 << 16;
+
+fn decode<T: Copy + Default>(
+    block: &Value,
+    tuple_size: usize,
+    count: usize,
+    conv: fn(&Value) -> Option<T>,
+) -> std::result::Result<Vec<T>, String> {
+    let numbers = |v: &Value| -> std::result::Result<Vec<T>, String> {
+        let convert = |v: Value| conv(&v).ok_or_else(|| "expected a number".to_string());
+        match v {
+            Value::IntArray(items) => items.iter().map(|v| convert(Value::Int(*v))).collect(),
+            Value::FloatArray(items) => items.iter().map(|v| convert(Value::Float(*v))).collect(),
+            _ => v
+                .as_array()
+                .ok_or("expected an array")?
+                .iter()
+                .map(|v| conv(v).ok_or_else(|| "expected a number".to_string()))
+                .collect(),
+        }
+    };
+    let len = count
+        .checked_mul(tuple_size)
+        .ok_or("attribute size overflows")?;
+    // Counts come from the file, only allocate what the data can fill
+    let alloc = |available: usize| -> std::result::Result<Vec<T>, String> {
+        if len > available {
+            return Err(format!("expected {len} values, found {available}"));
+        }
+        Ok(vec![T::default(); len])
+    };
+    let out = if let Some(tuples) = block.get("tuples") {
+        let tuples = tuples.as_array().ok_or("tuples is not an array")?;
+        if tuples.len() != count {
+            return Err(format!("expected {count} tuples, found {}", tuples.len()));
+        }
+        let tuples = tuples
+            .iter()
+            .map(numbers)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut out = alloc(tuples.iter().map(Vec::len).sum())?;
+        for (e, tuple) in tuples.iter().enumerate() {
+            if tuple.len() != tuple_size {
+                return Err(format!("tuple {e} has wrong size"));
+            }
+            out[e * tuple_size..(e + 1) * tuple_size].copy_from_slice(tuple);
+        }
+        out
+    } else if let Some(arrays) = block.get("arrays") {
+        let arrays = arrays.as_array().ok_or("arrays is not an array")?;
+        if arrays.len() != tuple_size {
+            return Err(format!("expected {tuple_size} arrays"));
+        }
+        let arrays = arrays
+            .iter()
+            .map(numbers)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut out = alloc(arrays.iter().map(Vec::len).sum())?;
+        for (c, array) in arrays.into_iter().enumerate() {
+            if array.len() != count {
+                return Err(format!("expected {count} values, found {}", array.len()));
+            }
+            for (e, v) in array.into_iter().enumerate() {
+                out[e * tuple_size + c] = v;
+            }
+        }
+        out
+    } else if let Some(raw) = block.get("rawpagedata") {
+        let data = numbers(raw)?;
+        let page_size = block
+            .get("pagesize")
+            .and_then(Value::as_i64)
+            .unwrap_or(1024)
+            .max(1) as usize;
+        if page_size > MAX_PAGE_SIZE {
+            return Err(format!("unsupported page size {page_size}"));
+        }
+        // A constant page repeats its values for all elements of the page
+        let mut out = alloc(data.len().saturating_mul(page_size))?;
+        let packing: Vec<usize> = match block.get("packing").and_then(Value::to_i64_vec) {
+            Some(p) => p
+                .into_iter()
+                .map(usize::try_from)
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|_| "negative packing".to_string())?,
+            None => vec![tuple_size],
+        };
+        if packing.iter().try_fold(0usize, |a, p| a.checked_add(*p)) != Some(tuple_size) {
+            return Err("packing doesn't match tuple size".to_string());
+        }
+        let constant: Vec<Vec<bool>> = match block.get("constantpageflags") {
+            Some(Value::Array(flags)) => flags
+                .iter()
+                .map(|f| {
+                    f.as_array()
+                        .map(|f| f.iter().map(|v| v.as_bool().unwrap_or(false)).collect())
+                        .unwrap_or_default()
+                })
+                .collect(),
+            _ => vec![],
+        };
+        let mut cursor = 0;
+        let mut take = |n: usize| -> std::result::Result<&[T], String> {
+            let slice = data
+                .get(cursor..cursor + n)
+                .ok_or("rawpagedata is too short")?;
+            cursor += n;
+            Ok(slice)
+        };
+        for page_start in (0..count).step_by(page_size) {
+            let page = page_start / page_size;
+            let page_len = page_size.min(count - page_start);
+            let mut comp = 0;
+            for (sub, sub_size) in packing.iter().enumerate() {
+                let is_constant = constant
+                    .get(sub)
+                    .and_then(|f| f.get(page))
+                    .copied()
+                    .unwrap_or(false);
+                if is_constant {
+                    let values = take(*sub_size)?;
+                    for e in page_start..page_start + page_len {
+                        out[e * tuple_size + comp..e * tuple_size + comp + sub_size]
+                            .copy_from_slice(values);
+                    }
+                } else {
+                    let values = take(page_len * sub_size)?;
+                    for (i, e) in (page_start..page_start + page_len).enumerate() {
+                        out[e * tuple_size + comp..e * tuple_size + comp + sub_size]
+                            .copy_from_slice(&values[i * sub_size..(i + 1) * sub_size]);
+                    }
+                }
+                comp += sub_size;
+            }
+        }
+        out
+    } else if count > 0 {
+        return Err("no tuples, arrays or rawpagedata".to_string());
+    } else {
+        vec![]
+    };
+    Ok(out)
+}
+
+fn vertex_list(value: &Value) -> Option<Vec<u32>> {
+    match value {
+        Value::Array(_) | Value::IntArray(_) | Value::FloatArray(_) => value
+            .to_i64_vec()
+            .map(|v| v.into_iter().map(|v| v as u32).collect()),
+        v => v.as_i64().map(|v| vec![v as u32]),
+    }
+}
+
+fn parse_primitive(prim: &Value, vertex_count: usize, out: &mut Vec<GeoPrimitive>) -> Result<()> {
+    let (header, body) = match prim.as_array() {
+        Some([header, body]) => (header, body),
+        _ => return Err(error("primitive must be a [header, data] pair")),
+    };
+    let type_name = required(header, "type")?.as_str().unwrap_or_default();
+    match type_name {
+        "Poly" => {
+            let vertices = vertex_list(required(body, "vertex")?)
+                .ok_or_else(|| error("invalid polygon vertices"))?;
+            let closed = body.get("closed").and_then(Value::as_bool).unwrap_or(true);
+            out.push(GeoPrimitive::Polygon { vertices, closed });
+        }
+        "Polygon_run" | "PolygonCurve_run" => {
+            let start = u32::try_from(usize_of(body, "startvertex")?)
+                .map_err(|_| error("invalid startvertex"))?;
+            let count = usize_of(body, "nprimitives")?;
+            // Each polygon of a run uses at least one vertex
+            if count > vertex_count {
+                return Err(error("polygon run exceeds the vertex count"));
+            }
+            let closed = body
+                .get("closed")
+                .and_then(Value::as_bool)
+                .unwrap_or(type_name == "Polygon_run");
+            let sizes: Vec<i64> = if let Some(rle) = body.get("nvertices_rle") {
+                let rle = rle
+                    .to_i64_vec()
+                    .ok_or_else(|| error("invalid nvertices_rle"))?;
+                let mut sizes = vec![];
+                for c in rle.chunks_exact(2) {
+                    let n = usize::try_from(c[1])
+                        .map_err(|_| error("negative count in nvertices_rle"))?;
+                    if n > count - sizes.len() {
+                        return Err(error("polygon run size mismatch"));
+                    }
+                    sizes.extend(std::iter::repeat_n(c[0], n));
+                }
+                sizes
+            } else {
+                required(body, "nvertices")?
+                    .to_i64_vec()
+                    .ok_or_else(|| error("invalid nvertices"))?
+            };
+            if sizes.len() != count {
+                return Err(error("polygon run size mismatch"));
+            }
+            let mut offset = start;
+            for size in sizes {
+                let size = u32::try_from(size).map_err(|_| error("negative polygon size"))?;
+                let end = offset
+                    .checked_add(size)
+                    .ok_or_else(|| error("polygon run vertex overflow"))?;
+                out.push(GeoPrimitive::Polygon {
+                    vertices: (offset..end).collect(),
+                    closed,
+                });
+                offset = end;
+            }
+        }
+        "run" => {
+            let run_type = required(header, "runtype")?.as_str().unwrap_or_default();
+            let fields: Vec<&str> = required(header, "varyingfields")?
+                .as_array()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(Value::as_str)
+                .collect();
+            let uniform = header.get("uniformfields");
+            let items = body
+                .as_array()
+                .ok_or_else(|| error("primitive run data is not an array"))?;
+            for item in items {
+                let values = item.as_array().unwrap_or_default();
+                let field = |name: &str| {
+                    fields
+                        .iter()
+                        .position(|f| *f == name)
+                        .and_then(|i| values.get(i))
+                        .or_else(|| uniform.and_then(|u| u.get(name)))
+                };
+                if run_type == "Poly" {
+                    let vertices = field("vertex")
+                        .and_then(vertex_list)
+                        .ok_or_else(|| error("invalid polygon vertices"))?;
+                    let closed = field("closed").and_then(Value::as_bool).unwrap_or(true);
+                    out.push(GeoPrimitive::Polygon { vertices, closed });
+                } else {
+                    let mut body = vec![];
+                    for (name, value) in fields.iter().zip(values) {
+                        body.push(Value::from(*name));
+                        body.push(value.clone());
+                    }
+                    for (name, value) in uniform.map(|u| u.entries()).into_iter().flatten() {
+                        body.push(Value::from(name));
+                        body.push(value.clone());
+                    }
+                    let body = Value::Array(body);
+                    out.push(GeoPrimitive::Other {
+                        type_name: run_type.to_string(),
+                        vertices: body.get("vertex").and_then(vertex_list).unwrap_or_default(),
+                        header: Value::kv([("type", Value::from(run_type))]),
+                        body,
+                    });
+                }
+            }
+        }
+        other => out.push(GeoPrimitive::Other {
+            type_name: other.to_string(),
+            vertices: body.get("vertex").and_then(vertex_list).unwrap_or_default(),
+            header: header.clone(),
+            body: body.clone(),
+        }),
+    }
+    Ok(())
+}
+
+fn parse_group(group: &Value, count: usize) -> Result<GeoGroup> {
+    let (header, body) = match group.as_array() {
+        Some([header, body]) => (header, body),
+        _ => return Err(error("group must be a [header, data] pair")),
+    };
+    let name = required(header, "name")?
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let selection = required(body, "selection")?;
+    let mut membership = vec![false; count];
+    if let Some(unordered) = selection.get("unordered") {
+        if let Some(flags) = unordered.get("i8").and_then(Value::to_i64_vec) {
+            for (m, f) in membership.iter_mut().zip(flags) {
+                *m = f != 0;
+            }
+        } else if let Some(rle) = unordered.get("boolRLE").and_then(Value::as_array) {
+            let mut idx = 0;
+            for run in rle.chunks_exact(2) {
+                let n = usize::try_from(run[0].as_i64().unwrap_or(0))
+                    .map_err(|_| error(format!("negative count in group \"{name}\"")))?;
+                let on = run[1].as_bool().unwrap_or(false);
+                for m in membership.iter_mut().skip(idx).take(n) {
+                    *m = on;
+                }
+                idx = idx.saturating_add(n);
+            }
+        } else {
+            return Err(error(format!("unsupported selection in group \"{name}\"")));
+        }
+    } else if let Some(ordered) = selection.get("ordered").and_then(Value::to_i64_vec) {
+        for idx in ordered {
+            if let Some(m) = membership.get_mut(idx as usize) {
+                *m = true;
+            }
+        }
+    } else {
+        return Err(error(format!("unsupported selection in group \"{name}\"")));
+    }
+    Ok(GeoGroup { name, membership })
+}
@@ -0,0 +1,223 @@
+//! Conversion of [`GeoFile`] into a `.geo` JSON document
+use super::json::Value;
+use super::{GeoAttribute, GeoAttributeData, GeoFile, GeoGroup, GeoPrimitive};
+
+// Oldest Houdini version supported by this crate.
+const FILE_VERSION: &str = "20.0.625";
+
+pub(super) fn to_value(geo: &GeoFile) -> Value {
+    let mut doc = vec![
+        ("fileversion", Value::from(FILE_VERSION)),
+        ("hasindex", Value::Bool(false)),
+        ("pointcount", Value::Int(geo.point_count as i64)),
+        ("vertexcount", Value::Int(geo.vertex_count() as i64)),
+        ("primitivecount", Value::Int(geo.primitive_count() as i64)),
+    ];
+    if let Some(info) = &geo.info {
+        doc.push(("info", info.clone()));
+    }
+    doc.push((
+        "topology",
+        Value::kv([(
+            "pointref",
+            Value::kv([(
+                "indices",
+                Value::Array(geo.vertices.iter().map(|v| Value::Int(*v as i64)).collect()),
+            )]),
+        )]),
+    ));
+
+    let mut attributes = vec![];
+    for (key, attrs) in [
+        ("vertexattributes", &geo.vertex_attributes),
+        ("pointattributes", &geo.point_attributes),
+        ("primitiveattributes", &geo.primitive_attributes),
+        ("globalattributes", &geo.detail_attributes),
+    ] {
+        if !attrs.is_empty() {
+            attributes.push((key, Value::Array(attrs.iter().map(attribute).collect())));
+        }
+    }
+    if !attributes.is_empty() {
+        doc.push(("attributes", Value::kv(attributes)));
+    }
+    doc.push(("primitives", Value::Array(primitives(&geo.primitives))));
+    for (key, groups) in [
+        ("pointgroups", &geo.point_groups),
+        ("primitivegroups", &geo.primitive_groups),
+        ("vertexgroups", &geo.vertex_groups),
+    ] {
+        if !groups.is_empty() {
+            doc.push((key, Value::Array(groups.iter().map(group).collect())));
+        }
+    }
+    Value::kv(doc)
+}
+
+fn attribute(attr: &GeoAttribute) -> Value {
+    let type_name = match &attr.data {
+        GeoAttributeData::Int { .. } | GeoAttributeData::Float { .. } => "numeric",
+        GeoAttributeData::String { .. } => "string",
+        GeoAttributeData::Raw { type_name, .. } => type_name,
+    };
+    let mut header = vec![
+        ("scope", Value::from(attr.scope.as_str())),
+        ("type", Value::from(type_name)),
+        ("name", Value::from(attr.name.as_str())),
+    ];
+    if let Some(type_info) = &attr.type_info {
+        header.push((
+            "options",
+            Value::Object(vec![(
+                "type".to_string(),
+                Value::Object(vec![
+                    ("type".to_string(), Value::from("string")),
+                    ("value".to_string(), Value::from(type_info.as_str())),
+                ]),
+            )]),
+        ));
+    }
+    let size = attr.tuple_size;
+    let data = match &attr.data {
+        GeoAttributeData::Int { storage, values } => {
+            let values: Vec<Value> = values.iter().map(|v| Value::Int(*v)).collect();
+            Value::kv([
+                ("size", Value::Int(size as i64)),
+                ("storage", Value::from(storage.name())),
+                ("defaults", defaults(storage.name(), Value::Int(0))),
+                ("values", values_block(storage.name(), size, values)),
+            ])
+        }
+        GeoAttributeData::Float { storage, values } => {
+            let values: Vec<Value> = values.iter().map(|v| Value::Float(*v)).collect();
+            Value::kv([
+                ("size", Value::Int(size as i64)),
+                ("storage", Value::from(storage.name())),
+                ("defaults", defaults("fpreal64", Value::Float(0.0))),
+                ("values", values_block(storage.name(), size, values)),
+            ])
+        }
+        GeoAttributeData::String { strings, indices } => {
+            let indices: Vec<Value> = indices.iter().map(|v| Value::Int(*v as i64)).collect();
+            Value::kv([
+                ("size", Value::Int(1)),
+                ("storage", Value::from("int32")),
+                (
+                    "strings",
+                    Value::Array(strings.iter().map(|s| Value::from(s.as_str())).collect()),
+                ),
+                ("indices", values_block("int32", 1, indices)),
+            ])
+        }
+        GeoAttributeData::Raw { data, .. } => data.clone(),
+    };
+    Value::Array(vec![Value::kv(header), data])
+}
+
+fn defaults(storage: &str, value: Value) -> Value {
+    Value::kv([
+        ("size", Value::Int(1)),
+        ("storage", Value::from(storage)),
+        ("values", Value::Array(vec![value])),
+    ])
+}
+
+// Single component values are written as one array, tuples otherwise.
+fn values_block(storage: &str, size: usize, values: Vec<Value>) -> Value {
+    let data = if size == 1 {
+        ("arrays", Value::Array(vec![Value::Array(values)]))
+    } else {
+        let mut values = values.into_iter();
+        let count = values.len() / size.max(1);
+        let tuples = (0..count)
+            .map(|_| Value::Array(values.by_ref().take(size).collect()))
+            .collect();
+        ("tuples", Value::Array(tuples))
+    };
+    Value::kv([
+        ("size", Value::Int(size as i64)),
+        ("storage", Value::from(storage)),
+        data,
+    ])
+}
+
+// Closed polygons with consecutive vertices are written as runs, everything else one by one.
+fn primitives(prims: &[GeoPrimitive]) -> Vec<Value> {
+    let mut out = vec![];
+    let mut run: Option<(u32, Vec<i64>)> = None;
+    let flush = |run: &mut Option<(u32, Vec<i64>)>, out: &mut Vec<Value>| {
+        if let Some((start, sizes)) = run.take() {
+            let mut rle = vec![];
+            for size in sizes {
+                match rle.as_mut_slice() {
+                    [.., last, count] if *last == size => *count += 1,
+                    _ => rle.extend([size, 1]),
+                }
+            }
+            let count: i64 = rle.chunks_exact(2).map(|c| c[1]).sum();
+            out.push(Value::Array(vec![
+                Value::kv([("type", Value::from("Polygon_run"))]),
+                Value::kv([
+                    ("startvertex", Value::Int(start as i64)),
+                    ("nprimitives", Value::Int(count)),
+                    ("nvertices_rle", Value::from(rle)),
+                ]),
+            ]));
+        }
+    };
+    for prim in prims {
+        match prim {
+            GeoPrimitive::Polygon { vertices, closed } => {
+                let consecutive =
+                    !vertices.is_empty() && vertices.windows(2).all(|w| w[1] == w[0] + 1);
+                if *closed && consecutive {
+                    let next_start = run
+                        .as_ref()
+                        .map(|(start, sizes)| *start + sizes.iter().sum::<i64>() as u32);
+                    match (&mut run, next_start) {
+                        (Some((_, sizes)), Some(next)) if next == vertices[0] => {
+                            sizes.push(vertices.len() as i64)
+                        }
+                        _ => {
+                            flush(&mut run, &mut out);
+                            run = Some((vertices[0], vec![vertices.len() as i64]));
+                        }
+                    }
+                    continue;
+                }
+                flush(&mut run, &mut out);
+                out.push(Value::Array(vec![
+                    Value::kv([("type", Value::from("Poly"))]),
+                    Value::kv([
+                        (
+                            "vertex",
+                            Value::Array(vertices.iter().map(|v| Value::Int(*v as i64)).collect()),
+                        ),
+                        ("closed", Value::Bool(*closed)),
+                    ]),
+                ]));
+            }
+            GeoPrimitive::Other { header, body, .. } => {
+                flush(&mut run, &mut out);
+                out.push(Value::Array(vec![header.clone(), body.clone()]));
+            }
+        }
+    }
+    flush(&mut run, &mut out);
+    out
+}
+
+fn group(group: &GeoGroup) -> Value {
+    let flags: Vec<Value> = group
+        .membership
+        .iter()
+        .map(|m| Value::Int(*m as i64))
+        .collect();
+    Value::Array(vec![
+        Value::kv([("name", Value::from(group.name.as_str()))]),
+        Value::kv([(
+            "selection",
+            Value::kv([("unordered", Value::kv([("i8", Value::Array(flags))]))]),
+        )]),
+    ])
+}
@@ -0,0 +1,367 @@
+//! Input geometry nodes managed by Rust
+//!
+//! [`Session::create_input_node`] returns a bare [`Geometry`], which must be connected, committed
+//! and deleted by the caller. [`InputNode`] owns the node instead: it remembers which asset inputs it
+//! feeds, reconnects them on commit and deletes the node (and its container Object) when dropped.
+//!
+//! [`InputCurve`] wraps an input curve node with a list of control points and pushes edits to it.
+use log::{debug, error};
+
+use super::Geometry;
+use crate::curve::Curves;
+use crate::errors::{HapiError, Result};
+use crate::ffi::enums::CurveType;
+use crate::ffi::{InputCurveInfo, TransformEuler};
+use crate::node::{HoudiniNode, NodeType};
+use crate::session::Session;
+
+/// An input geometry node which is deleted when dropped.
+#[derive(Debug)]
+pub struct InputNode {
+    geometry: Geometry,
+    connections: Vec<(HoudiniNode, i32)>,
+    dirty: bool,
+}
+
+impl InputNode {
+    /// Create a new input node. See [`Session::create_input_node`].
+    pub fn new(session: &Session, name: &str) -> Result<Self> {
+        Ok(Self::from_geometry(session.create_input_node(name)?))
+    }
+
+    /// Create a new input curve node. See [`Session::create_input_curve_node`].
+    pub fn new_curve(session: &Session, name: &str) -> Result<Self> {
+        Ok(Self::from_geometry(session.create_input_curve_node(name)?))
+    }
+
+    // Only for nodes created with `Session::create_input_node` or `create_input_curve_node`:
+    // drop deletes the parent Object.
+    pub(crate) fn from_geometry(geometry: Geometry) -> Self {
+        InputNode {
+            geometry,
+            connections: vec![],
+            dirty: true,
+        }
+    }
+
+    /// Geometry for reading back the node data.
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    /// Geometry for editing, marks the node data as changed.
+    pub fn edit(&mut self) -> &Geometry {
+        self.dirty = true;
+        &self.geometry
+    }
+
+    /// The input SOP node.
+    pub fn node(&self) -> &HoudiniNode {
+        &self.geometry.node
+    }
+
+    /// Whether the geometry was edited since the last [`InputNode::commit`].
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Mark the geometry as changed, e.g. after editing it through [`InputNode::geometry`].
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Set the transform of the Object containing the input SOP.
+    /// This is what an Object Merge node with "Into This Object" picks up.
+    pub fn set_object_transform(&self, transform: &TransformEuler) -> Result<()> {
+        let object = self
+            .geometry
+            .node
+            .parent_node()
+            .map(|parent| parent.to_node(&self.geometry.node.session))
+            .transpose()?
+            .filter(|parent| parent.info.node_type() == NodeType::Obj)
+            .ok_or_else(|| HapiError::internal("Input node has no parent Object"))?;
+        object.set_transform(transform)
+    }
+
+    /// Connect this node into an asset input, replacing whatever was connected there.
+    /// The connection is remembered and restored on every [`InputNode::commit`].
+    pub fn connect_to(&mut self, asset: &HoudiniNode, input_index: i32) -> Result<()> {
+        asset.connect_input(input_index, self.geometry.node.handle, 0)?;
+        if !self
+            .connections
+            .iter()
+            .any(|(node, idx)| node.handle == asset.handle && *idx == input_index)
+        {
+            self.connections.push((asset.clone(), input_index));
+        }
+        Ok(())
+    }
+
+    /// Disconnect this node from an asset input.
+    pub fn disconnect_from(&mut self, asset: &HoudiniNode, input_index: i32) -> Result<()> {
+        self.connections
+            .retain(|(node, idx)| !(node.handle == asset.handle && *idx == input_index));
+        match asset.input_node(input_index)? {
+            Some(input) if input.handle == self.geometry.node.handle => {
+                asset.disconnect_input(input_index)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Asset inputs this node is connected to.
+    pub fn connections(&self) -> impl Iterator<Item = (&HoudiniNode, i32)> {
+        self.connections.iter().map(|(node, idx)| (node, *idx))
+    }
+
+    /// Commit the geometry if it was changed and restore connections to asset inputs,
+    /// which could have been replaced by another node.
+    pub fn commit(&mut self) -> Result<()> {
+        if self.dirty {
+            self.geometry.commit()?;
+            self.dirty = false;
+        }
+        self.reconnect()
+    }
+
+    /// Restore connections to asset inputs without committing the geometry.
+    pub fn reconnect(&mut self) -> Result<()> {
+        let handle = self.geometry.node.handle;
+        self.connections
+            .retain(|(asset, _)| asset.is_valid().unwrap_or(false));
+        for (asset, idx) in &self.connections {
+            match asset.input_node(*idx)? {
+                Some(input) if input.handle == handle => {}
+                _ => {
+                    debug!("Reconnecting input node to {}", asset.path()?);
+                    asset.connect_input(*idx, handle, 0)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Revert the last geometry edits.
+    pub fn revert(&mut self) -> Result<()> {
+        self.geometry.revert()?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+impl Drop for InputNode {
+    fn drop(&mut self) {
+        let node = &self.geometry.node;
+        if !node.is_valid().unwrap_or(false) {
+            return;
+        }
+        // Input SOPs live in a dummy Object, delete the whole thing.
+        let to_delete = node
+            .parent_node()
+            .filter(|p| matches!(p.info(&node.session), Ok(i) if i.node_type() == NodeType::Obj))
+            .unwrap_or(node.handle);
+        if let Err(e) = node.session.delete_node(to_delete) {
+            error!("Could not delete input node in Drop: {}", e);
+        }
+    }
+}
+
+/// A control point of an [`InputCurve`] with optional rotation (quaternion) and scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputCurvePoint {
+    pub position: [f32; 3],
+    pub rotation: Option<[f32; 4]>,
+    pub scale: Option<[f32; 3]>,
+}
+
+impl InputCurvePoint {
+    pub fn new(position: [f32; 3]) -> Self {
+        InputCurvePoint {
+            position,
+            rotation: None,
+            scale: None,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: [f32; 4]) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    pub fn with_scale(mut self, scale: [f32; 3]) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+}
+
+// Flat positions, plus rotations and scales if any point has them.
+fn flatten_points(points: &[InputCurvePoint]) -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+    let positions = points.iter().flat_map(|p| p.position).collect();
+    if points
+        .iter()
+        .all(|p| p.rotation.is_none() && p.scale.is_none())
+    {
+        return (positions, vec![], vec![]);
+    }
+    let rotations = points
+        .iter()
+        .flat_map(|p| p.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]))
+        .collect();
+    let scales = points
+        .iter()
+        .flat_map(|p| p.scale.unwrap_or([1.0; 3]))
+        .collect();
+    (positions, rotations, scales)
+}
+
+/// An input curve node with its control points and [`InputCurveInfo`].
+///
+/// Edits are kept in Rust and sent with [`InputCurve::push`], which only sends the parts
+/// that changed. The node is deleted on drop like [`InputNode`].
+#[derive(Debug)]
+pub struct InputCurve {
+    input: InputNode,
+    info: InputCurveInfo,
+    points: Vec<InputCurvePoint>,
+    info_dirty: bool,
+    points_dirty: bool,
+}
+
+impl InputCurve {
+    /// Create an input curve node, by default a cubic NURBS curve.
+    pub fn new(session: &Session, name: &str) -> Result<Self> {
+        let info = InputCurveInfo::default()
+            .with_curve_type(CurveType::Nurbs)
+            .with_order(4);
+        Ok(InputCurve {
+            input: InputNode::new_curve(session, name)?,
+            info,
+            points: vec![],
+            info_dirty: true,
+            points_dirty: true,
+        })
+    }
+
+    /// The owned input node, e.g. for connecting it to assets.
+    pub fn input(&mut self) -> &mut InputNode {
+        &mut self.input
+    }
+
+    pub fn node(&self) -> &HoudiniNode {
+        self.input.node()
+    }
+
+    pub fn info(&self) -> &InputCurveInfo {
+        &self.info
+    }
+
+    /// Replace the curve type, order, closed and reverse flags and input method.
+    pub fn set_info(&mut self, info: InputCurveInfo) {
+        self.info = info;
+        self.info_dirty = true;
+    }
+
+    pub fn set_curve_type(&mut self, curve_type: CurveType) {
+        self.info.set_curve_type(curve_type);
+        self.info_dirty = true;
+    }
+
+    pub fn set_order(&mut self, order: i32) {
+        self.info.set_order(order);
+        self.info_dirty = true;
+    }
+
+    pub fn set_closed(&mut self, closed: bool) {
+        self.info.set_closed(closed);
+        self.info_dirty = true;
+    }
+
+    pub fn set_reverse(&mut self, reverse: bool) {
+        self.info.set_reverse(reverse);
+        self.info_dirty = true;
+    }
+
+    pub fn points(&self) -> &[InputCurvePoint] {
+        &self.points
+    }
+
+    /// Mutable access to the control points, marks them as changed.
+    pub fn points_mut(&mut self) -> &mut Vec<InputCurvePoint> {
+        self.points_dirty = true;
+        &mut self.points
+    }
+
+    pub fn push_point(&mut self, point: InputCurvePoint) {
+        self.points_mut().push(point);
+    }
+
+    pub fn insert_point(&mut self, index: usize, point: InputCurvePoint) {
+        self.points_mut().insert(index, point);
+    }
+
+    pub fn remove_point(&mut self, index: usize) -> InputCurvePoint {
+        self.points_mut().remove(index)
+    }
+
+    /// Whether there are edits not yet sent with [`InputCurve::push`].
+    pub fn is_dirty(&self) -> bool {
+        self.info_dirty || self.points_dirty
+    }
+
+    /// Send changed curve info and points to the node and restore its asset connections.
+    /// Rotations and scales are sent only if a point has one; points without them get
+    /// an identity rotation and unit scale.
+    pub fn push(&mut self) -> Result<()> {
+        let geometry = &self.input.geometry;
+        if self.info_dirty {
+            geometry.set_input_curve_info(0, &self.info)?;
+            self.info_dirty = false;
+        }
+        if self.points_dirty {
+            if self.points.is_empty() {
+                return Err(HapiError::internal("Input curve has no points"));
+            }
+            let (positions, rotations, scales) = flatten_points(&self.points);
+            if rotations.is_empty() {
+                geometry.set_input_curve_positions(0, &positions)?;
+            } else {
+                geometry.set_input_curve_transform(0, &positions, &rotations, &scales)?;
+            }
+            self.points_dirty = false;
+        }
+        self.input.reconnect()
+    }
+
+    /// Push pending edits, cook the node and read back the resulting curves.
+    pub fn cooked_curves(&mut self) -> Result<Curves> {
+        self.push()?;
+        self.input.node().cook_blocking()?;
+        self.input.geometry.read_curves(0)
+    }
+
+    /// Curve info as stored on the node.
+    pub fn read_info(&self) -> Result<InputCurveInfo> {
+        self.input.geometry.get_input_curve_info(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_curve_point_buffers() {
+        let mut points = vec![
+            InputCurvePoint::new([0.0, 1.0, 2.0]),
+            InputCurvePoint::new([3.0, 4.0, 5.0]),
+        ];
+        let (positions, rotations, scales) = flatten_points(&points);
+        assert_eq!(positions, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert!(rotations.is_empty() && scales.is_empty());
+        points[1] = points[1].with_scale([2.0; 3]);
+        let (_, rotations, scales) = flatten_points(&points);
+        assert_eq!(rotations, [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(scales, [1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+    }
+}
@@ -1,6 +1,9 @@
 //! Access to geometry data, attributes, reading and writing geometry to and from disk
 //!
 //!
+mod input;
+mod schema;
+
 use std::ffi::{CStr, CString};
 
 use crate::attribute::*;
@@ -14,6 +17,8 @@ use crate::node::{HoudiniNode, NodeHandle};
 use crate::stringhandle::StringArray;
 use crate::utils::unwrap_or_create;
 use crate::volume::{Tile, VolumeBounds, VolumeStorage};
+pub use input::{InputCurve, InputCurvePoint, InputNode};
+pub use schema::{AttributeSchema, GeometrySchema, PartSchema};
 
 #[derive(Debug, Clone)]
 /// Represents a SOP node with methods for manipulating geometry.
@@ -75,6 +80,16 @@ impl Geometry {
         crate::ffi::set_volume_info(&self.node, part_id, &info.inner)
     }
 
+    /// How Houdini displays the volume: visualization type, iso value and density.
+    pub fn volume_visual_info(&self, part_id: i32) -> Result<VolumeVisualInfo> {
+        debug_assert!(
+            self.node.get_info()?.total_cook_count() > 0,
+            "Node not cooked"
+        );
+        crate::ffi::get_volume_visual_info(&self.node, part_id)
+            .map(|inner| VolumeVisualInfo { inner })
+    }
+
     pub fn volume_bounds(&self, part_id: i32) -> Result<VolumeBounds> {
         debug_assert!(
             self.node.get_info()?.total_cook_count() > 0,
@@ -734,6 +749,52 @@ impl Geometry {
         .map(|vec| vec.into_iter().map(|inner| Transform { inner }).collect())
     }
 
+    /// Group membership of the elements of a packed instance part.
+    pub fn get_instance_part_group_membership(
+        &self,
+        part_info: Option<&PartInfo>,
+        group_type: GroupType,
+        group_name: &str,
+    ) -> Result<Vec<i32>> {
+        debug_assert!(
+            self.node.get_info()?.total_cook_count() > 0,
+            "Node not cooked"
+        );
+        let tmp;
+        let part = unwrap_or_create!(tmp, part_info, self.part_info(0)?.expect("partition id=0"));
+        let group_name = CString::new(group_name)?;
+        crate::ffi::get_group_membership_on_instance_part(
+            &self.node.session,
+            self.node.handle,
+            part.part_id(),
+            group_type,
+            &group_name,
+            part.element_count_by_group(group_type),
+        )
+        .map(|(_, membership)| membership)
+    }
+
+    /// Transforms of the points of an object instancer's geometry, one per instance.
+    pub fn get_instance_transforms(
+        &self,
+        part_info: Option<&PartInfo>,
+        order: RSTOrder,
+    ) -> Result<Vec<Transform>> {
+        debug_assert!(
+            self.node.get_info()?.total_cook_count() > 0,
+            "Node not cooked"
+        );
+        let tmp;
+        let part = unwrap_or_create!(tmp, part_info, self.part_info(0)?.expect("partition id=0"));
+        crate::ffi::get_instance_transforms_on_part(
+            &self.node.session,
+            self.node.handle,
+            part.inner,
+            order,
+        )
+        .map(|vec| vec.into_iter().map(|inner| Transform { inner }).collect())
+    }
+
     /// Save geometry to a file.
     pub fn save_to_file(&self, filepath: &str) -> Result<()> {
         debug_assert!(
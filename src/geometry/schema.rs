@@ -0,0 +1,491 @@
+//! Summary of all parts, attributes and groups of a geometry
+use std::fmt;
+
+use super::Geometry;
+use crate::errors::{HapiError, Result};
+use crate::ffi::enums::{AttributeOwner, AttributeTypeInfo, GroupType, PartType, StorageType};
+use crate::geofile::json::{self, Value};
+
+const OWNERS: [AttributeOwner; 4] = [
+    AttributeOwner::Vertex,
+    AttributeOwner::Point,
+    AttributeOwner::Prim,
+    AttributeOwner::Detail,
+];
+
+const STORAGES: [StorageType; 18] = [
+    StorageType::Int,
+    StorageType::Int64,
+    StorageType::Float,
+    StorageType::Float64,
+    StorageType::String,
+    StorageType::Uint8,
+    StorageType::Int8,
+    StorageType::Int16,
+    StorageType::Dictionary,
+    StorageType::IntArray,
+    StorageType::Int64Array,
+    StorageType::FloatArray,
+    StorageType::Float64Array,
+    StorageType::StringArray,
+    StorageType::Uint8Array,
+    StorageType::Int8Array,
+    StorageType::Int16Array,
+    StorageType::DictionaryArray,
+];
+
+const TYPE_INFOS: [AttributeTypeInfo; 14] = [
+    AttributeTypeInfo::None,
+    AttributeTypeInfo::Point,
+    AttributeTypeInfo::Hpoint,
+    AttributeTypeInfo::Vector,
+    AttributeTypeInfo::Normal,
+    AttributeTypeInfo::Color,
+    AttributeTypeInfo::Quaternion,
+    AttributeTypeInfo::Matrix3,
+    AttributeTypeInfo::Matrix,
+    AttributeTypeInfo::St,
+    AttributeTypeInfo::Hidden,
+    AttributeTypeInfo::Box2,
+    AttributeTypeInfo::Box,
+    AttributeTypeInfo::Texture,
+];
+
+const PART_TYPES: [PartType; 6] = [
+    PartType::Mesh,
+    PartType::Curve,
+    PartType::Volume,
+    PartType::Instancer,
+    PartType::Box,
+    PartType::Sphere,
+];
+
+fn from_name<T: Copy + fmt::Debug>(all: &[T], name: &str) -> Option<T> {
+    all.iter().find(|v| format!("{v:?}") == name).copied()
+}
+
+/// Attribute description in a [`PartSchema`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeSchema {
+    pub name: String,
+    pub owner: AttributeOwner,
+    pub storage: StorageType,
+    pub tuple_size: i32,
+    pub type_info: AttributeTypeInfo,
+}
+
+/// Element counts and attributes of a single part.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartSchema {
+    pub part_id: i32,
+    pub name: String,
+    pub part_type: PartType,
+    pub point_count: i32,
+    pub vertex_count: i32,
+    pub face_count: i32,
+    pub instance_count: i32,
+    pub attributes: Vec<AttributeSchema>,
+}
+
+impl PartSchema {
+    /// Find an attribute by owner and name.
+    pub fn attribute(&self, owner: AttributeOwner, name: &str) -> Option<&AttributeSchema> {
+        self.attributes
+            .iter()
+            .find(|a| a.owner == owner && a.name == name)
+    }
+}
+
+/// Description of a geometry returned by [`Geometry::schema`].
+/// Group names are shared by all parts in Houdini, so they are stored once.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GeometrySchema {
+    pub parts: Vec<PartSchema>,
+    pub point_groups: Vec<String>,
+    pub prim_groups: Vec<String>,
+}
+
+impl GeometrySchema {
+    /// List all differences to an expected schema, empty if they are equal.
+    pub fn diff(&self, expected: &GeometrySchema) -> Vec<String> {
+        self.compare(expected, true)
+    }
+
+    /// Check that the geometry provides everything in the `expected` schema: parts of the same
+    /// type, attributes with the same storage, tuple size and type info, and groups.
+    /// Element counts and extra attributes or groups are ignored, which makes this suitable for
+    /// asserting asset output contracts.
+    pub fn check_contract(&self, expected: &GeometrySchema) -> Result<()> {
+        let errors = self.compare(expected, false);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(HapiError::internal(format!(
+                "Geometry doesn't match the expected schema:\n{}",
+                errors.join("\n")
+            )))
+        }
+    }
+
+    fn compare(&self, expected: &GeometrySchema, strict: bool) -> Vec<String> {
+        let mut out = vec![];
+        if strict && self.parts.len() != expected.parts.len() {
+            out.push(format!(
+                "part count: {} != {}",
+                self.parts.len(),
+                expected.parts.len()
+            ));
+        }
+        for (i, exp) in expected.parts.iter().enumerate() {
+            let Some(part) = self.parts.get(i) else {
+                out.push(format!("part {i}: missing"));
+                continue;
+            };
+            if part.part_type != exp.part_type {
+                out.push(format!(
+                    "part {i}: type {:?} != {:?}",
+                    part.part_type, exp.part_type
+                ));
+            }
+            if strict {
+                for (what, a, b) in [
+                    ("points", part.point_count, exp.point_count),
+                    ("vertices", part.vertex_count, exp.vertex_count),
+                    ("faces", part.face_count, exp.face_count),
+                    ("instances", part.instance_count, exp.instance_count),
+                ] {
+                    if a != b {
+                        out.push(format!("part {i}: {what} {a} != {b}"));
+                    }
+                }
+            }
+            for attr in &exp.attributes {
+                let label = format!("part {i}: {:?} attribute \"{}\"", attr.owner, attr.name);
+                match part.attribute(attr.owner, &attr.name) {
+                    None => out.push(format!("{label}: missing")),
+                    Some(found) if found != attr => out.push(format!(
+                        "{label}: {:?}[{}] {:?} != {:?}[{}] {:?}",
+                        found.storage,
+                        found.tuple_size,
+                        found.type_info,
+                        attr.storage,
+                        attr.tuple_size,
+                        attr.type_info
+                    )),
+                    Some(_) => {}
+                }
+            }
+            if strict {
+                for attr in &part.attributes {
+                    if exp.attribute(attr.owner, &attr.name).is_none() {
+                        out.push(format!(
+                            "part {i}: {:?} attribute \"{}\": unexpected",
+                            attr.owner, attr.name
+                        ));
+                    }
+                }
+            }
+        }
+        for (kind, found, exp) in [
+            ("point", &self.point_groups, &expected.point_groups),
+            ("primitive", &self.prim_groups, &expected.prim_groups),
+        ] {
+            for name in exp.iter().filter(|g| !found.contains(g)) {
+                out.push(format!("{kind} group \"{name}\": missing"));
+            }
+            if strict {
+                for name in found.iter().filter(|g| !exp.contains(g)) {
+                    out.push(format!("{kind} group \"{name}\": unexpected"));
+                }
+            }
+        }
+        out
+    }
+
+    /// Serialize as JSON.
+    pub fn to_json_string(&self) -> String {
+        let name = |v: &dyn fmt::Debug| Value::from(format!("{v:?}"));
+        let strings =
+            |v: &[String]| Value::Array(v.iter().map(|s| Value::from(s.as_str())).collect());
+        let parts = self
+            .parts
+            .iter()
+            .map(|p| {
+                let attributes = p
+                    .attributes
+                    .iter()
+                    .map(|a| {
+                        Value::Object(vec![
+                            ("name".into(), Value::from(a.name.as_str())),
+                            ("owner".into(), name(&a.owner)),
+                            ("storage".into(), name(&a.storage)),
+                            ("tuple_size".into(), Value::Int(a.tuple_size as i64)),
+                            ("type_info".into(), name(&a.type_info)),
+                        ])
+                    })
+                    .collect();
+                Value::Object(vec![
+                    ("part_id".into(), Value::Int(p.part_id as i64)),
+                    ("name".into(), Value::from(p.name.as_str())),
+                    ("part_type".into(), name(&p.part_type)),
+                    ("point_count".into(), Value::Int(p.point_count as i64)),
+                    ("vertex_count".into(), Value::Int(p.vertex_count as i64)),
+                    ("face_count".into(), Value::Int(p.face_count as i64)),
+                    ("instance_count".into(), Value::Int(p.instance_count as i64)),
+                    ("attributes".into(), Value::Array(attributes)),
+                ])
+            })
+            .collect();
+        json::to_string(&Value::Object(vec![
+            ("parts".into(), Value::Array(parts)),
+            ("point_groups".into(), strings(&self.point_groups)),
+            ("prim_groups".into(), strings(&self.prim_groups)),
+        ]))
+    }
+
+    /// Parse a schema written by [`GeometrySchema::to_json_string`].
+    pub fn from_json_str(text: &str) -> Result<Self> {
+        let doc = json::parse(text)?;
+        let error = |msg: &str| HapiError::internal(format!("Invalid geometry schema: {msg}"));
+        let int = |v: &Value, key: &str| {
+            v.get(key)
+                .and_then(Value::as_i64)
+                .map(|v| v as i32)
+                .ok_or_else(|| error(key))
+        };
+        let string = |v: &Value, key: &str| {
+            v.get(key)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| error(key))
+        };
+        let strings = |key: &str| -> Result<Vec<String>> {
+            match doc.get(key) {
+                None => Ok(vec![]),
+                Some(v) => v
+                    .as_array()
+                    .ok_or_else(|| error(key))?
+                    .iter()
+                    .map(|s| s.as_str().map(str::to_string).ok_or_else(|| error(key)))
+                    .collect(),
+            }
+        };
+        let mut parts = vec![];
+        for part in doc
+            .get("parts")
+            .and_then(Value::as_array)
+            .ok_or_else(|| error("parts"))?
+        {
+            let mut attributes = vec![];
+            for attr in part
+                .get("attributes")
+                .and_then(Value::as_array)
+                .unwrap_or_default()
+            {
+                attributes.push(AttributeSchema {
+                    name: string(attr, "name")?,
+                    owner: from_name(&OWNERS, &string(attr, "owner")?)
+                        .ok_or_else(|| error("owner"))?,
+                    storage: from_name(&STORAGES, &string(attr, "storage")?)
+                        .ok_or_else(|| error("storage"))?,
+                    tuple_size: int(attr, "tuple_size")?,
+                    type_info: from_name(&TYPE_INFOS, &string(attr, "type_info")?)
+                        .ok_or_else(|| error("type_info"))?,
+                });
+            }
+            parts.push(PartSchema {
+                part_id: int(part, "part_id")?,
+                name: string(part, "name").unwrap_or_default(),
+                part_type: from_name(&PART_TYPES, &string(part, "part_type")?)
+                    .ok_or_else(|| error("part_type"))?,
+                point_count: int(part, "point_count").unwrap_or_default(),
+                vertex_count: int(part, "vertex_count").unwrap_or_default(),
+                face_count: int(part, "face_count").unwrap_or_default(),
+                instance_count: int(part, "instance_count").unwrap_or_default(),
+                attributes,
+            });
+        }
+        Ok(GeometrySchema {
+            parts,
+            point_groups: strings("point_groups")?,
+            prim_groups: strings("prim_groups")?,
+        })
+    }
+}
+
+impl fmt::Display for GeometrySchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            writeln!(
+                f,
+                "Part {} \"{}\" {:?}: {} points, {} vertices, {} faces, {} instances",
+                part.part_id,
+                part.name,
+                part.part_type,
+                part.point_count,
+                part.vertex_count,
+                part.face_count,
+                part.instance_count
+            )?;
+            let header = ["Owner", "Name", "Storage", "Size", "Type"];
+            let rows: Vec<[String; 5]> = part
+                .attributes
+                .iter()
+                .map(|a| {
+                    [
+                        format!("{:?}", a.owner),
+                        a.name.clone(),
+                        format!("{:?}", a.storage),
+                        a.tuple_size.to_string(),
+                        format!("{:?}", a.type_info),
+                    ]
+                })
+                .collect();
+            let mut widths = header.map(str::len);
+            for row in &rows {
+                for (w, cell) in widths.iter_mut().zip(row) {
+                    *w = (*w).max(cell.len());
+                }
+            }
+            for row in std::iter::once(header.map(str::to_string)).chain(rows) {
+                let line: Vec<String> = row
+                    .iter()
+                    .zip(widths)
+                    .map(|(cell, w)| format!("{cell:w$}"))
+                    .collect();
+                writeln!(f, "  {}", line.join("  ").trim_end())?;
+            }
+        }
+        writeln!(f, "Point groups: {}", self.point_groups.join(", "))?;
+        write!(f, "Primitive groups: {}", self.prim_groups.join(", "))
+    }
+}
+
+impl Geometry {
+    /// Describe all parts, attributes and groups of the geometry.
+    pub fn schema(&self) -> Result<GeometrySchema> {
+        debug_assert!(
+            self.node.get_info()?.total_cook_count() > 0,
+            "Node not cooked"
+        );
+        let mut parts = vec![];
+        for part in self.partitions()? {
+            let part_id = part.part_id();
+            let mut attributes = vec![];
+            for owner in OWNERS {
+                for name in self.get_attribute_names(owner, Some(&part))?.iter_str() {
+                    let info = self.get_attribute_info(part_id, owner, name)?;
+                    attributes.push(AttributeSchema {
+                        name: name.to_string(),
+                        owner,
+                        storage: info.storage(),
+                        tuple_size: info.tuple_size(),
+                        type_info: info.type_info(),
+                    });
+                }
+            }
+            parts.push(PartSchema {
+                part_id,
+                name: part.name(&self.node.session)?,
+                part_type: part.part_type(),
+                point_count: part.point_count(),
+                vertex_count: part.vertex_count(),
+                face_count: part.face_count(),
+                instance_count: part.instance_count(),
+                attributes,
+            });
+        }
+        let groups = |group_type| -> Result<Vec<String>> {
+            Ok(self.get_group_names(group_type)?.into_iter().collect())
+        };
+        Ok(GeometrySchema {
+            parts,
+            point_groups: groups(GroupType::Point)?,
+            prim_groups: groups(GroupType::Prim)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> GeometrySchema {
+        let attr = |name: &str, owner, storage, tuple_size, type_info| AttributeSchema {
+            name: name.to_string(),
+            owner,
+            storage,
+            tuple_size,
+            type_info,
+        };
+        GeometrySchema {
+            parts: vec![PartSchema {
+                part_id: 0,
+                name: "box".to_string(),
+                part_type: PartType::Mesh,
+                point_count: 8,
+                vertex_count: 24,
+                face_count: 6,
+                instance_count: 0,
+                attributes: vec![
+                    attr(
+                        "P",
+                        AttributeOwner::Point,
+                        StorageType::Float,
+                        3,
+                        AttributeTypeInfo::Point,
+                    ),
+                    attr(
+                        "name",
+                        AttributeOwner::Prim,
+                        StorageType::String,
+                        1,
+                        AttributeTypeInfo::None,
+                    ),
+                ],
+            }],
+            point_groups: vec!["top".to_string()],
+            prim_groups: vec![],
+        }
+    }
+
+    #[test]
+    fn schema_json_roundtrip() {
+        let schema = schema();
+        let text = schema.to_json_string();
+        assert_eq!(GeometrySchema::from_json_str(&text).unwrap(), schema);
+        assert!(GeometrySchema::from_json_str(r#"{"parts":[{"part_id":0}]}"#).is_err());
+    }
+
+    #[test]
+    fn schema_diff_and_contract() {
+        let actual = schema();
+        assert!(actual.diff(&actual).is_empty());
+
+        let mut expected = schema();
+        expected.parts[0].point_count = 4;
+        expected.parts[0].attributes.remove(1);
+        expected.point_groups.clear();
+        assert_eq!(actual.diff(&expected).len(), 3);
+        assert!(actual.check_contract(&expected).is_ok());
+
+        expected.parts[0].attributes[0].tuple_size = 4;
+        expected.prim_groups.push("sides".to_string());
+        let err = actual.check_contract(&expected).unwrap_err().to_string();
+        assert!(err.contains("Point attribute \"P\""));
+        assert!(err.contains("primitive group \"sides\": missing"));
+    }
+
+    #[test]
+    fn schema_table() {
+        let table = schema().to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(
+            lines[0],
+            "Part 0 \"box\" Mesh: 8 points, 24 vertices, 6 faces, 0 instances"
+        );
+        assert_eq!(lines[1], "  Owner  Name  Storage  Size  Type");
+        assert_eq!(lines[2], "  Point  P     Float    3     Point");
+        assert_eq!(lines[4], "Point groups: top");
+    }
+}
@@ -0,0 +1,935 @@
+//! Export of cooked geometry and objects to glTF 2.0
+//!
+//! [`GltfDocument`] is a small builder for glTF scenes made of [`Mesh`]es, nodes, PBR materials
+//! and PNG textures. It can be written as `.gltf` (JSON with an embedded buffer) or `.glb`.
+//!
+//! [`HoudiniNode::export_gltf`] collects the display geometry of an Object network or a SOP node:
+//! meshes from all parts, object transforms, packed and object instances and materials with
+//! textures rendered by the Engine.
+//!
+//! Houdini and glTF are both right-handed and Y-up, only the polygon winding and the `v` texture
+//! coordinate are flipped.
+//!
+//! ```ignore
+//! let asset = library.try_create_first()?;
+//! asset.cook()?;
+//! asset.export_gltf("asset.glb", &GltfExportOptions::default())?;
+//! ```
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::errors::{HapiError, Result};
+use crate::ffi::enums::{AttributeOwner, PartType};
+use crate::ffi::raw::RSTOrder;
+use crate::geofile::json::{self, Value};
+use crate::geometry::{Geometry, Materials, PartInfo};
+use crate::instance::MAX_DEPTH;
+use crate::material::Material;
+use crate::mesh::{AttributeValues, Mesh, MeshChannel, MeshReadOptions};
+use crate::node::{HoudiniNode, NodeType};
+use crate::object::{ObjectEntry, ObjectTree};
+use crate::parameter::Parameter;
+use crate::session::Session;
+use crate::transform::{Matrix4, IDENTITY};
+
+const FLOAT: i64 = 5126;
+const UNSIGNED_INT: i64 = 5125;
+const ARRAY_BUFFER: i64 = 34962;
+const ELEMENT_ARRAY_BUFFER: i64 = 34963;
+const TRIANGLES: i64 = 4;
+
+// Temporary primitive attribute holding the material index of each face
+const MATERIAL_ATTRIB: &str = "__gltf_material";
+
+/// A metallic-roughness material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Texture index returned by [`GltfDocument::add_texture_png`].
+    pub base_color_texture: Option<usize>,
+    pub double_sided: bool,
+}
+
+impl Default for GltfMaterial {
+    fn default() -> Self {
+        GltfMaterial {
+            name: String::new(),
+            base_color: [1.0; 4],
+            metallic: 0.0,
+            roughness: 1.0,
+            base_color_texture: None,
+            double_sided: false,
+        }
+    }
+}
+
+/// A node in the glTF scene.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GltfNode {
+    pub name: String,
+    /// Column-major local matrix.
+    pub matrix: Option<Matrix4>,
+    /// Mesh index returned by [`GltfDocument::add_mesh`].
+    pub mesh: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+/// In-memory glTF scene with a single binary buffer.
+#[derive(Debug, Clone, Default)]
+pub struct GltfDocument {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+    meshes: Vec<Value>,
+    materials: Vec<GltfMaterial>,
+    images: Vec<Value>,
+    nodes: Vec<GltfNode>,
+    roots: Vec<usize>,
+}
+
+impl GltfDocument {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push_view(&mut self, data: &[u8], target: Option<i64>) -> usize {
+        // Accessor data must be aligned to the component size
+        self.buffer.resize(self.buffer.len().next_multiple_of(4), 0);
+        let mut view = vec![
+            ("buffer", Value::Int(0)),
+            ("byteOffset", Value::Int(self.buffer.len() as i64)),
+            ("byteLength", Value::Int(data.len() as i64)),
+        ];
+        if let Some(target) = target {
+            view.push(("target", Value::Int(target)));
+        }
+        self.buffer.extend_from_slice(data);
+        self.buffer_views.push(object(view));
+        self.buffer_views.len() - 1
+    }
+
+    fn push_floats<const N: usize>(&mut self, values: &[[f32; N]], bounds: bool) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flatten()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let view = self.push_view(&bytes, Some(ARRAY_BUFFER));
+        let mut accessor = vec![
+            ("bufferView", Value::Int(view as i64)),
+            ("componentType", Value::Int(FLOAT)),
+            ("count", Value::Int(values.len() as i64)),
+            ("type", Value::from(format!("VEC{N}"))),
+        ];
+        if bounds && !values.is_empty() {
+            let mut min = [f32::MAX; N];
+            let mut max = [f32::MIN; N];
+            for v in values {
+                for i in 0..N {
+                    min[i] = min[i].min(v[i]);
+                    max[i] = max[i].max(v[i]);
+                }
+            }
+            accessor.push(("min", floats(&min)));
+            accessor.push(("max", floats(&max)));
+        }
+        self.accessors.push(object(accessor));
+        self.accessors.len() - 1
+    }
+
+    fn push_indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, Some(ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(object(vec![
+            ("bufferView", Value::Int(view as i64)),
+            ("componentType", Value::Int(UNSIGNED_INT)),
+            ("count", Value::Int(indices.len() as i64)),
+            ("type", Value::from("SCALAR")),
+        ]));
+        self.accessors.len() - 1
+    }
+
+    /// Add a PNG image and a texture using it, returns the texture index.
+    pub fn add_texture_png(&mut self, name: &str, png: &[u8]) -> usize {
+        let view = self.push_view(png, None);
+        self.images.push(object(vec![
+            ("name", Value::from(name)),
+            ("bufferView", Value::Int(view as i64)),
+            ("mimeType", Value::from("image/png")),
+        ]));
+        self.images.len() - 1
+    }
+
+    /// Add a material, returns its index.
+    pub fn add_material(&mut self, material: GltfMaterial) -> usize {
+        self.materials.push(material);
+        self.materials.len() - 1
+    }
+
+    /// Add a mesh, returns its index. The mesh is triangulated, gets smooth normals if it has
+    /// none and is welded, so all attributes end up on glTF vertices. Only `P`, `N`, `uv` and `Cd`
+    /// are exported.
+    ///
+    /// `face_materials` is either empty, holds one material for all faces, or one per face. Faces
+    /// with different materials are written as separate primitives.
+    pub fn add_mesh(
+        &mut self,
+        name: &str,
+        mesh: &Mesh,
+        face_materials: &[Option<usize>],
+    ) -> Result<usize> {
+        mesh.validate()?;
+        if mesh.face_count() == 0 {
+            return Err(HapiError::internal(format!(
+                "Mesh {name} has no faces, glTF meshes need at least one primitive"
+            )));
+        }
+        let per_face = match face_materials.len() {
+            0 => vec![None; mesh.face_count()],
+            1 => vec![face_materials[0]; mesh.face_count()],
+            n if n == mesh.face_count() => face_materials.to_vec(),
+            n => {
+                return Err(HapiError::internal(format!(
+                    "Expected 1 or {} face materials, got {n}",
+                    mesh.face_count()
+                )))
+            }
+        };
+        let mut mesh = Mesh {
+            attributes: vec![],
+            groups: vec![],
+            ..mesh.clone()
+        }
+        .with_attribute(
+            MATERIAL_ATTRIB,
+            AttributeOwner::Prim,
+            1,
+            AttributeValues::Int(
+                per_face
+                    .iter()
+                    .map(|m| m.map_or(-1, |m| m as i32))
+                    .collect(),
+            ),
+        );
+        mesh.triangulate();
+        mesh.compute_normals(true);
+        // Primitive channels can't be welded, move them to vertices first
+        let vertex_channel = |mesh: &Mesh, ch: &Option<MeshChannel>| {
+            ch.as_ref()
+                .filter(|ch| ch.owner == AttributeOwner::Prim)
+                .map(|ch| MeshChannel {
+                    owner: AttributeOwner::Vertex,
+                    values: mesh.vertex_channel(ch),
+                })
+        };
+        if let Some(ch) = vertex_channel(&mesh, &mesh.normals) {
+            mesh.normals = Some(ch);
+        }
+        if let Some(ch) = vertex_channel(&mesh, &mesh.uvs) {
+            mesh.uvs = Some(ch);
+        }
+        if let Some(ch) = vertex_channel(&mesh, &mesh.colors) {
+            mesh.colors = Some(ch);
+        }
+        mesh.weld();
+
+        let mut attributes = vec![("POSITION", self.push_floats(&mesh.positions, true))];
+        if let Some(normals) = &mesh.normals {
+            attributes.push(("NORMAL", self.push_floats(&normals.values, false)));
+        }
+        if let Some(uvs) = &mesh.uvs {
+            let uvs: Vec<[f32; 2]> = uvs.values.iter().map(|uv| [uv[0], 1.0 - uv[1]]).collect();
+            attributes.push(("TEXCOORD_0", self.push_floats(&uvs, false)));
+        }
+        if let Some(colors) = &mesh.colors {
+            attributes.push(("COLOR_0", self.push_floats(&colors.values, false)));
+        }
+        let attributes = object(
+            attributes
+                .into_iter()
+                .map(|(k, v)| (k, Value::Int(v as i64)))
+                .collect(),
+        );
+
+        let face_material = match &mesh.attributes[0].values {
+            AttributeValues::Int(v) => v.clone(),
+            _ => unreachable!(),
+        };
+        // Group triangles by material, keeping the first-seen order
+        let mut groups: Vec<(i32, Vec<u32>)> = vec![];
+        for (face, triangle) in mesh.indices.chunks_exact(3).enumerate() {
+            let material = face_material[face];
+            let group = match groups.iter().position(|(m, _)| *m == material) {
+                Some(i) => i,
+                None => {
+                    groups.push((material, vec![]));
+                    groups.len() - 1
+                }
+            };
+            // Houdini winds clockwise, glTF counter-clockwise
+            groups[group]
+                .1
+                .extend([triangle[0] as u32, triangle[2] as u32, triangle[1] as u32]);
+        }
+        let mut primitives = vec![];
+        for (material, indices) in groups {
+            let mut primitive = vec![
+                ("attributes", attributes.clone()),
+                ("indices", Value::Int(self.push_indices(&indices) as i64)),
+                ("mode", Value::Int(TRIANGLES)),
+            ];
+            if material >= 0 {
+                primitive.push(("material", Value::Int(material as i64)));
+            }
+            primitives.push(object(primitive));
+        }
+        self.meshes.push(object(vec![
+            ("name", Value::from(name)),
+            ("primitives", Value::Array(primitives)),
+        ]));
+        Ok(self.meshes.len() - 1)
+    }
+
+    /// Add a node, returns its index. Nodes are not part of the scene until added with
+    /// [`GltfDocument::add_root`] or as a child of another node.
+    pub fn add_node(&mut self, node: GltfNode) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    pub fn add_child(&mut self, parent: usize, child: usize) {
+        self.nodes[parent].children.push(child);
+    }
+
+    pub fn add_root(&mut self, node: usize) {
+        self.roots.push(node);
+    }
+
+    pub fn nodes(&self) -> &[GltfNode] {
+        &self.nodes
+    }
+
+    pub fn mesh_count(&self) -> usize {
+        self.meshes.len()
+    }
+
+    pub fn material_count(&self) -> usize {
+        self.materials.len()
+    }
+
+    // The glTF JSON document, with the buffer embedded as a data URI or not.
+    fn to_json(&self, embed: bool) -> Value {
+        let mut buffer = vec![("byteLength", Value::Int(self.buffer.len() as i64))];
+        if embed {
+            let uri = format!(
+                "data:application/octet-stream;base64,{}",
+                base64(&self.buffer)
+            );
+            buffer.push(("uri", Value::from(uri)));
+        }
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut items = vec![("name", Value::from(node.name.as_str()))];
+                if let Some(matrix) = node.matrix.filter(|m| *m != IDENTITY) {
+                    items.push(("matrix", floats(&matrix)));
+                }
+                if let Some(mesh) = node.mesh {
+                    items.push(("mesh", Value::Int(mesh as i64)));
+                }
+                if !node.children.is_empty() {
+                    items.push(("children", indices(&node.children)));
+                }
+                object(items)
+            })
+            .collect();
+        let materials = self
+            .materials
+            .iter()
+            .map(|m| {
+                let mut pbr = vec![
+                    ("baseColorFactor", floats(&m.base_color)),
+                    ("metallicFactor", Value::Float(m.metallic as f64)),
+                    ("roughnessFactor", Value::Float(m.roughness as f64)),
+                ];
+                if let Some(texture) = m.base_color_texture {
+                    pbr.push((
+                        "baseColorTexture",
+                        object(vec![("index", Value::Int(texture as i64))]),
+                    ));
+                }
+                object(vec![
+                    ("name", Value::from(m.name.as_str())),
+                    ("pbrMetallicRoughness", object(pbr)),
+                    ("doubleSided", Value::Bool(m.double_sided)),
+                ])
+            })
+            .collect();
+        let textures = (0..self.images.len())
+            .map(|i| object(vec![("source", Value::Int(i as i64))]))
+            .collect();
+
+        let mut doc = vec![
+            (
+                "asset",
+                object(vec![
+                    ("version", Value::from("2.0")),
+                    ("generator", Value::from("hapi-rs")),
+                ]),
+            ),
+            ("scene", Value::Int(0)),
+            (
+                "scenes",
+                Value::Array(vec![object(vec![("nodes", indices(&self.roots))])]),
+            ),
+            ("nodes", Value::Array(nodes)),
+        ];
+        for (key, items) in [
+            ("meshes", &self.meshes),
+            ("accessors", &self.accessors),
+            ("bufferViews", &self.buffer_views),
+            ("images", &self.images),
+        ] {
+            if !items.is_empty() {
+                doc.push((key, Value::Array(items.clone())));
+            }
+        }
+        if !self.materials.is_empty() {
+            doc.push(("materials", Value::Array(materials)));
+        }
+        if !self.images.is_empty() {
+            doc.push(("textures", Value::Array(textures)));
+        }
+        if !self.buffer.is_empty() {
+            doc.push(("buffers", Value::Array(vec![object(buffer)])));
+        }
+        object(doc)
+    }
+
+    /// Serialize as a `.gltf` JSON document with an embedded buffer.
+    pub fn to_gltf_string(&self) -> String {
+        json::to_string(&self.to_json(true))
+    }
+
+    /// Serialize as a binary `.glb` container.
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut json = json::to_string(&self.to_json(false)).into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin = self.buffer.clone();
+        bin.resize(bin.len().next_multiple_of(4), 0);
+        let has_bin = !bin.is_empty();
+        let total = 12 + 8 + json.len() + if has_bin { 8 + bin.len() } else { 0 };
+        let mut out = Vec::with_capacity(total);
+        out.extend(b"glTF");
+        out.extend(2u32.to_le_bytes());
+        out.extend((total as u32).to_le_bytes());
+        out.extend((json.len() as u32).to_le_bytes());
+        out.extend(b"JSON");
+        out.extend(json);
+        if has_bin {
+            out.extend((bin.len() as u32).to_le_bytes());
+            out.extend(b"BIN\0");
+            out.extend(bin);
+        }
+        out
+    }
+
+    /// Write a `.glb` or `.gltf` file, depending on the extension.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let is_glb = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("glb"));
+        if is_glb {
+            std::fs::write(path, self.to_glb())?;
+        } else {
+            std::fs::write(path, self.to_gltf_string())?;
+        }
+        Ok(())
+    }
+}
+
+fn object(items: Vec<(&str, Value)>) -> Value {
+    Value::Object(items.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn floats(values: &[f32]) -> Value {
+    Value::Array(values.iter().map(|v| Value::Float(*v as f64)).collect())
+}
+
+fn indices(values: &[usize]) -> Value {
+    Value::Array(values.iter().map(|v| Value::Int(*v as i64)).collect())
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Options for [`HoudiniNode::export_gltf`].
+#[derive(Debug, Clone)]
+pub struct GltfExportOptions {
+    /// Render and embed material textures.
+    pub textures: bool,
+    /// Texture parameters tried in order when rendering a material's base color texture.
+    pub texture_parms: Vec<String>,
+    /// Export packed instances and objects placed by object instancers.
+    pub instances: bool,
+    /// Export objects with the visibility flag off.
+    pub hidden_objects: bool,
+}
+
+impl Default for GltfExportOptions {
+    fn default() -> Self {
+        GltfExportOptions {
+            textures: true,
+            texture_parms: vec!["basecolor_texture".to_string(), "baseColorMap".to_string()],
+            instances: true,
+            hidden_objects: false,
+        }
+    }
+}
+
+impl GltfExportOptions {
+    pub fn with_textures(mut self, textures: bool) -> Self {
+        self.textures = textures;
+        self
+    }
+
+    pub fn with_texture_parms(mut self, parms: Vec<String>) -> Self {
+        self.texture_parms = parms;
+        self
+    }
+
+    pub fn with_instances(mut self, instances: bool) -> Self {
+        self.instances = instances;
+        self
+    }
+
+    pub fn with_hidden_objects(mut self, hidden: bool) -> Self {
+        self.hidden_objects = hidden;
+        self
+    }
+}
+
+struct Exporter<'a> {
+    doc: GltfDocument,
+    options: &'a GltfExportOptions,
+    session: &'a Session,
+    // Material node id to glTF material index
+    materials: HashMap<i32, usize>,
+    // Geometry node id and part id to glTF mesh index, shared by all instances
+    meshes: HashMap<(i32, i32), usize>,
+}
+
+impl Exporter<'_> {
+    fn material(&mut self, material: &Material) -> Result<usize> {
+        let id = material.info.nodeId;
+        if let Some(index) = self.materials.get(&id) {
+            return Ok(*index);
+        }
+        let node = material.node()?;
+        let mut gltf = GltfMaterial {
+            name: node.name()?,
+            ..Default::default()
+        };
+        let float = |name: &str, index: i32| match node.parameter(name) {
+            Ok(Parameter::Float(p)) => p.get(index).ok(),
+            _ => None,
+        };
+        for i in 0..3 {
+            if let Some(v) = float("basecolor", i) {
+                gltf.base_color[i as usize] = v;
+            }
+        }
+        if let Some(v) = float("metallic", 0) {
+            gltf.metallic = v;
+        }
+        if let Some(v) = float("rough", 0) {
+            gltf.roughness = v;
+        }
+        if self.options.textures {
+            for parm in &self.options.texture_parms {
+                // Fails if the material doesn't have the parameter or a texture
+                if material.render_texture(parm).is_err() {
+                    continue;
+                }
+                let mut png = vec![];
+                if material
+                    .extract_image_to_memory(&mut png, "C", "PNG")
+                    .is_ok()
+                {
+                    gltf.base_color_texture = Some(
+                        self.doc
+                            .add_texture_png(&format!("{}_{parm}", gltf.name), &png),
+                    );
+                    break;
+                }
+            }
+        }
+        let index = self.doc.add_material(gltf);
+        self.materials.insert(id, index);
+        Ok(index)
+    }
+
+    fn face_materials(&mut self, geo: &Geometry, part: &PartInfo) -> Result<Vec<Option<usize>>> {
+        Ok(match geo.get_materials(Some(part))? {
+            None => vec![],
+            Some(Materials::Single(material)) => vec![Some(self.material(&material)?)],
+            Some(Materials::Multiple(materials)) => {
+                let mut out = Vec::with_capacity(materials.len());
+                for material in &materials {
+                    // Faces without a material have an invalid node id
+                    let index = if material.info.nodeId < 0 {
+                        None
+                    } else {
+                        Some(self.material(material)?)
+                    };
+                    out.push(index);
+                }
+                out
+            }
+        })
+    }
+
+    fn part_mesh(&mut self, geo: &Geometry, part: &PartInfo) -> Result<usize> {
+        let key = (geo.node.handle.0, part.part_id());
+        if let Some(mesh) = self.meshes.get(&key) {
+            return Ok(*mesh);
+        }
+        let mesh = geo.read_mesh(Some(part), &MeshReadOptions::default())?;
+        let face_materials = self.face_materials(geo, part)?;
+        let name = part.name(&geo.node.session)?;
+        let mesh = self.doc.add_mesh(&name, &mesh, &face_materials)?;
+        self.meshes.insert(key, mesh);
+        Ok(mesh)
+    }
+
+    // Add all parts of a geometry as children of the parent node.
+    fn geometry(&mut self, geo: &Geometry, parent: usize, depth: usize) -> Result<()> {
+        for part in geo.partitions()? {
+            // Instanced parts are added by their instancer
+            if !part.is_instanced() {
+                self.part(geo, &part, parent, depth)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn part(&mut self, geo: &Geometry, part: &PartInfo, parent: usize, depth: usize) -> Result<()> {
+        match part.part_type() {
+            // Point clouds can't be glTF meshes
+            PartType::Mesh if part.face_count() > 0 => {
+                let mesh = self.part_mesh(geo, part)?;
+                let node = self.doc.add_node(GltfNode {
+                    name: part.name(&geo.node.session)?,
+                    mesh: Some(mesh),
+                    ..Default::default()
+                });
+                self.doc.add_child(parent, node);
+            }
+            PartType::Instancer if self.options.instances => {
+                self.instancer(geo, part, parent, depth)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Add a node per packed instance, instanced parts can be instancers themselves.
+    fn instancer(
+        &mut self,
+        geo: &Geometry,
+        part: &PartInfo,
+        parent: usize,
+        depth: usize,
+    ) -> Result<()> {
+        check_depth(depth)?;
+        let transforms = geo.get_instance_part_transforms(Some(part), RSTOrder::Default)?;
+        let instanced = geo
+            .get_instanced_part_ids(Some(part))?
+            .into_iter()
+            .map(|id| geo.part_info(id))
+            .filter_map(Result::transpose)
+            .collect::<Result<Vec<_>>>()?;
+        let instancer = self.doc.add_node(GltfNode {
+            name: part.name(&geo.node.session)?,
+            ..Default::default()
+        });
+        self.doc.add_child(parent, instancer);
+        for (i, transform) in transforms.iter().enumerate() {
+            let instance = self.doc.add_node(GltfNode {
+                name: format!("instance{i}"),
+                matrix: Some(transform.to_matrix()),
+                ..Default::default()
+            });
+            self.doc.add_child(instancer, instance);
+            for part in &instanced {
+                self.part(geo, part, instance, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Add the geometry of an object, object instancers add their target objects on each point.
+    fn object(
+        &mut self,
+        tree: &ObjectTree,
+        object: &ObjectEntry,
+        geo: &Geometry,
+        parent: usize,
+        depth: usize,
+    ) -> Result<()> {
+        if !object.is_instancer {
+            return self.geometry(geo, parent, depth);
+        }
+        if !self.options.instances {
+            return Ok(());
+        }
+        check_depth(depth)?;
+        let Some(part) = geo.part_info(0)? else {
+            return Ok(());
+        };
+        let targets = object
+            .handle
+            .to_node(self.session)?
+            .get_instanced_object_ids()?;
+        let transforms = geo.get_instance_transforms(Some(&part), RSTOrder::Default)?;
+        // Geometry of each target object, looked up once
+        let mut geos: HashMap<i32, Option<Geometry>> = HashMap::new();
+        for (i, (target, transform)) in targets.iter().zip(&transforms).enumerate() {
+            let Some(entry) = tree.get(*target) else {
+                continue;
+            };
+            let target_geo = match geos.get(&target.0) {
+                Some(geo) => geo.clone(),
+                None => {
+                    let geo = target.to_node(self.session)?.geometry()?;
+                    geos.insert(target.0, geo.clone());
+                    geo
+                }
+            };
+            let Some(target_geo) = target_geo else {
+                continue;
+            };
+            let instance = self.doc.add_node(GltfNode {
+                name: format!("{}_instance{i}", entry.name),
+                matrix: Some(transform.to_matrix()),
+                ..Default::default()
+            });
+            self.doc.add_child(parent, instance);
+            self.object(tree, entry, &target_geo, instance, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_depth(depth: usize) -> Result<()> {
+    if depth >= MAX_DEPTH {
+        return Err(HapiError::internal(format!(
+            "Instancer nesting deeper than {MAX_DEPTH} levels"
+        )));
+    }
+    Ok(())
+}
+
+impl HoudiniNode {
+    /// Collect the cooked display geometry of this Object network, Object or SOP node into a
+    /// [`GltfDocument`].
+    pub fn to_gltf(&self, options: &GltfExportOptions) -> Result<GltfDocument> {
+        debug_assert!(self.is_valid()?, "Invalid node: {}", self.path()?);
+        let mut exporter = Exporter {
+            doc: GltfDocument::new(),
+            options,
+            session: &self.session,
+            materials: HashMap::new(),
+            meshes: HashMap::new(),
+        };
+        if self.info.node_type() == NodeType::Sop {
+            let root = exporter.doc.add_node(GltfNode {
+                name: self.name()?,
+                ..Default::default()
+            });
+            exporter.doc.add_root(root);
+            if let Some(geo) = self.geometry()? {
+                exporter.geometry(&geo, root, 0)?;
+            }
+            return Ok(exporter.doc);
+        }
+        let tree = self.object_tree()?;
+        // glTF node index of each object
+        let mut nodes = vec![0; tree.objects().len()];
+        for (i, object) in tree.objects().iter().enumerate() {
+            nodes[i] = exporter.doc.add_node(GltfNode {
+                name: object.name.clone(),
+                matrix: Some(object.local.to_matrix()),
+                ..Default::default()
+            });
+        }
+        for (i, object) in tree.objects().iter().enumerate() {
+            match object.parent {
+                Some(parent) => exporter.doc.add_child(nodes[parent], nodes[i]),
+                None => exporter.doc.add_root(nodes[i]),
+            }
+            // Instanced objects are exported through their instancer
+            if (!object.is_visible && !options.hidden_objects) || object.is_instanced {
+                continue;
+            }
+            let node = object.handle.to_node(&self.session)?;
+            if let Some(geo) = node.geometry()? {
+                exporter.object(&tree, object, &geo, nodes[i], 0)?;
+            }
+        }
+        Ok(exporter.doc)
+    }
+
+    /// Export the cooked display geometry as `.gltf` or `.glb`, see [`HoudiniNode::to_gltf`].
+    pub fn export_gltf(&self, path: impl AsRef<Path>, options: &GltfExportOptions) -> Result<()> {
+        self.to_gltf(options)?.write(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad() -> Mesh {
+        Mesh::new(
+            vec![
+                [0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [1.0, 0.0, 0.0],
+            ],
+            vec![4],
+            vec![0, 1, 2, 3],
+        )
+        .with_uvs(
+            AttributeOwner::Point,
+            vec![
+                [0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [1.0, 0.0, 0.0],
+            ],
+        )
+    }
+
+    #[test]
+    fn base64_encoding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn gltf_mesh_and_nodes() {
+        let mut doc = GltfDocument::new();
+        let red = doc.add_material(GltfMaterial {
+            name: "red".to_string(),
+            base_color: [1.0, 0.0, 0.0, 1.0],
+            ..Default::default()
+        });
+        let two_quads = Mesh::new(
+            vec![[0.0; 3], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+            vec![3, 3],
+            vec![0, 1, 2, 0, 2, 3],
+        );
+        let mesh = doc.add_mesh("quad", &quad(), &[Some(red)]).unwrap();
+        let split = doc
+            .add_mesh("split", &two_quads, &[Some(red), None])
+            .unwrap();
+        assert!(doc.add_mesh("bad", &two_quads, &[None; 3]).is_err());
+        let points = Mesh::new(vec![[0.0; 3], [1.0; 3]], vec![], vec![]);
+        assert!(doc.add_mesh("points", &points, &[]).is_err());
+        assert_eq!(doc.mesh_count(), 2);
+        let root = doc.add_node(GltfNode {
+            name: "root".to_string(),
+            matrix: Some(IDENTITY),
+            ..Default::default()
+        });
+        let child = doc.add_node(GltfNode {
+            name: "child".to_string(),
+            mesh: Some(mesh),
+            ..Default::default()
+        });
+        doc.add_child(root, child);
+        doc.add_root(root);
+
+        let value = json::parse(&doc.to_gltf_string()).unwrap();
+        let meshes = value.get("meshes").unwrap().as_array().unwrap();
+        assert_eq!(meshes.len(), 2);
+        let primitives = meshes[split].get("primitives").unwrap().as_array().unwrap();
+        assert_eq!(primitives.len(), 2);
+        assert!(primitives[1].get("material").is_none());
+        let quad = &meshes[mesh].get("primitives").unwrap().as_array().unwrap()[0];
+        let attributes = quad.get("attributes").unwrap();
+        assert!(attributes.get("NORMAL").is_some());
+        assert!(attributes.get("TEXCOORD_0").is_some());
+        let position = attributes.get("POSITION").unwrap().as_i64().unwrap() as usize;
+        let accessor = &value.get("accessors").unwrap().as_array().unwrap()[position];
+        assert_eq!(accessor.get("count").unwrap().as_i64(), Some(4));
+        assert_eq!(
+            accessor.get("max").unwrap().to_f64_vec(),
+            Some(vec![1.0, 1.0, 0.0])
+        );
+        // Identity matrices are omitted
+        let nodes = value.get("nodes").unwrap().as_array().unwrap();
+        assert!(nodes[root].get("matrix").is_none());
+        assert_eq!(
+            nodes[root].get("children").unwrap().to_i64_vec(),
+            Some(vec![1])
+        );
+        let uri = value.get("buffers").unwrap().as_array().unwrap()[0]
+            .get("uri")
+            .unwrap()
+            .as_str()
+            .unwrap();
+        assert!(uri.starts_with("data:application/octet-stream;base64,"));
+    }
+
+    #[test]
+    fn glb_container() {
+        let mut doc = GltfDocument::new();
+        let mesh = doc.add_mesh("quad", &quad(), &[]).unwrap();
+        let node = doc.add_node(GltfNode {
+            mesh: Some(mesh),
+            ..Default::default()
+        });
+        doc.add_root(node);
+        let glb = doc.to_glb();
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(
+            u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize,
+            glb.len()
+        );
+        let json_len = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        assert_eq!(&glb[16..20], b"JSON");
+        let text = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+        let value = json::parse(text.trim_end()).unwrap();
+        let buffer = &value.get("buffers").unwrap().as_array().unwrap()[0];
+        assert!(buffer.get("uri").is_none());
+        assert_eq!(&glb[24 + json_len..28 + json_len], b"BIN\0");
+    }
+}
@@ -0,0 +1,320 @@
+//! Flattened packed primitive and object instancer hierarchy
+//!
+//! Instancers can be nested: an instancer part can instance parts which are instancers
+//! themselves, and object instancers place whole objects on points. [`InstanceTree`] walks all
+//! of them and produces a flat list of [`Instance`] with composed matrices, ready to be turned
+//! into engine-native instances.
+//!
+//! Matrices are relative to the same root as the [`ObjectTree`] of the node.
+use std::collections::HashMap;
+
+use crate::errors::{HapiError, Result};
+use crate::ffi::enums::{AttributeOwner, PartType, RSTOrder};
+use crate::ffi::PartInfo;
+use crate::geometry::Geometry;
+use crate::mesh::MeshAttribute;
+use crate::node::{HoudiniNode, NodeHandle};
+use crate::object::{ObjectEntry, ObjectTree};
+use crate::transform::{multiply, Matrix4};
+
+// Guards against instancers referencing each other.
+pub(crate) const MAX_DEPTH: usize = 32;
+
+/// What an [`Instance`] places.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceSource {
+    /// A part of a SOP geometry node.
+    Part { geometry: NodeHandle, part_id: i32 },
+    /// An object node, from an object instancer. Instancers inside the object are
+    /// resolved into separate instances.
+    Object(NodeHandle),
+}
+
+/// A single resolved instance.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub source: InstanceSource,
+    /// Composed matrix relative to the tree root.
+    pub world: Matrix4,
+    /// Point or primitive attributes of the instance with a single tuple each.
+    /// Attributes of outer instancers are included unless an inner one has the same name.
+    pub attributes: Vec<MeshAttribute>,
+    /// Object containing the outermost instancer.
+    pub instancer: NodeHandle,
+    /// Number of instancers this instance is nested in, minus one.
+    pub depth: usize,
+}
+
+impl Instance {
+    pub fn attribute(&self, name: &str) -> Option<&MeshAttribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+}
+
+/// All instances of an Object asset or network. See the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct InstanceTree {
+    instances: Vec<Instance>,
+}
+
+impl InstanceTree {
+    /// Resolve the instances of all visible objects of this node.
+    pub fn new(node: &HoudiniNode) -> Result<Self> {
+        let objects = ObjectTree::new(node)?;
+        let mut instances = vec![];
+        for object in objects.objects() {
+            // Instanced objects are reached through their instancer
+            if !object.is_visible || object.is_instanced {
+                continue;
+            }
+            let root = Parent {
+                world: object.world,
+                attributes: vec![],
+                instancer: object.handle,
+                depth: 0,
+            };
+            resolve_object(node, &objects, object, &root, &mut instances)?;
+        }
+        Ok(InstanceTree { instances })
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Instance> {
+        self.instances.iter()
+    }
+}
+
+// State inherited from the enclosing instance.
+struct Parent {
+    world: Matrix4,
+    attributes: Vec<MeshAttribute>,
+    instancer: NodeHandle,
+    depth: usize,
+}
+
+// Instancers of an object placed at `parent.world`.
+fn resolve_object(
+    node: &HoudiniNode,
+    objects: &ObjectTree,
+    object: &ObjectEntry,
+    parent: &Parent,
+    out: &mut Vec<Instance>,
+) -> Result<()> {
+    if parent.depth >= MAX_DEPTH {
+        return Err(HapiError::internal(format!(
+            "Instancer nesting deeper than {MAX_DEPTH} levels"
+        )));
+    }
+    let object_node = object.handle.to_node(&node.session)?;
+    let Some(geo) = object_node.geometry()? else {
+        return Ok(());
+    };
+    if object.is_instancer {
+        let Some(part) = geo.part_info(0)? else {
+            return Ok(());
+        };
+        let targets = object_node.get_instanced_object_ids()?;
+        let transforms = geo.get_instance_transforms(Some(&part), RSTOrder::Default)?;
+        let count = part.point_count() as usize;
+        let attributes = instance_attributes(&geo, &part, &[AttributeOwner::Point], count)?;
+        // Targets are checked once, most of them have no instancers
+        let mut nested: HashMap<i32, Option<&ObjectEntry>> = HashMap::new();
+        for (i, (target, transform)) in targets.iter().zip(&transforms).enumerate() {
+            let instance = Parent {
+                world: multiply(&parent.world, &transform.to_matrix()),
+                attributes: merge_attributes(
+                    element_attributes(&attributes, i),
+                    &parent.attributes,
+                ),
+                instancer: parent.instancer,
+                depth: parent.depth + 1,
+            };
+            out.push(Instance {
+                source: InstanceSource::Object(*target),
+                world: instance.world,
+                attributes: instance.attributes.clone(),
+                instancer: instance.instancer,
+                depth: parent.depth,
+            });
+            let entry = match nested.get(&target.0) {
+                Some(entry) => *entry,
+                None => {
+                    let entry = match objects.get(*target) {
+                        Some(entry) if has_instancers(node, entry)? => Some(entry),
+                        _ => None,
+                    };
+                    nested.insert(target.0, entry);
+                    entry
+                }
+            };
+            if let Some(entry) = entry {
+                resolve_object(node, objects, entry, &instance, out)?;
+            }
+        }
+        return Ok(());
+    }
+    for part in geo.partitions()? {
+        if part.part_type() == PartType::Instancer && !part.is_instanced() {
+            resolve(&geo, &part, parent, out)?;
+        }
+    }
+    Ok(())
+}
+
+fn has_instancers(node: &HoudiniNode, object: &ObjectEntry) -> Result<bool> {
+    if object.is_instancer {
+        return Ok(true);
+    }
+    let Some(geo) = object.handle.to_node(&node.session)?.geometry()? else {
+        return Ok(false);
+    };
+    Ok(geo
+        .partitions()?
+        .iter()
+        .any(|part| part.part_type() == PartType::Instancer))
+}
+
+fn resolve(
+    geo: &Geometry,
+    part: &PartInfo,
+    parent: &Parent,
+    out: &mut Vec<Instance>,
+) -> Result<()> {
+    if parent.depth >= MAX_DEPTH {
+        return Err(HapiError::internal(format!(
+            "Instancer nesting deeper than {MAX_DEPTH} levels"
+        )));
+    }
+    let instanced = geo
+        .get_instanced_part_ids(Some(part))?
+        .into_iter()
+        .map(|id| geo.part_info(id))
+        .filter_map(Result::transpose)
+        .collect::<Result<Vec<_>>>()?;
+    let transforms = geo.get_instance_part_transforms(Some(part), RSTOrder::Default)?;
+    let owners = [AttributeOwner::Prim, AttributeOwner::Point];
+    let attributes = instance_attributes(geo, part, &owners, transforms.len())?;
+    for (i, transform) in transforms.iter().enumerate() {
+        let instance = Parent {
+            world: multiply(&parent.world, &transform.to_matrix()),
+            attributes: merge_attributes(element_attributes(&attributes, i), &parent.attributes),
+            instancer: parent.instancer,
+            depth: parent.depth + 1,
+        };
+        for part in &instanced {
+            if part.part_type() == PartType::Instancer {
+                resolve(geo, part, &instance, out)?;
+            } else {
+                out.push(Instance {
+                    source: InstanceSource::Part {
+                        geometry: geo.node.handle,
+                        part_id: part.part_id(),
+                    },
+                    world: instance.world,
+                    attributes: instance.attributes.clone(),
+                    instancer: instance.instancer,
+                    depth: parent.depth,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+// Attributes of an instancer part with one tuple per instance.
+fn instance_attributes(
+    geo: &Geometry,
+    part: &PartInfo,
+    owners: &[AttributeOwner],
+    count: usize,
+) -> Result<Vec<MeshAttribute>> {
+    let mut attributes = vec![];
+    for owner in owners {
+        for name in geo.get_attribute_names(*owner, Some(part))?.iter_str() {
+            if name == "P" || attributes.iter().any(|a: &MeshAttribute| a.name == name) {
+                continue;
+            }
+            if let Some(attr) = geo.read_mesh_attribute(part.part_id(), *owner, name)? {
+                if attr.values.len() == count * attr.tuple_size as usize {
+                    attributes.push(attr);
+                }
+            }
+        }
+    }
+    Ok(attributes)
+}
+
+fn element_attributes(attributes: &[MeshAttribute], index: usize) -> Vec<MeshAttribute> {
+    attributes
+        .iter()
+        .map(|attr| {
+            let tuple = attr.tuple_size as usize;
+            MeshAttribute {
+                values: attr.values.slice(index * tuple..(index + 1) * tuple),
+                ..attr.clone()
+            }
+        })
+        .collect()
+}
+
+fn merge_attributes(mut inner: Vec<MeshAttribute>, outer: &[MeshAttribute]) -> Vec<MeshAttribute> {
+    for attr in outer {
+        if !inner.iter().any(|a| a.name == attr.name) {
+            inner.push(attr.clone());
+        }
+    }
+    inner
+}
+
+impl HoudiniNode {
+    /// Build an [`InstanceTree`] from this Object asset or network.
+    pub fn instance_tree(&self) -> Result<InstanceTree> {
+        InstanceTree::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::AttributeValues;
+
+    #[test]
+    fn instance_attribute_slices() {
+        let attr = |name: &str, values| MeshAttribute {
+            name: name.to_string(),
+            owner: AttributeOwner::Prim,
+            tuple_size: 2,
+            values,
+        };
+        let instancer = [
+            attr("scale", AttributeValues::Float(vec![1.0, 2.0, 3.0, 4.0])),
+            attr("id", AttributeValues::Int(vec![5, 6, 7, 8])),
+        ];
+        let second = element_attributes(&instancer, 1);
+        assert_eq!(second[0].values, AttributeValues::Float(vec![3.0, 4.0]));
+        assert_eq!(second[1].values, AttributeValues::Int(vec![7, 8]));
+
+        let outer = [
+            attr("id", AttributeValues::Int(vec![0, 0])),
+            attr(
+                "variant",
+                AttributeValues::String(vec!["a".into(), "b".into()]),
+            ),
+        ];
+        let merged = merge_attributes(second, &outer);
+        let names: Vec<&str> = merged.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["scale", "id", "variant"]);
+        assert_eq!(merged[1].values, AttributeValues::Int(vec![7, 8]));
+    }
+}
@@ -119,19 +119,36 @@
 //! # Parameters
 //! Parameters are modelled with [`parameter::Parameter`] enum that contains different parameter types.
 //! Common parameter methods are provided in [`parameter::ParmBaseTrait`].
+//!
+//! # Optional features
+//! Conversions between [`node::Transform`] and matrix types of popular math libraries
+//! are available with `glam`, `nalgebra` and `mint` features. See the [`transform`] module.
+//!
+//! The `serde_json` feature adds typed reading and writing of dictionary attributes,
+//! e.g. [`attribute::DictionaryAttr::get_as`].
+//!
+//! The `image` feature converts extracted pixels ([`material::RawImage`]) into `image::DynamicImage`.
 
 pub mod asset;
 pub mod attribute;
+pub mod curve;
+pub mod geofile;
 pub mod geometry;
+pub mod gltf;
+pub mod instance;
 pub mod material;
+pub mod mesh;
 pub mod node;
+pub mod object;
 pub mod parameter;
 pub mod session;
 pub mod stringhandle;
+pub mod transform;
 pub mod volume;
 pub mod pdg;
 mod errors;
 mod utils;
+mod png;
 mod ffi;
 
 pub use errors::{HapiError, Result};
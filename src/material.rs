@@ -1,12 +1,23 @@
 //! Rendering material textures to memory or disk
 //!
+//! [`Material::describe`] maps the common parameters of the Principled Shader and MaterialX
+//! Standard Surface to a [`PbrMaterial`], rendering each texture slot with the image APIs.
+//! [`Geometry::get_face_materials`] pairs the materials of a part with a per-face index buffer.
+//!
+//! [`Material::extract_image_pixels`] and [`Session::render_cop_to_pixels`] return decoded
+//! [`RawImage`]s instead of encoded image files. With the `image` feature they convert to
+//! `image::DynamicImage`.
+use crate::errors::HapiError;
+use crate::ffi::enums::{ImageDataFormat, ImagePacking};
 use crate::ffi::{raw::HAPI_MaterialInfo, ImageInfo};
+use crate::geometry::{Geometry, PartInfo};
 use crate::node::{HoudiniNode, NodeHandle};
-use crate::parameter::ParmHandle;
+use crate::parameter::{Parameter, ParmHandle};
 use crate::session::Session;
+use crate::utils::unwrap_or_create;
 use crate::Result;
 use std::ffi::CString;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 /// Represents a material node (SHOP) with methods for texture baking
@@ -79,6 +90,305 @@ impl Material {
         crate::ffi::get_image_planes(&self.session, self.node_handle())
             .map(|a| a.into_iter().collect())
     }
+
+    /// Extract an image plane of the last rendered texture as raw pixels.
+    /// Use [`Material::set_image_info`] before to choose the data format and packing.
+    pub fn extract_image_pixels(&self, image_planes: impl AsRef<str>) -> Result<RawImage> {
+        debug_assert!(self.session.is_valid());
+        extract_image_pixels(&self.session, self.node_handle(), image_planes)
+    }
+
+    /// Describe the material, extracting textures to memory as PNG.
+    pub fn describe(&self) -> Result<PbrMaterial> {
+        self.describe_with(&TextureTarget::Memory("PNG".to_string()))
+    }
+
+    /// Describe the material, extracting textures to `target`.
+    /// Parameters missing on the material keep their [`PbrMaterial::default`] values.
+    pub fn describe_with(&self, target: &TextureTarget) -> Result<PbrMaterial> {
+        debug_assert!(self.session.is_valid());
+        let node = self.node()?;
+        let name = node.name()?;
+        let float = |names: &[&str], index: i32| {
+            names.iter().find_map(|name| match node.parameter(name) {
+                Ok(Parameter::Float(p)) => p.get(index).ok(),
+                _ => None,
+            })
+        };
+        let color = |names: &[&str]| -> Option<[f32; 3]> {
+            Some([float(names, 0)?, float(names, 1)?, float(names, 2)?])
+        };
+        let mut material = PbrMaterial {
+            name,
+            node: self.node_handle(),
+            ..Default::default()
+        };
+        if let Some(c) = color(BASE_COLOR.values) {
+            material.base_color = c;
+        }
+        if let Some(v) = float(ROUGHNESS.values, 0) {
+            material.roughness = v;
+        }
+        if let Some(v) = float(METALNESS.values, 0) {
+            material.metalness = v;
+        }
+        if let Some(c) = color(EMISSION.values) {
+            let intensity = float(EMISSION_INTENSITY, 0).unwrap_or(1.0);
+            material.emission = c.map(|v| v * intensity);
+        }
+        material.base_color_texture =
+            self.slot_texture(&node, &material.name, &BASE_COLOR, target)?;
+        material.roughness_texture =
+            self.slot_texture(&node, &material.name, &ROUGHNESS, target)?;
+        material.metalness_texture =
+            self.slot_texture(&node, &material.name, &METALNESS, target)?;
+        material.normal_texture = self.slot_texture(&node, &material.name, &NORMAL, target)?;
+        material.emission_texture = self.slot_texture(&node, &material.name, &EMISSION, target)?;
+        Ok(material)
+    }
+
+    // First texture parameter of the slot which is set, enabled and renders.
+    fn slot_texture(
+        &self,
+        node: &HoudiniNode,
+        name: &str,
+        slot: &Slot,
+        target: &TextureTarget,
+    ) -> Result<Option<PbrTexture>> {
+        for (parm, toggle) in slot.textures {
+            let source = match node.parameter(parm) {
+                Ok(Parameter::String(p)) => p.get(0)?,
+                _ => continue,
+            };
+            if source.is_empty() {
+                continue;
+            }
+            if let Some(Ok(Parameter::Int(p))) = toggle.map(|t| node.parameter(t)) {
+                if p.get(0)? == 0 {
+                    continue;
+                }
+            }
+            if !matches!(target, TextureTarget::Skip) {
+                if let Err(e) = self.render_texture(parm) {
+                    log::warn!("Could not render texture {parm} of material {name}: {e}");
+                    continue;
+                }
+            }
+            let image = match target {
+                TextureTarget::Skip => None,
+                TextureTarget::Memory(format) => {
+                    let mut data = vec![];
+                    self.extract_image_to_memory(&mut data, "C", format)?;
+                    Some(TextureImage::Memory {
+                        format: format.clone(),
+                        data,
+                    })
+                }
+                TextureTarget::Directory { dir, format } => {
+                    let file = dir.join(format!("{name}_{parm}.{}", format.to_lowercase()));
+                    let path = self.extract_image_to_file("C", file)?;
+                    Some(TextureImage::File(PathBuf::from(path)))
+                }
+            };
+            return Ok(Some(PbrTexture {
+                parm: parm.to_string(),
+                source,
+                image,
+            }));
+        }
+        Ok(None)
+    }
+}
+
+/// Where [`Material::describe_with`] puts rendered textures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextureTarget {
+    /// Don't render, only record the texture parameters.
+    Skip,
+    /// Extract to memory in an image file format, e.g. `"PNG"`.
+    Memory(String),
+    /// Extract to `<dir>/<material>_<parm>.<ext>` in an image file format.
+    Directory { dir: PathBuf, format: String },
+}
+
+/// A rendered texture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextureImage {
+    Memory { format: String, data: Vec<u8> },
+    File(PathBuf),
+}
+
+/// A texture slot of a [`PbrMaterial`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PbrTexture {
+    /// Material parameter the texture was rendered from.
+    pub parm: String,
+    /// Value of the parameter, usually the source image path.
+    pub source: String,
+    /// `None` with [`TextureTarget::Skip`].
+    pub image: Option<TextureImage>,
+}
+
+/// A metallic-roughness description of a material node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PbrMaterial {
+    pub name: String,
+    pub node: NodeHandle,
+    pub base_color: [f32; 3],
+    pub base_color_texture: Option<PbrTexture>,
+    pub roughness: f32,
+    pub roughness_texture: Option<PbrTexture>,
+    pub metalness: f32,
+    pub metalness_texture: Option<PbrTexture>,
+    pub normal_texture: Option<PbrTexture>,
+    /// Emission color multiplied by its intensity.
+    pub emission: [f32; 3],
+    pub emission_texture: Option<PbrTexture>,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        PbrMaterial {
+            name: String::new(),
+            node: NodeHandle(-1),
+            base_color: [1.0; 3],
+            base_color_texture: None,
+            roughness: 1.0,
+            roughness_texture: None,
+            metalness: 0.0,
+            metalness_texture: None,
+            normal_texture: None,
+            emission: [0.0; 3],
+            emission_texture: None,
+        }
+    }
+}
+
+// Parameter names of a material slot, tried in order.
+// Principled Shader first, then MaterialX Standard Surface and game-style texture maps.
+struct Slot {
+    values: &'static [&'static str],
+    /// Texture parameter and the toggle enabling it.
+    textures: &'static [(&'static str, Option<&'static str>)],
+}
+
+const BASE_COLOR: Slot = Slot {
+    values: &["basecolor", "base_color"],
+    textures: &[
+        ("basecolor_texture", Some("basecolor_useTexture")),
+        ("baseColorMap", None),
+    ],
+};
+
+const ROUGHNESS: Slot = Slot {
+    values: &["rough", "specular_roughness"],
+    textures: &[
+        ("rough_texture", Some("rough_useTexture")),
+        ("roughnessMap", None),
+    ],
+};
+
+const METALNESS: Slot = Slot {
+    values: &["metallic", "metalness"],
+    textures: &[
+        ("metallic_texture", Some("metallic_useTexture")),
+        ("metallicMap", None),
+    ],
+};
+
+const NORMAL: Slot = Slot {
+    values: &[],
+    textures: &[
+        ("baseNormal_texture", Some("baseBumpAndNormal_enable")),
+        ("normalMap", None),
+    ],
+};
+
+const EMISSION: Slot = Slot {
+    values: &["emitcolor", "emission_color"],
+    textures: &[
+        ("emitcolor_texture", Some("emitcolor_useTexture")),
+        ("emissiveMap", None),
+    ],
+};
+
+const EMISSION_INTENSITY: &[&str] = &["emitint", "emission"];
+
+/// Materials of a part with a material index for each face.
+#[derive(Debug, Clone)]
+pub struct FaceMaterials {
+    /// Unique materials in the order faces first use them.
+    pub materials: Vec<Material>,
+    /// Index into `materials` per face, -1 for faces without a material.
+    pub indices: Vec<i32>,
+}
+
+impl FaceMaterials {
+    /// Describe all materials, see [`Material::describe_with`].
+    pub fn describe(&self, target: &TextureTarget) -> Result<Vec<PbrMaterial>> {
+        self.materials
+            .iter()
+            .map(|m| m.describe_with(target))
+            .collect()
+    }
+}
+
+// Deduplicate material node ids, returning unique ids and a per-face index.
+fn index_faces(node_ids: &[i32]) -> (Vec<i32>, Vec<i32>) {
+    let mut unique: Vec<i32> = vec![];
+    let indices = node_ids
+        .iter()
+        .map(|&id| {
+            if id < 0 {
+                return -1;
+            }
+            let index = match unique.iter().position(|u| *u == id) {
+                Some(i) => i,
+                None => {
+                    unique.push(id);
+                    unique.len() - 1
+                }
+            };
+            index as i32
+        })
+        .collect();
+    (unique, indices)
+}
+
+impl Geometry {
+    /// Return the materials of a part with a per-face index into them, matching [`Geometry::get_materials`].
+    pub fn get_face_materials(&self, part: Option<&PartInfo>) -> Result<Option<FaceMaterials>> {
+        debug_assert!(
+            self.node.get_info()?.total_cook_count() > 0,
+            "Node not cooked"
+        );
+        let tmp;
+        let part = unwrap_or_create!(tmp, part, self.part_info(0)?.expect("partition id=0"));
+        let (all_the_same, ids) = crate::ffi::get_material_node_ids_on_faces(
+            &self.node.session,
+            self.node.handle,
+            part.face_count(),
+            part.part_id(),
+        )?;
+        let (unique, indices) = match ids.first() {
+            None | Some(-1) if all_the_same => return Ok(None),
+            Some(&id) if all_the_same => (vec![id], vec![0; part.face_count() as usize]),
+            _ => index_faces(&ids),
+        };
+        // Only query each material once, parts often have thousands of faces.
+        let materials = unique
+            .into_iter()
+            .map(|id| {
+                crate::ffi::get_material_info(&self.node.session, NodeHandle(id)).map(|info| {
+                    Material {
+                        session: self.node.session.clone(),
+                        info,
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(FaceMaterials { materials, indices }))
+    }
 }
 
 pub(crate) fn extract_image_to_file(
@@ -125,3 +435,338 @@ pub(crate) fn extract_image_to_memory(
     let image_planes = CString::new(image_planes.as_ref())?;
     crate::ffi::extract_image_to_memory(session, node, buffer, &format, &image_planes)
 }
+
+pub(crate) fn extract_image_pixels(
+    session: &Session,
+    node: NodeHandle,
+    image_planes: impl AsRef<str>,
+) -> Result<RawImage> {
+    let info = crate::ffi::get_image_info(session, node).map(|inner| ImageInfo { inner })?;
+    let mut buffer = vec![];
+    // Raw format uses the data format, packing and interleaving of the image info
+    extract_image_to_memory(session, node, &mut buffer, image_planes, "HAPI_RAW")?;
+    RawImage::from_bytes(
+        info.x_res() as u32,
+        info.y_res() as u32,
+        info.packing(),
+        info.data_format(),
+        info.interleaved(),
+        &buffer,
+    )
+}
+
+/// Pixel values of a [`RawImage`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PixelData {
+    /// [`ImageDataFormat::Int8`]
+    U8(Vec<u8>),
+    /// Other data formats, integers are normalized to `0..=1`.
+    F32(Vec<f32>),
+}
+
+impl PixelData {
+    pub fn len(&self) -> usize {
+        match self {
+            PixelData::U8(v) => v.len(),
+            PixelData::F32(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Decoded image with interleaved channels in `packing` order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawImage {
+    width: u32,
+    height: u32,
+    channels: u8,
+    packing: ImagePacking,
+    data_format: ImageDataFormat,
+    pixels: PixelData,
+}
+
+impl RawImage {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Number of channels per pixel, 1 to 4.
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    pub fn packing(&self) -> ImagePacking {
+        self.packing
+    }
+
+    /// Data format the image was extracted in.
+    pub fn data_format(&self) -> ImageDataFormat {
+        self.data_format
+    }
+
+    /// Interleaved pixel values, `width * height * channels` long.
+    pub fn pixels(&self) -> &PixelData {
+        &self.pixels
+    }
+
+    pub fn into_pixels(self) -> PixelData {
+        self.pixels
+    }
+
+    /// Decode a `HAPI_RAW` buffer. Falls back to a single channel if the buffer
+    /// holds one plane only, as when extracting a single channel plane.
+    pub(crate) fn from_bytes(
+        width: u32,
+        height: u32,
+        packing: ImagePacking,
+        data_format: ImageDataFormat,
+        interleaved: bool,
+        bytes: &[u8],
+    ) -> Result<RawImage> {
+        let size = match data_format {
+            ImageDataFormat::Int8 => 1,
+            ImageDataFormat::Int16 | ImageDataFormat::Float16 => 2,
+            ImageDataFormat::Int32 | ImageDataFormat::Float32 => 4,
+            other => {
+                return Err(HapiError::internal(format!(
+                    "Unsupported image data format {other:?}"
+                )))
+            }
+        };
+        let pixel_count = width as usize * height as usize;
+        let channels = match packing {
+            ImagePacking::Single => 1,
+            ImagePacking::Dual => 2,
+            ImagePacking::Rgb | ImagePacking::Bgr => 3,
+            ImagePacking::Rgba | ImagePacking::Abgr => 4,
+            other => {
+                return Err(HapiError::internal(format!(
+                    "Unsupported image packing {other:?}"
+                )))
+            }
+        };
+        let (channels, packing) = match bytes.len() {
+            n if n == pixel_count * channels * size => (channels, packing),
+            n if n == pixel_count * size => (1, ImagePacking::Single),
+            n => {
+                return Err(HapiError::internal(format!(
+                    "Image buffer of {n} bytes doesn't match {width}x{height} {packing:?} {data_format:?}"
+                )))
+            }
+        };
+        let chunks = bytes.chunks_exact(size);
+        let pixels = match data_format {
+            ImageDataFormat::Int8 => PixelData::U8(bytes.to_vec()),
+            ImageDataFormat::Int16 => PixelData::F32(
+                chunks
+                    .map(|b| u16::from_ne_bytes([b[0], b[1]]) as f32 / u16::MAX as f32)
+                    .collect(),
+            ),
+            ImageDataFormat::Int32 => PixelData::F32(
+                chunks
+                    .map(|b| {
+                        (u32::from_ne_bytes(b.try_into().unwrap()) as f64 / u32::MAX as f64) as f32
+                    })
+                    .collect(),
+            ),
+            ImageDataFormat::Float16 => PixelData::F32(
+                chunks
+                    .map(|b| crate::geofile::binary::f16_to_f32(u16::from_ne_bytes([b[0], b[1]])))
+                    .collect(),
+            ),
+            _ => PixelData::F32(
+                chunks
+                    .map(|b| f32::from_ne_bytes(b.try_into().unwrap()))
+                    .collect(),
+            ),
+        };
+        let pixels = match pixels {
+            PixelData::U8(v) if !interleaved => PixelData::U8(interleave(&v, channels)),
+            PixelData::F32(v) if !interleaved => PixelData::F32(interleave(&v, channels)),
+            pixels => pixels,
+        };
+        Ok(RawImage {
+            width,
+            height,
+            channels: channels as u8,
+            packing,
+            data_format,
+            pixels,
+        })
+    }
+
+    /// Convert to 8-bit RGBA, e.g. for display.
+    /// Single channel images are gray, a second channel is alpha.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let values: Vec<u8> = match &self.pixels {
+            PixelData::U8(v) => v.clone(),
+            PixelData::F32(v) => v
+                .iter()
+                .map(|f| (f.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect(),
+        };
+        let mut rgba = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+        for px in values.chunks_exact(self.channels as usize) {
+            let pixel = match (self.packing, px) {
+                (ImagePacking::Bgr, [b, g, r]) => [*r, *g, *b, 255],
+                (ImagePacking::Abgr, [a, b, g, r]) => [*r, *g, *b, *a],
+                (_, [r, g, b, a]) => [*r, *g, *b, *a],
+                (_, [r, g, b]) => [*r, *g, *b, 255],
+                (_, [v, a]) => [*v, *v, *v, *a],
+                (_, [v]) => [*v, *v, *v, 255],
+                _ => unreachable!("1 to 4 channels"),
+            };
+            rgba.extend_from_slice(&pixel);
+        }
+        rgba
+    }
+}
+
+// Planar RRRGGGBBB to interleaved RGBRGBRGB.
+fn interleave<T: Copy>(planar: &[T], channels: usize) -> Vec<T> {
+    let plane = planar.len() / channels;
+    (0..planar.len())
+        .map(|i| planar[(i % channels) * plane + i / channels])
+        .collect()
+}
+
+#[cfg(feature = "image")]
+impl TryFrom<RawImage> for image::DynamicImage {
+    type Error = HapiError;
+
+    /// 8-bit images keep their channel count, others convert to 32-bit float RGB(A).
+    fn try_from(raw: RawImage) -> Result<Self> {
+        use image::{DynamicImage, ImageBuffer};
+        fn reorder<T>(packing: ImagePacking, px: &mut [T]) {
+            match packing {
+                ImagePacking::Bgr => px.swap(0, 2),
+                ImagePacking::Abgr => px.reverse(),
+                _ => {}
+            }
+        }
+        fn buffer<P: image::Pixel>(
+            w: u32,
+            h: u32,
+            v: Vec<P::Subpixel>,
+        ) -> Result<ImageBuffer<P, Vec<P::Subpixel>>> {
+            ImageBuffer::from_raw(w, h, v).ok_or_else(|| {
+                HapiError::internal(format!("Pixel buffer too small for {w}x{h} image"))
+            })
+        }
+        let (w, h) = (raw.width, raw.height);
+        Ok(match raw.pixels {
+            PixelData::U8(mut v) => {
+                v.chunks_exact_mut(raw.channels as usize)
+                    .for_each(|px| reorder(raw.packing, px));
+                match raw.channels {
+                    1 => DynamicImage::ImageLuma8(buffer(w, h, v)?),
+                    2 => DynamicImage::ImageLumaA8(buffer(w, h, v)?),
+                    3 => DynamicImage::ImageRgb8(buffer(w, h, v)?),
+                    _ => DynamicImage::ImageRgba8(buffer(w, h, v)?),
+                }
+            }
+            PixelData::F32(mut v) => {
+                v.chunks_exact_mut(raw.channels as usize)
+                    .for_each(|px| reorder(raw.packing, px));
+                match raw.channels {
+                    1 => DynamicImage::ImageRgb32F(buffer(
+                        w,
+                        h,
+                        v.iter().flat_map(|f| [*f; 3]).collect(),
+                    )?),
+                    2 => DynamicImage::ImageRgba32F(buffer(
+                        w,
+                        h,
+                        v.chunks_exact(2)
+                            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                            .collect(),
+                    )?),
+                    3 => DynamicImage::ImageRgb32F(buffer(w, h, v)?),
+                    _ => DynamicImage::ImageRgba32F(buffer(w, h, v)?),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_material_indices() {
+        let (unique, indices) = index_faces(&[7, 7, -1, 3, 7, 3, 9]);
+        assert_eq!(unique, [7, 3, 9]);
+        assert_eq!(indices, [0, 0, -1, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn raw_image_decoding() {
+        let planar: Vec<u8> = [1.0f32, 2.0, 3.0, 4.0]
+            .iter()
+            .flat_map(|f| f.to_ne_bytes())
+            .collect();
+        let image = RawImage::from_bytes(
+            2,
+            1,
+            ImagePacking::Dual,
+            ImageDataFormat::Float32,
+            false,
+            &planar,
+        )
+        .unwrap();
+        assert_eq!(image.channels, 2);
+        assert_eq!(image.pixels, PixelData::F32(vec![1.0, 3.0, 2.0, 4.0]));
+
+        // A single plane extracted from an RGB image
+        let image = RawImage::from_bytes(
+            2,
+            1,
+            ImagePacking::Rgb,
+            ImageDataFormat::Int8,
+            true,
+            &[10, 20],
+        )
+        .unwrap();
+        assert_eq!(image.packing, ImagePacking::Single);
+        assert_eq!(image.to_rgba8(), [10, 10, 10, 255, 20, 20, 20, 255]);
+
+        let half = RawImage::from_bytes(
+            1,
+            1,
+            ImagePacking::Bgr,
+            ImageDataFormat::Int16,
+            true,
+            &[0, 0, 0xff, 0xff, 0, 0],
+        )
+        .unwrap();
+        assert_eq!(half.pixels, PixelData::F32(vec![0.0, 1.0, 0.0]));
+        assert_eq!(half.to_rgba8(), [0, 255, 0, 255]);
+
+        assert!(RawImage::from_bytes(
+            2,
+            2,
+            ImagePacking::Rgba,
+            ImageDataFormat::Int8,
+            true,
+            &[0; 5]
+        )
+        .is_err());
+        assert!(RawImage::from_bytes(
+            1,
+            1,
+            ImagePacking::Unknown,
+            ImageDataFormat::Int8,
+            true,
+            &[0; 4]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,609 @@
+//! Plain Rust polygon mesh for uploading geometry in a single call
+//!
+//! Writing a mesh through the low level APIs takes many steps: [`PartInfo`], positions, vertex list,
+//! face counts, attributes, groups and a commit. [`Mesh`] holds all of that data and
+//! [`Geometry::set_mesh`] validates and uploads it.
+//!
+//! [`Geometry::read_mesh`] does the opposite and can optionally triangulate, compute normals and
+//! weld the mesh into buffers ready for a renderer.
+//!
+//! Meshes can be read from and written to Wavefront OBJ and PLY files, see
+//! [`Geometry::import_obj`] and [`Geometry::import_ply`].
+//!
+//! ```ignore
+//! use hapi_rs::mesh::Mesh;
+//! let mesh = Mesh::new(
+//!     vec![[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+//!     vec![3],
+//!     vec![0, 1, 2],
+//! );
+//! let geo = session.create_input_node("triangle")?;
+//! geo.set_mesh(&mesh)?;
+//! ```
+mod obj;
+mod ops;
+mod ply;
+
+use crate::attribute::{NumericAttr, StringAttr};
+use crate::errors::{HapiError, Result};
+use crate::ffi::enums::{AttributeOwner, GroupType, PartType, StorageType};
+use crate::ffi::{AttributeInfo, PartInfo};
+use crate::geometry::Geometry;
+use crate::utils::unwrap_or_create;
+
+/// Values of a [`MeshAttribute`], flattened by tuple size.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValues {
+    Int(Vec<i32>),
+    Int64(Vec<i64>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    String(Vec<String>),
+}
+
+impl AttributeValues {
+    pub fn len(&self) -> usize {
+        match self {
+            AttributeValues::Int(v) => v.len(),
+            AttributeValues::Int64(v) => v.len(),
+            AttributeValues::Float(v) => v.len(),
+            AttributeValues::Double(v) => v.len(),
+            AttributeValues::String(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy of the values in `range`.
+    pub fn slice(&self, range: std::ops::Range<usize>) -> AttributeValues {
+        match self {
+            AttributeValues::Int(v) => AttributeValues::Int(v[range].to_vec()),
+            AttributeValues::Int64(v) => AttributeValues::Int64(v[range].to_vec()),
+            AttributeValues::Float(v) => AttributeValues::Float(v[range].to_vec()),
+            AttributeValues::Double(v) => AttributeValues::Double(v[range].to_vec()),
+            AttributeValues::String(v) => AttributeValues::String(v[range].to_vec()),
+        }
+    }
+
+    pub fn storage(&self) -> StorageType {
+        match self {
+            AttributeValues::Int(_) => StorageType::Int,
+            AttributeValues::Int64(_) => StorageType::Int64,
+            AttributeValues::Float(_) => StorageType::Float,
+            AttributeValues::Double(_) => StorageType::Float64,
+            AttributeValues::String(_) => StorageType::String,
+        }
+    }
+}
+
+/// An arbitrary attribute on a [`Mesh`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshAttribute {
+    pub name: String,
+    pub owner: AttributeOwner,
+    pub tuple_size: i32,
+    pub values: AttributeValues,
+}
+
+/// A 3 component attribute with its owner, used for normals, uvs and colors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshChannel {
+    /// One of Point, Vertex or Prim.
+    pub owner: AttributeOwner,
+    pub values: Vec<[f32; 3]>,
+}
+
+/// A named point or primitive group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshGroup {
+    pub name: String,
+    /// One of Point or Prim.
+    pub group_type: GroupType,
+    /// Indices of the elements in the group.
+    pub members: Vec<i32>,
+}
+
+/// Polygon mesh with attributes and groups.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    /// Point positions.
+    pub positions: Vec<[f32; 3]>,
+    /// Number of vertices in each face.
+    pub face_counts: Vec<i32>,
+    /// Point index of each vertex.
+    pub indices: Vec<i32>,
+    /// Written as "N".
+    pub normals: Option<MeshChannel>,
+    /// Written as "uv", Houdini uvs have 3 components.
+    pub uvs: Option<MeshChannel>,
+    /// Written as "Cd".
+    pub colors: Option<MeshChannel>,
+    pub attributes: Vec<MeshAttribute>,
+    pub groups: Vec<MeshGroup>,
+}
+
+impl Mesh {
+    pub fn new(positions: Vec<[f32; 3]>, face_counts: Vec<i32>, indices: Vec<i32>) -> Self {
+        Mesh {
+            positions,
+            face_counts,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_normals(mut self, owner: AttributeOwner, values: Vec<[f32; 3]>) -> Self {
+        self.normals = Some(MeshChannel { owner, values });
+        self
+    }
+
+    pub fn with_uvs(mut self, owner: AttributeOwner, values: Vec<[f32; 3]>) -> Self {
+        self.uvs = Some(MeshChannel { owner, values });
+        self
+    }
+
+    pub fn with_colors(mut self, owner: AttributeOwner, values: Vec<[f32; 3]>) -> Self {
+        self.colors = Some(MeshChannel { owner, values });
+        self
+    }
+
+    pub fn with_attribute(
+        mut self,
+        name: impl Into<String>,
+        owner: AttributeOwner,
+        tuple_size: i32,
+        values: AttributeValues,
+    ) -> Self {
+        self.attributes.push(MeshAttribute {
+            name: name.into(),
+            owner,
+            tuple_size,
+            values,
+        });
+        self
+    }
+
+    pub fn with_group(
+        mut self,
+        name: impl Into<String>,
+        group_type: GroupType,
+        members: Vec<i32>,
+    ) -> Self {
+        self.groups.push(MeshGroup {
+            name: name.into(),
+            group_type,
+            members,
+        });
+        self
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.face_counts.len()
+    }
+
+    /// Number of elements of the given owner class.
+    pub fn element_count(&self, owner: AttributeOwner) -> usize {
+        match owner {
+            AttributeOwner::Point => self.point_count(),
+            AttributeOwner::Vertex => self.vertex_count(),
+            AttributeOwner::Prim => self.face_count(),
+            AttributeOwner::Detail => 1,
+            AttributeOwner::Invalid | AttributeOwner::Max => 0,
+        }
+    }
+
+    /// Check that all counts, indices and attribute sizes are consistent.
+    pub fn validate(&self) -> Result<()> {
+        let err = |msg: String| Err(HapiError::internal(msg));
+        if let Some(count) = self.face_counts.iter().find(|c| **c < 1) {
+            return err(format!("Invalid face vertex count: {count}"));
+        }
+        let total: i64 = self.face_counts.iter().map(|c| *c as i64).sum();
+        if total != self.indices.len() as i64 {
+            return err(format!(
+                "Sum of face counts ({total}) doesn't match the number of indices ({})",
+                self.indices.len()
+            ));
+        }
+        let point_count = self.point_count() as i32;
+        if let Some(idx) = self.indices.iter().find(|i| !(0..point_count).contains(*i)) {
+            return err(format!(
+                "Vertex index {idx} is out of range for {point_count} points"
+            ));
+        }
+        for (name, channel) in self.channels() {
+            if !matches!(
+                channel.owner,
+                AttributeOwner::Point | AttributeOwner::Vertex | AttributeOwner::Prim
+            ) {
+                return err(format!("Invalid owner for \"{name}\": {:?}", channel.owner));
+            }
+            let expected = self.element_count(channel.owner);
+            if channel.values.len() != expected {
+                return err(format!(
+                    "Attribute \"{name}\" has {} values, expected {expected}",
+                    channel.values.len()
+                ));
+            }
+        }
+        for attr in &self.attributes {
+            if attr.tuple_size < 1 {
+                return err(format!(
+                    "Attribute \"{}\" tuple size must be > 0",
+                    attr.name
+                ));
+            }
+            let expected = self.element_count(attr.owner) * attr.tuple_size as usize;
+            if attr.values.len() != expected {
+                return err(format!(
+                    "Attribute \"{}\" has {} values, expected {expected}",
+                    attr.name,
+                    attr.values.len()
+                ));
+            }
+        }
+        for group in &self.groups {
+            let count = match group.group_type {
+                GroupType::Point => self.point_count(),
+                GroupType::Prim => self.face_count(),
+                other => return err(format!("Unsupported group type: {other:?}")),
+            } as i32;
+            if let Some(idx) = group.members.iter().find(|i| !(0..count).contains(*i)) {
+                return err(format!(
+                    "Group \"{}\" member {idx} is out of range",
+                    group.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn channels(&self) -> impl Iterator<Item = (&'static str, &MeshChannel)> {
+        [
+            ("N", &self.normals),
+            ("uv", &self.uvs),
+            ("Cd", &self.colors),
+        ]
+        .into_iter()
+        .filter_map(|(name, ch)| ch.as_ref().map(|ch| (name, ch)))
+    }
+}
+
+/// How to compute normals in [`Geometry::read_mesh`] when the geometry has no `N` attribute.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ComputeNormals {
+    #[default]
+    None,
+    /// Face normals on vertices.
+    Flat,
+    /// Area weighted normals on points.
+    Smooth,
+}
+
+/// Options for [`Geometry::read_mesh`]. Applied in the order: triangulate, compute normals,
+/// promote, weld.
+#[derive(Debug, Clone, Default)]
+pub struct MeshReadOptions {
+    pub triangulate: bool,
+    pub compute_normals: ComputeNormals,
+    pub promote_to_vertex: bool,
+    pub weld: bool,
+}
+
+impl MeshReadOptions {
+    pub fn with_triangulate(mut self, triangulate: bool) -> Self {
+        self.triangulate = triangulate;
+        self
+    }
+
+    pub fn with_compute_normals(mut self, normals: ComputeNormals) -> Self {
+        self.compute_normals = normals;
+        self
+    }
+
+    pub fn with_promote_to_vertex(mut self, promote: bool) -> Self {
+        self.promote_to_vertex = promote;
+        self
+    }
+
+    pub fn with_weld(mut self, weld: bool) -> Self {
+        self.weld = weld;
+        self
+    }
+
+    /// Triangles with smooth normals and all attributes on points, ready for index buffers.
+    pub fn render() -> Self {
+        MeshReadOptions {
+            triangulate: true,
+            compute_normals: ComputeNormals::Smooth,
+            promote_to_vertex: false,
+            weld: true,
+        }
+    }
+}
+
+fn flatten(values: &[[f32; 3]]) -> Vec<f32> {
+    values.iter().flatten().copied().collect()
+}
+
+impl Geometry {
+    /// Upload a [`Mesh`] into part 0 of this (input) geometry and commit it.
+    pub fn set_mesh(&self, mesh: &Mesh) -> Result<()> {
+        debug_assert!(self.node.is_valid()?);
+        mesh.validate()?;
+        let part = PartInfo::default()
+            .with_part_type(PartType::Mesh)
+            .with_point_count(mesh.point_count() as i32)
+            .with_vertex_count(mesh.vertex_count() as i32)
+            .with_face_count(mesh.face_count() as i32);
+        self.set_part_info(&part)?;
+        let part_id = part.part_id();
+
+        let float_info = |owner: AttributeOwner, tuple_size: i32| {
+            AttributeInfo::default()
+                .with_owner(owner)
+                .with_count(mesh.element_count(owner) as i32)
+                .with_tuple_size(tuple_size)
+                .with_storage(StorageType::Float)
+        };
+        self.add_numeric_attribute::<f32>("P", part_id, float_info(AttributeOwner::Point, 3))?
+            .set(part_id, &flatten(&mesh.positions))?;
+        if !mesh.indices.is_empty() {
+            self.set_vertex_list(part_id, &mesh.indices)?;
+            self.set_face_counts(part_id, &mesh.face_counts)?;
+        }
+        for (name, channel) in mesh.channels() {
+            self.add_numeric_attribute::<f32>(name, part_id, float_info(channel.owner, 3))?
+                .set(part_id, &flatten(&channel.values))?;
+        }
+        for attr in &mesh.attributes {
+            self.add_mesh_attribute(part_id, attr, mesh.element_count(attr.owner))?;
+        }
+        for group in &mesh.groups {
+            let count = match group.group_type {
+                GroupType::Point => mesh.point_count(),
+                _ => mesh.face_count(),
+            };
+            let mut membership = vec![0; count];
+            for idx in &group.members {
+                membership[*idx as usize] = 1;
+            }
+            self.add_group(part_id, group.group_type, &group.name, Some(&membership))?;
+        }
+        log::debug!(
+            "Uploaded mesh: {} points, {} faces",
+            mesh.point_count(),
+            mesh.face_count()
+        );
+        self.commit()
+    }
+
+    /// Read a polygon mesh part into a [`Mesh`].
+    /// `N`, `uv` and `Cd` are looked up on vertices, points, then primitives.
+    /// Other numeric and string attributes, point and primitive groups are read as well.
+    pub fn read_mesh(&self, part: Option<&PartInfo>, options: &MeshReadOptions) -> Result<Mesh> {
+        debug_assert!(
+            self.node.get_info()?.total_cook_count() > 0,
+            "Node not cooked"
+        );
+        let tmp;
+        let part = unwrap_or_create!(tmp, part, self.part_info(0)?.expect("partition id=0"));
+        if part.part_type() != PartType::Mesh {
+            return Err(HapiError::internal(format!(
+                "Part {} is not a mesh: {:?}",
+                part.part_id(),
+                part.part_type()
+            )));
+        }
+        let part_id = part.part_id();
+        let positions = self.get_position_attribute(part_id)?.get(part_id)?;
+        let mut mesh = Mesh::new(
+            to_tuples(&positions, 3),
+            self.get_face_counts(Some(part))?,
+            self.vertex_list(Some(part))?,
+        );
+        mesh.normals = self.read_channel(part_id, "N")?;
+        mesh.uvs = self.read_channel(part_id, "uv")?;
+        mesh.colors = self.read_channel(part_id, "Cd")?;
+        for owner in [
+            AttributeOwner::Point,
+            AttributeOwner::Vertex,
+            AttributeOwner::Prim,
+        ] {
+            for name in self.get_attribute_names(owner, Some(part))?.iter_str() {
+                if matches!(name, "P" | "N" | "uv" | "Cd") {
+                    continue;
+                }
+                if let Some(attr) = self.read_mesh_attribute(part_id, owner, name)? {
+                    mesh.attributes.push(attr);
+                }
+            }
+        }
+        for group_type in [GroupType::Point, GroupType::Prim] {
+            for name in self.get_group_names(group_type)?.iter_str() {
+                let membership = self.get_group_membership(Some(part), group_type, name)?;
+                mesh.groups.push(MeshGroup {
+                    name: name.to_string(),
+                    group_type,
+                    members: (0..membership.len() as i32)
+                        .filter(|i| membership[*i as usize] != 0)
+                        .collect(),
+                });
+            }
+        }
+
+        if options.triangulate {
+            mesh.triangulate();
+        }
+        match options.compute_normals {
+            ComputeNormals::None => {}
+            ComputeNormals::Flat => mesh.compute_normals(false),
+            ComputeNormals::Smooth => mesh.compute_normals(true),
+        }
+        if options.promote_to_vertex {
+            mesh.promote_to_vertex();
+        }
+        if options.weld {
+            mesh.weld();
+        }
+        Ok(mesh)
+    }
+
+    /// Add an attribute with `count` elements and set its values.
+    pub(crate) fn add_mesh_attribute(
+        &self,
+        part_id: i32,
+        attr: &MeshAttribute,
+        count: usize,
+    ) -> Result<()> {
+        let info = AttributeInfo::default()
+            .with_owner(attr.owner)
+            .with_count(count as i32)
+            .with_tuple_size(attr.tuple_size)
+            .with_storage(attr.values.storage());
+        let name = attr.name.as_str();
+        match &attr.values {
+            AttributeValues::Int(v) => self
+                .add_numeric_attribute::<i32>(name, part_id, info)?
+                .set(part_id, v),
+            AttributeValues::Int64(v) => self
+                .add_numeric_attribute::<i64>(name, part_id, info)?
+                .set(part_id, v),
+            AttributeValues::Float(v) => self
+                .add_numeric_attribute::<f32>(name, part_id, info)?
+                .set(part_id, v),
+            AttributeValues::Double(v) => self
+                .add_numeric_attribute::<f64>(name, part_id, info)?
+                .set(part_id, v),
+            AttributeValues::String(v) => {
+                let values: Vec<&str> = v.iter().map(String::as_str).collect();
+                self.add_string_attribute(name, part_id, info)?
+                    .set(part_id, &values)
+            }
+        }
+    }
+
+    /// Read a numeric or string attribute, `None` if missing or of another storage type.
+    pub(crate) fn read_mesh_attribute(
+        &self,
+        part_id: i32,
+        owner: AttributeOwner,
+        name: &str,
+    ) -> Result<Option<MeshAttribute>> {
+        let Some(attr) = self.get_attribute(part_id, owner, name)? else {
+            return Ok(None);
+        };
+        let values = match attr.storage() {
+            StorageType::Int => attr
+                .downcast::<NumericAttr<i32>>()
+                .map(|a| a.get(part_id).map(AttributeValues::Int)),
+            StorageType::Int64 => attr
+                .downcast::<NumericAttr<i64>>()
+                .map(|a| a.get(part_id).map(AttributeValues::Int64)),
+            StorageType::Float => attr
+                .downcast::<NumericAttr<f32>>()
+                .map(|a| a.get(part_id).map(AttributeValues::Float)),
+            StorageType::Float64 => attr
+                .downcast::<NumericAttr<f64>>()
+                .map(|a| a.get(part_id).map(AttributeValues::Double)),
+            StorageType::String => attr
+                .downcast::<StringAttr>()
+                .map(|a| a.get(part_id).map(|s| AttributeValues::String(s.into()))),
+            _ => None,
+        };
+        let Some(values) = values else {
+            return Ok(None);
+        };
+        Ok(Some(MeshAttribute {
+            name: name.to_string(),
+            owner,
+            tuple_size: attr.info().tuple_size(),
+            values: values?,
+        }))
+    }
+
+    fn read_channel(&self, part_id: i32, name: &str) -> Result<Option<MeshChannel>> {
+        for owner in [
+            AttributeOwner::Vertex,
+            AttributeOwner::Point,
+            AttributeOwner::Prim,
+        ] {
+            let Some(attr) = self.get_attribute(part_id, owner, name)? else {
+                continue;
+            };
+            let tuple_size = attr.info().tuple_size() as usize;
+            let values = match attr.storage() {
+                StorageType::Float => match attr.downcast::<NumericAttr<f32>>() {
+                    Some(attr) => attr.get(part_id)?,
+                    None => continue,
+                },
+                StorageType::Float64 => match attr.downcast::<NumericAttr<f64>>() {
+                    Some(attr) => attr.get(part_id)?.into_iter().map(|v| v as f32).collect(),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            return Ok(Some(MeshChannel {
+                owner,
+                values: to_tuples(&values, tuple_size),
+            }));
+        }
+        Ok(None)
+    }
+}
+
+// Pad or truncate flat values into 3 component tuples.
+fn to_tuples(values: &[f32], tuple_size: usize) -> Vec<[f32; 3]> {
+    values
+        .chunks_exact(tuple_size.max(1))
+        .map(|t| {
+            let mut out = [0.0; 3];
+            for (o, v) in out.iter_mut().zip(t) {
+                *o = *v;
+            }
+            out
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> Mesh {
+        Mesh::new(
+            vec![[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+            vec![3],
+            vec![0, 1, 2],
+        )
+    }
+
+    #[test]
+    fn mesh_validate() {
+        assert!(triangle().validate().is_ok());
+        let mut bad = triangle();
+        bad.indices[2] = 3;
+        assert!(bad.validate().is_err());
+        let bad = Mesh::new(vec![[0.0; 3]; 3], vec![4], vec![0, 1, 2]);
+        assert!(bad.validate().is_err());
+        let bad = triangle().with_normals(AttributeOwner::Vertex, vec![[0.0, 0.0, 1.0]; 2]);
+        assert!(bad.validate().is_err());
+        let ok = triangle()
+            .with_normals(AttributeOwner::Vertex, vec![[0.0, 0.0, 1.0]; 3])
+            .with_attribute("id", AttributeOwner::Prim, 1, AttributeValues::Int(vec![7]))
+            .with_group("top", GroupType::Point, vec![1]);
+        assert!(ok.validate().is_ok());
+        let bad = triangle().with_group("top", GroupType::Prim, vec![1]);
+        assert!(bad.validate().is_err());
+    }
+}
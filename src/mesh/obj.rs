@@ -0,0 +1,376 @@
+//! Wavefront OBJ import and export
+//!
+//! Positions, vertex colors (`v x y z r g b`), `vt` and `vn` are mapped to `P`, `Cd`, `uv` and `N`.
+//! OBJ groups (`g`) become primitive groups and `usemtl` names are stored in the
+//! `shop_materialpath` primitive attribute, `g default` and `usemtl default` reset them. Houdini
+//! polygons wind clockwise, so the vertex order of every face is reversed on import and export.
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use super::{AttributeValues, Mesh, MeshChannel, MeshReadOptions};
+use crate::errors::{HapiError, Result};
+use crate::ffi::enums::{AttributeOwner, GroupType};
+use crate::ffi::PartInfo;
+use crate::geometry::Geometry;
+
+const MATERIAL_ATTRIB: &str = "shop_materialpath";
+
+impl Mesh {
+    /// Parse OBJ text. Lines, points, curves and surfaces are ignored.
+    pub fn from_obj_str(text: &str) -> Result<Mesh> {
+        let mut positions = vec![];
+        let mut colors = vec![];
+        let mut texcoords = vec![];
+        let mut normals = vec![];
+        let mut face_counts = vec![];
+        let mut indices = vec![];
+        let mut vertex_uvs: Vec<Option<usize>> = vec![];
+        let mut vertex_normals: Vec<Option<usize>> = vec![];
+        let mut groups: Vec<(String, Vec<i32>)> = vec![];
+        let mut current_groups: Vec<usize> = vec![];
+        let mut materials: Vec<String> = vec![];
+        let mut current_material = String::new();
+
+        let mut lines = text.lines().enumerate();
+        while let Some((n, line)) = lines.next() {
+            let err = |msg: &str| HapiError::internal(format!("OBJ line {}: {msg}", n + 1));
+            // Join continued lines
+            let mut line = line.to_string();
+            while line.ends_with('\\') {
+                line.pop();
+                match lines.next() {
+                    Some((_, next)) => line.push_str(next),
+                    None => break,
+                }
+            }
+            let line = line.split('#').next().unwrap_or_default();
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let floats = |tokens: std::str::SplitWhitespace| -> Result<Vec<f32>> {
+                tokens
+                    .map(|t| t.parse::<f32>().map_err(|_| err("invalid number")))
+                    .collect()
+            };
+            let resolve = |token: &str, len: usize| -> Result<usize> {
+                let idx: i64 = token.parse().map_err(|_| err("invalid index"))?;
+                let resolved = if idx < 0 { len as i64 + idx } else { idx - 1 };
+                if idx == 0 || resolved < 0 || resolved >= len as i64 {
+                    return Err(err("index out of range"));
+                }
+                Ok(resolved as usize)
+            };
+            match keyword {
+                "v" => {
+                    let v = floats(tokens)?;
+                    if v.len() < 3 {
+                        return Err(err("vertex needs 3 components"));
+                    }
+                    positions.push([v[0], v[1], v[2]]);
+                    if v.len() >= 6 {
+                        colors.resize(positions.len() - 1, [1.0; 3]);
+                        colors.push([v[3], v[4], v[5]]);
+                    }
+                }
+                "vt" => {
+                    let v = floats(tokens)?;
+                    let get = |i: usize| v.get(i).copied().unwrap_or(0.0);
+                    texcoords.push([get(0), get(1), get(2)]);
+                }
+                "vn" => {
+                    let v = floats(tokens)?;
+                    if v.len() < 3 {
+                        return Err(err("normal needs 3 components"));
+                    }
+                    normals.push([v[0], v[1], v[2]]);
+                }
+                "f" => {
+                    let mut corners = vec![];
+                    for token in tokens {
+                        let mut parts = token.split('/');
+                        let p = resolve(parts.next().unwrap_or_default(), positions.len())?;
+                        let uv = match parts.next() {
+                            Some(t) if !t.is_empty() => Some(resolve(t, texcoords.len())?),
+                            _ => None,
+                        };
+                        let normal = match parts.next() {
+                            Some(t) if !t.is_empty() => Some(resolve(t, normals.len())?),
+                            _ => None,
+                        };
+                        corners.push((p as i32, uv, normal));
+                    }
+                    if corners.len() < 3 {
+                        return Err(err("face needs at least 3 vertices"));
+                    }
+                    let face = face_counts.len() as i32;
+                    face_counts.push(corners.len() as i32);
+                    for (p, uv, normal) in corners.into_iter().rev() {
+                        indices.push(p);
+                        vertex_uvs.push(uv);
+                        vertex_normals.push(normal);
+                    }
+                    for g in &current_groups {
+                        groups[*g].1.push(face);
+                    }
+                    materials.push(current_material.clone());
+                }
+                "g" => {
+                    current_groups = tokens
+                        .filter(|name| *name != "default")
+                        .map(|name| {
+                            groups
+                                .iter()
+                                .position(|(n, _)| n == name)
+                                .unwrap_or_else(|| {
+                                    groups.push((name.to_string(), vec![]));
+                                    groups.len() - 1
+                                })
+                        })
+                        .collect();
+                }
+                "usemtl" => {
+                    current_material = tokens.collect::<Vec<_>>().join(" ");
+                    if current_material == "default" {
+                        current_material.clear();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut mesh = Mesh::new(positions, face_counts, indices);
+        if !colors.is_empty() {
+            colors.resize(mesh.point_count(), [1.0; 3]);
+            mesh.colors = Some(MeshChannel {
+                owner: AttributeOwner::Point,
+                values: colors,
+            });
+        }
+        let per_vertex = |refs: &[Option<usize>], values: &[[f32; 3]]| {
+            refs.iter().any(Option::is_some).then(|| {
+                refs.iter()
+                    .map(|i| i.map(|i| values[i]).unwrap_or_default())
+                    .collect()
+            })
+        };
+        if let Some(uvs) = per_vertex(&vertex_uvs, &texcoords) {
+            mesh = mesh.with_uvs(AttributeOwner::Vertex, uvs);
+        }
+        if let Some(normals) = per_vertex(&vertex_normals, &normals) {
+            mesh = mesh.with_normals(AttributeOwner::Vertex, normals);
+        }
+        if materials.iter().any(|m| !m.is_empty()) {
+            mesh = mesh.with_attribute(
+                MATERIAL_ATTRIB,
+                AttributeOwner::Prim,
+                1,
+                AttributeValues::String(materials),
+            );
+        }
+        for (name, members) in groups {
+            mesh = mesh.with_group(name, GroupType::Prim, members);
+        }
+        Ok(mesh)
+    }
+
+    /// Read an OBJ file.
+    pub fn read_obj(path: impl AsRef<Path>) -> Result<Mesh> {
+        Mesh::from_obj_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Serialize as OBJ text. Point groups and attributes other than `N`, `uv`, `Cd`
+    /// and `shop_materialpath` are not written.
+    pub fn to_obj_string(&self) -> Result<String> {
+        self.validate()?;
+        let mut out = String::new();
+        let point_colors = self
+            .colors
+            .as_ref()
+            .filter(|c| c.owner == AttributeOwner::Point);
+        for (i, p) in self.positions.iter().enumerate() {
+            write!(out, "v {} {} {}", p[0], p[1], p[2]).unwrap();
+            if let Some(colors) = point_colors {
+                let c = colors.values[i];
+                write!(out, " {} {} {}", c[0], c[1], c[2]).unwrap();
+            }
+            out.push('\n');
+        }
+        let uv_refs = self.uvs.as_ref().map(|uvs| {
+            let (values, refs) = dedup(self.vertex_channel(uvs));
+            for uv in values {
+                writeln!(out, "vt {} {}", uv[0], uv[1]).unwrap();
+            }
+            refs
+        });
+        let normal_refs = self.normals.as_ref().map(|normals| {
+            let (values, refs) = dedup(self.vertex_channel(normals));
+            for n in values {
+                writeln!(out, "vn {} {} {}", n[0], n[1], n[2]).unwrap();
+            }
+            refs
+        });
+
+        let mut face_groups = vec![vec![]; self.face_count()];
+        for group in self
+            .groups
+            .iter()
+            .filter(|g| g.group_type == GroupType::Prim)
+        {
+            for face in &group.members {
+                face_groups[*face as usize].push(group.name.as_str());
+            }
+        }
+        let materials = self.attributes.iter().find_map(|a| match &a.values {
+            AttributeValues::String(v)
+                if a.name == MATERIAL_ATTRIB && a.owner == AttributeOwner::Prim =>
+            {
+                Some(v.as_slice())
+            }
+            _ => None,
+        });
+        let mut current_groups: &[&str] = &[];
+        let mut current_material = "";
+        for (face, offset) in self.face_offsets().into_iter().enumerate() {
+            if face_groups[face] != current_groups {
+                current_groups = &face_groups[face];
+                if current_groups.is_empty() {
+                    out.push_str("g default\n");
+                } else {
+                    writeln!(out, "g {}", current_groups.join(" ")).unwrap();
+                }
+            }
+            if let Some(material) = materials.map(|m| m[face].as_str()) {
+                if material != current_material {
+                    let material = if material.is_empty() {
+                        "default"
+                    } else {
+                        material
+                    };
+                    writeln!(out, "usemtl {material}").unwrap();
+                }
+                current_material = material;
+            }
+            out.push('f');
+            let count = self.face_counts[face] as usize;
+            for v in (offset..offset + count).rev() {
+                write!(out, " {}", self.indices[v] + 1).unwrap();
+                match (&uv_refs, &normal_refs) {
+                    (Some(uv), Some(n)) => write!(out, "/{}/{}", uv[v] + 1, n[v] + 1),
+                    (Some(uv), None) => write!(out, "/{}", uv[v] + 1),
+                    (None, Some(n)) => write!(out, "//{}", n[v] + 1),
+                    (None, None) => Ok(()),
+                }
+                .unwrap();
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Write an OBJ file.
+    pub fn write_obj(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_obj_string()?)?;
+        Ok(())
+    }
+}
+
+// Unique values and the index of each input value into them.
+fn dedup(values: Vec<[f32; 3]>) -> (Vec<[f32; 3]>, Vec<usize>) {
+    let mut unique = vec![];
+    let mut lookup = HashMap::new();
+    let refs = values
+        .into_iter()
+        .map(|v| {
+            *lookup.entry(v.map(f32::to_bits)).or_insert_with(|| {
+                unique.push(v);
+                unique.len() - 1
+            })
+        })
+        .collect();
+    (unique, refs)
+}
+
+impl Geometry {
+    /// Read a Wavefront OBJ file and upload it with [`Geometry::set_mesh`].
+    pub fn import_obj(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.set_mesh(&Mesh::read_obj(path)?)
+    }
+
+    /// Write a mesh part to a Wavefront OBJ file.
+    pub fn export_obj(&self, part: Option<&PartInfo>, path: impl AsRef<Path>) -> Result<()> {
+        self.read_mesh(part, &MeshReadOptions::default())?
+            .write_obj(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE_SIDE: &str = "\
+# two quads
+mtllib box.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0 0.5 0.5 0.5
+v 0 1 0
+v 2 0 0
+v 2 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+g front side
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g side
+usemtl blue
+f 2/1/1 5/2/1 -1/3/1 \\
+  3/4/1
+";
+
+    #[test]
+    fn obj_import() {
+        let mesh = Mesh::from_obj_str(CUBE_SIDE).unwrap();
+        mesh.validate().unwrap();
+        assert_eq!(mesh.point_count(), 6);
+        assert_eq!(mesh.face_counts, [4, 4]);
+        // Winding is reversed
+        assert_eq!(mesh.indices, [3, 2, 1, 0, 2, 5, 4, 1]);
+        let colors = mesh.colors.as_ref().unwrap();
+        assert_eq!(colors.values[2], [0.5; 3]);
+        assert_eq!(colors.values[0], [1.0; 3]);
+        assert_eq!(mesh.uvs.as_ref().unwrap().values[0], [0.0, 1.0, 0.0]);
+        assert_eq!(mesh.normals.as_ref().unwrap().values.len(), 8);
+        assert_eq!(mesh.groups[0].name, "front");
+        assert_eq!(mesh.groups[0].members, [0]);
+        assert_eq!(mesh.groups[1].members, [0, 1]);
+        assert_eq!(
+            mesh.attributes[0].values,
+            AttributeValues::String(vec!["red".into(), "blue".into()])
+        );
+        assert!(Mesh::from_obj_str("v 0 0 0\nf 1 2 3").is_err());
+    }
+
+    #[test]
+    fn obj_roundtrip() {
+        let mesh = Mesh::from_obj_str(CUBE_SIDE).unwrap();
+        let text = mesh.to_obj_string().unwrap();
+        assert_eq!(text.matches("\nvt ").count(), 4);
+        assert_eq!(text.matches("\nvn ").count(), 1);
+        assert_eq!(Mesh::from_obj_str(&text).unwrap(), mesh);
+
+        // A face without material after one with a material
+        let mut mesh = mesh;
+        mesh.attributes[0].values = AttributeValues::String(vec!["red".into(), "".into()]);
+        let text = mesh.to_obj_string().unwrap();
+        assert!(text.contains("usemtl default"));
+        assert_eq!(Mesh::from_obj_str(&text).unwrap(), mesh);
+
+        mesh.uvs.as_mut().unwrap().values.pop();
+        assert!(mesh.to_obj_string().is_err());
+    }
+}
@@ -0,0 +1,387 @@
+//! Topology operations on [`Mesh`] used to produce render-ready buffers
+use std::collections::HashMap;
+
+use super::{AttributeValues, Mesh, MeshChannel, MeshGroup};
+use crate::ffi::enums::{AttributeOwner, GroupType};
+
+impl AttributeValues {
+    /// New values made of tuples at the given element indices.
+    pub(crate) fn gather(&self, tuple_size: usize, elements: &[usize]) -> AttributeValues {
+        fn gather<T: Clone>(src: &[T], tuple_size: usize, elements: &[usize]) -> Vec<T> {
+            let mut out = Vec::with_capacity(elements.len() * tuple_size);
+            for e in elements {
+                out.extend_from_slice(&src[e * tuple_size..(e + 1) * tuple_size]);
+            }
+            out
+        }
+        match self {
+            AttributeValues::Int(v) => AttributeValues::Int(gather(v, tuple_size, elements)),
+            AttributeValues::Int64(v) => AttributeValues::Int64(gather(v, tuple_size, elements)),
+            AttributeValues::Float(v) => AttributeValues::Float(gather(v, tuple_size, elements)),
+            AttributeValues::Double(v) => AttributeValues::Double(gather(v, tuple_size, elements)),
+            AttributeValues::String(v) => AttributeValues::String(gather(v, tuple_size, elements)),
+        }
+    }
+
+    // Append the raw bytes of a tuple, used as a hash key for welding.
+    fn write_key(&self, tuple_size: usize, element: usize, key: &mut Vec<u8>) {
+        let range = element * tuple_size..(element + 1) * tuple_size;
+        match self {
+            AttributeValues::Int(v) => v[range].iter().for_each(|x| key.extend(x.to_le_bytes())),
+            AttributeValues::Int64(v) => v[range].iter().for_each(|x| key.extend(x.to_le_bytes())),
+            AttributeValues::Float(v) => v[range]
+                .iter()
+                .for_each(|x| key.extend(x.to_bits().to_le_bytes())),
+            AttributeValues::Double(v) => v[range]
+                .iter()
+                .for_each(|x| key.extend(x.to_bits().to_le_bytes())),
+            AttributeValues::String(v) => v[range].iter().for_each(|x| {
+                key.extend(x.as_bytes());
+                key.push(0);
+            }),
+        }
+    }
+}
+
+fn gather_channel(values: &[[f32; 3]], elements: &[usize]) -> Vec<[f32; 3]> {
+    elements.iter().map(|e| values[*e]).collect()
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len > f32::EPSILON {
+        [v[0] / len, v[1] / len, v[2] / len]
+    } else {
+        [0.0, 1.0, 0.0]
+    }
+}
+
+impl Mesh {
+    /// Offset of the first vertex of each face.
+    pub fn face_offsets(&self) -> Vec<usize> {
+        let mut offset = 0;
+        self.face_counts
+            .iter()
+            .map(|c| {
+                let o = offset;
+                offset += *c as usize;
+                o
+            })
+            .collect()
+    }
+
+    /// Values of a channel for every vertex, whatever its owner.
+    pub(crate) fn vertex_channel(&self, channel: &MeshChannel) -> Vec<[f32; 3]> {
+        match channel.owner {
+            AttributeOwner::Point => self
+                .indices
+                .iter()
+                .map(|p| channel.values[*p as usize])
+                .collect(),
+            AttributeOwner::Prim => self
+                .face_counts
+                .iter()
+                .zip(&channel.values)
+                .flat_map(|(count, v)| std::iter::repeat_n(*v, *count as usize))
+                .collect(),
+            _ => channel.values.clone(),
+        }
+    }
+
+    /// Non-normalized face normal (Newell's method), its length is twice the face area.
+    /// Houdini polygons wind clockwise when seen from the side the normal points to.
+    pub fn face_normal(&self, face: usize, offset: usize) -> [f32; 3] {
+        let count = self.face_counts[face] as usize;
+        let mut n = [0.0f32; 3];
+        for i in 0..count {
+            let a = self.positions[self.indices[offset + i] as usize];
+            let b = self.positions[self.indices[offset + (i + 1) % count] as usize];
+            n[0] += (b[1] - a[1]) * (a[2] + b[2]);
+            n[1] += (b[2] - a[2]) * (a[0] + b[0]);
+            n[2] += (b[0] - a[0]) * (a[1] + b[1]);
+        }
+        n
+    }
+
+    /// Split polygons into triangles with a fan from the first vertex.
+    /// Faces with less than 3 vertices are removed. Concave polygons are not handled.
+    pub fn triangulate(&mut self) {
+        if self.face_counts.iter().all(|c| *c == 3) {
+            return;
+        }
+        let mut vertex_map = Vec::with_capacity(self.indices.len());
+        let mut face_map = Vec::with_capacity(self.face_counts.len());
+        for (face, offset) in self.face_offsets().into_iter().enumerate() {
+            let count = self.face_counts[face] as usize;
+            for i in 1..count.saturating_sub(1) {
+                vertex_map.extend([offset, offset + i, offset + i + 1]);
+                face_map.push(face);
+            }
+        }
+        self.indices = vertex_map.iter().map(|v| self.indices[*v]).collect();
+        self.face_counts = vec![3; face_map.len()];
+        self.remap(AttributeOwner::Vertex, &vertex_map);
+        self.remap(AttributeOwner::Prim, &face_map);
+    }
+
+    /// Compute normals if the mesh has none.
+    /// Smooth normals are area weighted and stored on points, flat normals are stored on vertices.
+    pub fn compute_normals(&mut self, smooth: bool) {
+        if self.normals.is_some() {
+            return;
+        }
+        let offsets = self.face_offsets();
+        let channel = if smooth {
+            let mut values = vec![[0.0f32; 3]; self.point_count()];
+            for (face, offset) in offsets.iter().enumerate() {
+                let n = self.face_normal(face, *offset);
+                for v in *offset..*offset + self.face_counts[face] as usize {
+                    let p = &mut values[self.indices[v] as usize];
+                    *p = [p[0] + n[0], p[1] + n[1], p[2] + n[2]];
+                }
+            }
+            MeshChannel {
+                owner: AttributeOwner::Point,
+                values: values.into_iter().map(normalize).collect(),
+            }
+        } else {
+            let mut values = Vec::with_capacity(self.vertex_count());
+            for (face, offset) in offsets.iter().enumerate() {
+                let n = normalize(self.face_normal(face, *offset));
+                values.extend(std::iter::repeat_n(n, self.face_counts[face] as usize));
+            }
+            MeshChannel {
+                owner: AttributeOwner::Vertex,
+                values,
+            }
+        };
+        self.normals = Some(channel);
+    }
+
+    /// Convert all point attributes to vertex attributes.
+    pub fn promote_to_vertex(&mut self) {
+        let vertex_map: Vec<usize> = self.indices.iter().map(|p| *p as usize).collect();
+        for channel in [&mut self.normals, &mut self.uvs, &mut self.colors]
+            .into_iter()
+            .flatten()
+        {
+            if channel.owner == AttributeOwner::Point {
+                channel.values = gather_channel(&channel.values, &vertex_map);
+                channel.owner = AttributeOwner::Vertex;
+            }
+        }
+        for attr in &mut self.attributes {
+            if attr.owner == AttributeOwner::Point {
+                attr.values = attr.values.gather(attr.tuple_size as usize, &vertex_map);
+                attr.owner = AttributeOwner::Vertex;
+            }
+        }
+    }
+
+    /// Split points where vertex attributes differ and convert vertex attributes to point attributes.
+    /// After welding every point carries all of its data, which is what index buffers expect.
+    pub fn weld(&mut self) {
+        let vertex_channels: Vec<&[[f32; 3]]> = [&self.normals, &self.uvs, &self.colors]
+            .into_iter()
+            .flatten()
+            .filter(|ch| ch.owner == AttributeOwner::Vertex)
+            .map(|ch| ch.values.as_slice())
+            .collect();
+        let vertex_attribs: Vec<_> = self
+            .attributes
+            .iter()
+            .filter(|a| a.owner == AttributeOwner::Vertex)
+            .collect();
+
+        let mut unique: HashMap<Vec<u8>, i32> = HashMap::new();
+        let mut point_map: Vec<usize> = Vec::new();
+        let mut vertex_map: Vec<usize> = Vec::new();
+        let mut indices = Vec::with_capacity(self.indices.len());
+        let mut key = Vec::new();
+        for (vertex, point) in self.indices.iter().enumerate() {
+            key.clear();
+            key.extend(point.to_le_bytes());
+            for ch in &vertex_channels {
+                ch[vertex]
+                    .iter()
+                    .for_each(|x| key.extend(x.to_bits().to_le_bytes()));
+            }
+            for attr in &vertex_attribs {
+                attr.values
+                    .write_key(attr.tuple_size as usize, vertex, &mut key);
+            }
+            let next = point_map.len() as i32;
+            let new_point = *unique.entry(key.clone()).or_insert_with(|| {
+                point_map.push(*point as usize);
+                vertex_map.push(vertex);
+                next
+            });
+            indices.push(new_point);
+        }
+
+        self.positions = gather_channel(&self.positions, &point_map);
+        for channel in [&mut self.normals, &mut self.uvs, &mut self.colors]
+            .into_iter()
+            .flatten()
+        {
+            match channel.owner {
+                AttributeOwner::Point => {
+                    channel.values = gather_channel(&channel.values, &point_map)
+                }
+                AttributeOwner::Vertex => {
+                    channel.values = gather_channel(&channel.values, &vertex_map);
+                    channel.owner = AttributeOwner::Point;
+                }
+                _ => {}
+            }
+        }
+        for attr in &mut self.attributes {
+            let size = attr.tuple_size as usize;
+            match attr.owner {
+                AttributeOwner::Point => attr.values = attr.values.gather(size, &point_map),
+                AttributeOwner::Vertex => {
+                    attr.values = attr.values.gather(size, &vertex_map);
+                    attr.owner = AttributeOwner::Point;
+                }
+                _ => {}
+            }
+        }
+        for group in &mut self.groups {
+            if group.group_type == GroupType::Point {
+                let members = membership(group, point_map.iter().max().map_or(0, |m| m + 1));
+                group.members = (0..point_map.len() as i32)
+                    .filter(|p| members[point_map[*p as usize]])
+                    .collect();
+            }
+        }
+        self.indices = indices;
+    }
+
+    // Rebuild data of the given owner class from old element indices.
+    fn remap(&mut self, owner: AttributeOwner, elements: &[usize]) {
+        for channel in [&mut self.normals, &mut self.uvs, &mut self.colors]
+            .into_iter()
+            .flatten()
+        {
+            if channel.owner == owner {
+                channel.values = gather_channel(&channel.values, elements);
+            }
+        }
+        for attr in &mut self.attributes {
+            if attr.owner == owner {
+                attr.values = attr.values.gather(attr.tuple_size as usize, elements);
+            }
+        }
+        if owner == AttributeOwner::Prim {
+            for group in &mut self.groups {
+                if group.group_type == GroupType::Prim {
+                    let members = membership(group, elements.iter().max().map_or(0, |m| m + 1));
+                    group.members = (0..elements.len() as i32)
+                        .filter(|f| members[elements[*f as usize]])
+                        .collect();
+                }
+            }
+        }
+    }
+}
+
+fn membership(group: &MeshGroup, count: usize) -> Vec<bool> {
+    let mut members = vec![false; count];
+    for m in &group.members {
+        if let Some(slot) = members.get_mut(*m as usize) {
+            *slot = true;
+        }
+    }
+    members
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad() -> Mesh {
+        Mesh::new(
+            vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+            vec![4],
+            vec![0, 1, 2, 3],
+        )
+    }
+
+    #[test]
+    fn triangulate_quad() {
+        let mut mesh = quad()
+            .with_uvs(
+                AttributeOwner::Vertex,
+                vec![[0.0; 3], [1.0; 3], [2.0; 3], [3.0; 3]],
+            )
+            .with_group("all", GroupType::Prim, vec![0]);
+        mesh.triangulate();
+        assert_eq!(mesh.face_counts, [3, 3]);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.uvs.as_ref().unwrap().values[4], [2.0; 3]);
+        assert_eq!(mesh.groups[0].members, [0, 1]);
+        assert!(mesh.validate().is_ok());
+    }
+
+    #[test]
+    fn compute_normals() {
+        // Counter-clockwise seen from +Z, so the quad faces -Z in Houdini
+        let mut flat = quad();
+        flat.compute_normals(false);
+        let normals = flat.normals.unwrap();
+        assert_eq!(normals.owner, AttributeOwner::Vertex);
+        assert!(normals.values.iter().all(|n| *n == [0.0, 0.0, -1.0]));
+        let mut smooth = quad();
+        smooth.compute_normals(true);
+        assert_eq!(smooth.normals.unwrap().values[2], [0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn weld_splits_seams() {
+        // Two triangles sharing an edge, with a uv seam at point 2
+        let mut mesh = Mesh::new(
+            vec![[0.0; 3], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+            vec![3, 3],
+            vec![0, 1, 2, 0, 2, 3],
+        )
+        .with_uvs(
+            AttributeOwner::Vertex,
+            vec![[0.0; 3], [1.0; 3], [2.0; 3], [0.0; 3], [5.0; 3], [3.0; 3]],
+        )
+        .with_attribute(
+            "id",
+            AttributeOwner::Point,
+            1,
+            AttributeValues::Int(vec![10, 11, 12, 13]),
+        )
+        .with_group("corner", GroupType::Point, vec![2]);
+        mesh.weld();
+        assert_eq!(mesh.point_count(), 5);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 3, 4]);
+        assert_eq!(mesh.uvs.as_ref().unwrap().owner, AttributeOwner::Point);
+        assert_eq!(mesh.uvs.as_ref().unwrap().values[3], [5.0; 3]);
+        assert_eq!(
+            mesh.attributes[0].values,
+            AttributeValues::Int(vec![10, 11, 12, 12, 13])
+        );
+        assert_eq!(mesh.groups[0].members, [2, 3]);
+        assert!(mesh.validate().is_ok());
+    }
+
+    #[test]
+    fn promote_points() {
+        let mut mesh = quad().with_colors(
+            AttributeOwner::Point,
+            vec![[0.0; 3], [1.0; 3], [2.0; 3], [3.0; 3]],
+        );
+        mesh.promote_to_vertex();
+        let colors = mesh.colors.unwrap();
+        assert_eq!(colors.owner, AttributeOwner::Vertex);
+        assert_eq!(colors.values.len(), 4);
+    }
+}
@@ -0,0 +1,561 @@
+//! Stanford PLY import and export
+//!
+//! ASCII and binary files of both endiannesses are read. Vertex positions, normals, colors and
+//! texture coordinates are mapped to `P`, `N`, `Cd` and `uv` point attributes, other scalar vertex
+//! and face properties become point and primitive attributes. Files are written in the binary
+//! little endian format. As with OBJ, the vertex order of faces is reversed for Houdini.
+use std::fmt::Write;
+use std::path::Path;
+
+use super::{AttributeValues, Mesh, MeshChannel, MeshReadOptions};
+use crate::errors::{HapiError, Result};
+use crate::ffi::enums::AttributeOwner;
+use crate::ffi::PartInfo;
+use crate::geometry::Geometry;
+
+fn error(msg: impl AsRef<str>) -> HapiError {
+    HapiError::internal(format!("PLY error: {}", msg.as_ref()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, Scalar::F32 | Scalar::F64)
+    }
+
+    // Scale of normalized integer colors
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::U8 => 1.0 / 255.0,
+            Scalar::U16 => 1.0 / 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+struct Property {
+    name: String,
+    kind: Scalar,
+    // Type of the item count for list properties
+    list: Option<Scalar>,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+struct Reader<'a> {
+    format: Format,
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    // Upper bound of values left in the data, for pre-allocating with counts read from the file.
+    fn max_remaining(&self, kind: Scalar) -> usize {
+        let size = match self.format {
+            Format::Ascii => 1,
+            _ => kind.size(),
+        };
+        self.data.len().saturating_sub(self.pos) / size
+    }
+
+    fn read(&mut self, kind: Scalar) -> Result<f64> {
+        if self.format == Format::Ascii {
+            while self.data.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            while self
+                .data
+                .get(self.pos)
+                .is_some_and(|c| !c.is_ascii_whitespace())
+            {
+                self.pos += 1;
+            }
+            return std::str::from_utf8(&self.data[start..self.pos])
+                .ok()
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(|| error("invalid or missing value"));
+        }
+        let size = kind.size();
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + size)
+            .ok_or_else(|| error("unexpected end of data"))?;
+        self.pos += size;
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(bytes);
+        if self.format == Format::BigEndian {
+            buf[..size].reverse();
+        }
+        let b = buf;
+        Ok(match kind {
+            Scalar::I8 => b[0] as i8 as f64,
+            Scalar::U8 => b[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(b),
+        })
+    }
+}
+
+// Scalar property values of an element, one column per property.
+struct Columns<'a> {
+    element: &'a Element,
+    values: Vec<Vec<f64>>,
+}
+
+impl Columns<'_> {
+    fn get(&self, name: &str) -> Option<(&[f64], Scalar)> {
+        self.element
+            .properties
+            .iter()
+            .position(|p| p.name == name && p.list.is_none())
+            .map(|i| (self.values[i].as_slice(), self.element.properties[i].kind))
+    }
+
+    fn channel(&self, names: [&str; 3], scale: bool) -> Option<Vec<[f32; 3]>> {
+        let (x, kind) = self.get(names[0])?;
+        let (y, _) = self.get(names[1])?;
+        let z = self.get(names[2]).map(|(z, _)| z);
+        let scale = if scale { kind.color_scale() } else { 1.0 };
+        Some(
+            (0..x.len())
+                .map(|i| {
+                    let z = z.map(|z| z[i]).unwrap_or_default();
+                    [
+                        (x[i] * scale) as f32,
+                        (y[i] * scale) as f32,
+                        (z * scale) as f32,
+                    ]
+                })
+                .collect(),
+        )
+    }
+
+    // Remaining scalar properties as attributes.
+    fn attributes<'s>(
+        &'s self,
+        skip: &'s [&str],
+    ) -> impl Iterator<Item = (String, AttributeValues)> + 's {
+        self.element
+            .properties
+            .iter()
+            .zip(&self.values)
+            .filter(move |(p, _)| p.list.is_none() && !skip.contains(&p.name.as_str()))
+            .map(|(p, values)| {
+                let name = if p.name == "alpha" {
+                    "Alpha".to_string()
+                } else {
+                    p.name.clone()
+                };
+                let values = if p.kind.is_float() {
+                    AttributeValues::Float(values.iter().map(|v| *v as f32).collect())
+                } else {
+                    AttributeValues::Int(values.iter().map(|v| *v as i32).collect())
+                };
+                (name, values)
+            })
+    }
+}
+
+const VERTEX_PROPERTIES: &[&str] = &[
+    "x",
+    "y",
+    "z",
+    "nx",
+    "ny",
+    "nz",
+    "red",
+    "green",
+    "blue",
+    "s",
+    "t",
+    "u",
+    "v",
+    "texture_u",
+    "texture_v",
+];
+
+impl Mesh {
+    /// Parse PLY data.
+    pub fn from_ply_bytes(bytes: &[u8]) -> Result<Mesh> {
+        let header_end = bytes
+            .windows(10)
+            .position(|w| w == b"end_header")
+            .ok_or_else(|| error("missing end_header"))?;
+        let header = std::str::from_utf8(&bytes[..header_end])
+            .map_err(|_| error("header is not valid text"))?;
+        let mut lines = header.lines().map(str::trim);
+        if lines.next() != Some("ply") {
+            return Err(error("missing ply magic"));
+        }
+        let mut format = None;
+        let mut elements: Vec<Element> = vec![];
+        for line in lines {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.as_slice() {
+                ["format", "ascii", ..] => format = Some(Format::Ascii),
+                ["format", "binary_little_endian", ..] => format = Some(Format::LittleEndian),
+                ["format", "binary_big_endian", ..] => format = Some(Format::BigEndian),
+                ["element", name, count] => elements.push(Element {
+                    name: name.to_string(),
+                    count: count.parse().map_err(|_| error("invalid element count"))?,
+                    properties: vec![],
+                }),
+                ["property", "list", count, kind, name] => {
+                    let element = elements
+                        .last_mut()
+                        .ok_or_else(|| error("property without element"))?;
+                    element.properties.push(Property {
+                        name: name.to_string(),
+                        kind: Scalar::from_name(kind).ok_or_else(|| error("invalid type"))?,
+                        list: Some(Scalar::from_name(count).ok_or_else(|| error("invalid type"))?),
+                    });
+                }
+                ["property", kind, name] => {
+                    let element = elements
+                        .last_mut()
+                        .ok_or_else(|| error("property without element"))?;
+                    element.properties.push(Property {
+                        name: name.to_string(),
+                        kind: Scalar::from_name(kind).ok_or_else(|| error("invalid type"))?,
+                        list: None,
+                    });
+                }
+                _ => {}
+            }
+        }
+        let format = format.ok_or_else(|| error("missing format"))?;
+        // Data starts after the end_header line
+        let mut pos = header_end + 10;
+        if bytes.get(pos) == Some(&b'\r') {
+            pos += 1;
+        }
+        if bytes.get(pos) == Some(&b'\n') {
+            pos += 1;
+        }
+        let mut reader = Reader {
+            format,
+            data: bytes,
+            pos,
+        };
+
+        let mut vertex = None;
+        let mut face = None;
+        let mut faces: Vec<Vec<i32>> = vec![];
+        for element in &elements {
+            let mut values = vec![vec![]; element.properties.len()];
+            let is_face = element.name == "face";
+            for _ in 0..element.count {
+                for (prop, column) in element.properties.iter().zip(&mut values) {
+                    match prop.list {
+                        None => column.push(reader.read(prop.kind)?),
+                        Some(count_kind) => {
+                            let count = reader.read(count_kind)? as usize;
+                            let mut items =
+                                Vec::with_capacity(count.min(reader.max_remaining(prop.kind)));
+                            for _ in 0..count {
+                                items.push(reader.read(prop.kind)? as i32);
+                            }
+                            if is_face
+                                && matches!(prop.name.as_str(), "vertex_indices" | "vertex_index")
+                            {
+                                faces.push(items);
+                            }
+                        }
+                    }
+                }
+            }
+            let columns = Columns { element, values };
+            match element.name.as_str() {
+                "vertex" => vertex = Some(columns),
+                "face" => face = Some(columns),
+                _ => {}
+            }
+        }
+
+        let vertex = vertex.ok_or_else(|| error("missing vertex element"))?;
+        let positions = vertex
+            .channel(["x", "y", "z"], false)
+            .ok_or_else(|| error("missing vertex positions"))?;
+        let mut mesh = Mesh::new(
+            positions,
+            faces.iter().map(|f| f.len() as i32).collect(),
+            faces
+                .into_iter()
+                .flat_map(|f| f.into_iter().rev())
+                .collect(),
+        );
+        let point = |values| {
+            Some(MeshChannel {
+                owner: AttributeOwner::Point,
+                values,
+            })
+        };
+        if let Some(normals) = vertex.channel(["nx", "ny", "nz"], false) {
+            mesh.normals = point(normals);
+        }
+        if let Some(colors) = vertex.channel(["red", "green", "blue"], true) {
+            mesh.colors = point(colors);
+        }
+        for names in [
+            ["s", "t", ""],
+            ["u", "v", ""],
+            ["texture_u", "texture_v", ""],
+        ] {
+            if let Some(uvs) = vertex.channel(names, false) {
+                mesh.uvs = point(uvs);
+                break;
+            }
+        }
+        for (name, values) in vertex.attributes(VERTEX_PROPERTIES) {
+            mesh = mesh.with_attribute(name, AttributeOwner::Point, 1, values);
+        }
+        if let Some(face) = &face {
+            for (name, values) in face.attributes(&[]) {
+                mesh = mesh.with_attribute(name, AttributeOwner::Prim, 1, values);
+            }
+        }
+        mesh.validate()?;
+        Ok(mesh)
+    }
+
+    /// Read a PLY file.
+    pub fn read_ply(path: impl AsRef<Path>) -> Result<Mesh> {
+        Mesh::from_ply_bytes(&std::fs::read(path)?)
+    }
+
+    /// Serialize as binary little endian PLY. Only point channels and single component numeric
+    /// point and primitive attributes can be written, weld the mesh first to convert vertex
+    /// attributes.
+    pub fn to_ply_bytes(&self) -> Vec<u8> {
+        fn point_channel(ch: &Option<MeshChannel>) -> Option<&[[f32; 3]]> {
+            ch.as_ref()
+                .filter(|c| c.owner == AttributeOwner::Point)
+                .map(|c| c.values.as_slice())
+        }
+        let normals = point_channel(&self.normals);
+        let colors = point_channel(&self.colors);
+        let uvs = point_channel(&self.uvs);
+        let scalar_attributes = |owner: AttributeOwner| {
+            self.attributes
+                .iter()
+                .filter(move |a| {
+                    a.owner == owner
+                        && a.tuple_size == 1
+                        && !matches!(a.values, AttributeValues::String(_))
+                })
+                .collect::<Vec<_>>()
+        };
+        let point_attributes = scalar_attributes(AttributeOwner::Point);
+        let prim_attributes = scalar_attributes(AttributeOwner::Prim);
+        let property = |values: &AttributeValues| match values {
+            AttributeValues::Int(_) => "int",
+            AttributeValues::Float(_) => "float",
+            _ => "double",
+        };
+        let property_name = |name: &str| {
+            if name == "Alpha" {
+                "alpha".to_string()
+            } else {
+                name.to_string()
+            }
+        };
+
+        let mut header = String::from("ply\nformat binary_little_endian 1.0\n");
+        writeln!(header, "element vertex {}", self.point_count()).unwrap();
+        header.push_str("property float x\nproperty float y\nproperty float z\n");
+        if normals.is_some() {
+            header.push_str("property float nx\nproperty float ny\nproperty float nz\n");
+        }
+        if colors.is_some() {
+            header.push_str("property uchar red\nproperty uchar green\nproperty uchar blue\n");
+        }
+        if uvs.is_some() {
+            header.push_str("property float s\nproperty float t\n");
+        }
+        for attr in &point_attributes {
+            let name = property_name(&attr.name);
+            writeln!(header, "property {} {name}", property(&attr.values)).unwrap();
+        }
+        writeln!(header, "element face {}", self.face_count()).unwrap();
+        let wide_faces = self.face_counts.iter().any(|c| *c > 255);
+        let count_type = if wide_faces { "int" } else { "uchar" };
+        writeln!(header, "property list {count_type} int vertex_indices").unwrap();
+        for attr in &prim_attributes {
+            let name = property_name(&attr.name);
+            writeln!(header, "property {} {name}", property(&attr.values)).unwrap();
+        }
+        header.push_str("end_header\n");
+
+        let mut out = header.into_bytes();
+        let write_value = |out: &mut Vec<u8>, values: &AttributeValues, i: usize| match values {
+            AttributeValues::Int(v) => out.extend(v[i].to_le_bytes()),
+            AttributeValues::Float(v) => out.extend(v[i].to_le_bytes()),
+            AttributeValues::Int64(v) => out.extend((v[i] as f64).to_le_bytes()),
+            AttributeValues::Double(v) => out.extend(v[i].to_le_bytes()),
+            AttributeValues::String(_) => unreachable!(),
+        };
+        for (i, p) in self.positions.iter().enumerate() {
+            p.iter().for_each(|v| out.extend(v.to_le_bytes()));
+            if let Some(normals) = normals {
+                normals[i].iter().for_each(|v| out.extend(v.to_le_bytes()));
+            }
+            if let Some(colors) = colors {
+                out.extend(colors[i].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8));
+            }
+            if let Some(uvs) = uvs {
+                uvs[i][..2].iter().for_each(|v| out.extend(v.to_le_bytes()));
+            }
+            for attr in &point_attributes {
+                write_value(&mut out, &attr.values, i);
+            }
+        }
+        for (face, offset) in self.face_offsets().into_iter().enumerate() {
+            let count = self.face_counts[face];
+            if wide_faces {
+                out.extend(count.to_le_bytes());
+            } else {
+                out.push(count as u8);
+            }
+            for v in self.indices[offset..offset + count as usize].iter().rev() {
+                out.extend(v.to_le_bytes());
+            }
+            for attr in &prim_attributes {
+                write_value(&mut out, &attr.values, face);
+            }
+        }
+        out
+    }
+
+    /// Write a binary PLY file.
+    pub fn write_ply(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_ply_bytes())?;
+        Ok(())
+    }
+}
+
+impl Geometry {
+    /// Read a PLY file and upload it with [`Geometry::set_mesh`].
+    pub fn import_ply(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.set_mesh(&Mesh::read_ply(path)?)
+    }
+
+    /// Write a mesh part to a binary PLY file. The mesh is welded so vertex attributes can be
+    /// stored on PLY vertices.
+    pub fn export_ply(&self, part: Option<&PartInfo>, path: impl AsRef<Path>) -> Result<()> {
+        self.read_mesh(part, &MeshReadOptions::default().with_weld(true))?
+            .write_ply(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII: &str = "ply
+format ascii 1.0
+comment quad and triangle
+element vertex 5
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float confidence
+element face 2
+property list uchar int vertex_indices
+property int material
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 255 0 0 0.5
+1 0 0 0 255 0 0.5
+1 1 0 0 0 255 1
+0 1 0 255 255 255 1
+2 0 0 0 0 0 0
+4 0 1 2 3 7
+3 1 4 2 8
+0 1
+";
+
+    #[test]
+    fn ply_ascii_import() {
+        let mesh = Mesh::from_ply_bytes(ASCII.as_bytes()).unwrap();
+        assert_eq!(mesh.point_count(), 5);
+        assert_eq!(mesh.face_counts, [4, 3]);
+        assert_eq!(mesh.indices, [3, 2, 1, 0, 2, 4, 1]);
+        assert_eq!(mesh.colors.as_ref().unwrap().values[1], [0.0, 1.0, 0.0]);
+        assert_eq!(mesh.attributes[0].name, "confidence");
+        assert_eq!(mesh.attributes[1].owner, AttributeOwner::Prim);
+        assert_eq!(mesh.attributes[1].values, AttributeValues::Int(vec![7, 8]));
+        assert!(Mesh::from_ply_bytes(b"ply\nformat ascii 1.0\nend_header\n").is_err());
+    }
+
+    #[test]
+    fn ply_binary_roundtrip() {
+        let mesh = Mesh::from_ply_bytes(ASCII.as_bytes()).unwrap();
+        let bytes = mesh.to_ply_bytes();
+        assert!(bytes.starts_with(b"ply\nformat binary_little_endian 1.0\n"));
+        assert_eq!(Mesh::from_ply_bytes(&bytes).unwrap(), mesh);
+
+        // A huge list count must fail on missing data instead of allocating
+        let mut bytes = b"ply\nformat binary_little_endian 1.0\nelement vertex 1\n\
+            property float x\nproperty float y\nproperty float z\nelement face 1\n\
+            property list uint int vertex_indices\nend_header\n"
+            .to_vec();
+        bytes.extend([0; 12]);
+        bytes.extend(u32::MAX.to_le_bytes());
+        assert!(Mesh::from_ply_bytes(&bytes).is_err());
+    }
+}
@@ -16,7 +16,9 @@ use log::debug;
 use crate::pdg::TopNode;
 pub use crate::{
     errors::Result,
-    ffi::{AssetInfo, GeoInfo, KeyFrame, NodeInfo, ObjectInfo, ParmInfo},
+    ffi::{
+        AssetInfo, GeoInfo, HandleBindingInfo, HandleInfo, KeyFrame, NodeInfo, ObjectInfo, ParmInfo,
+    },
     geometry::Geometry,
     parameter::*,
     session::{CookResult, Session},
@@ -355,6 +357,17 @@ impl HoudiniNode {
         })
     }
 
+    /// If the node is an object instancer, get the object instanced by each point of its
+    /// display geometry.
+    pub fn get_instanced_object_ids(&self) -> Result<Vec<NodeHandle>> {
+        debug_assert!(self.is_valid()?, "Invalid node: {}", self.path()?);
+        let count = match self.geometry()? {
+            Some(geo) => geo.part_info(0)?.map_or(0, |part| part.point_count()),
+            None => 0,
+        };
+        crate::ffi::get_instanced_object_ids(&self.session, self.handle, count)
+    }
+
     /// Get a new NodeInfo even for this node.
     pub fn get_info(&self) -> Result<NodeInfo> {
         debug_assert!(self.is_valid()?, "Invalid node: {}", self.path()?);
@@ -476,6 +489,24 @@ impl HoudiniNode {
             session: self.session.clone().into(),
         })
     }
+
+    /// If node is an HDA, return information about its handles (gizmos).
+    pub fn handles(&self) -> Result<Vec<HandleInfo>> {
+        debug_assert!(self.is_valid()?, "Invalid node: {}", self.path()?);
+        let count = self.asset_info()?.handle_count();
+        let infos = crate::ffi::get_handle_info(self, count)?;
+        Ok(infos
+            .into_iter()
+            .enumerate()
+            .map(|(index, inner)| HandleInfo {
+                inner,
+                node: self.handle,
+                index: index as i32,
+                session: self.session.clone().into(),
+            })
+            .collect())
+    }
+
     /// Recursively check all nodes for a specific error.
     pub fn check_for_specific_error(&self, error_bits: i32) -> Result<ErrorCode> {
         debug_assert!(self.is_valid()?, "Invalid node: {}", self.path()?);
@@ -0,0 +1,176 @@
+//! Object node hierarchy with resolved parent/child relations and world transforms
+//!
+//! [`HoudiniNode::get_objects_info`] returns a flat list of objects and [`HoudiniNode::get_transform`]
+//! returns transforms with raw arrays. [`ObjectTree`] links the objects together and
+//! composes their matrices, so the caller doesn't have to deal with [`crate::enums::RSTOrder`].
+//!
+//! World matrices are relative to the network the tree was built from: for an asset that is an
+//! Object subnet, this is the asset node itself, for an Object manager network, this is world space.
+use std::collections::HashMap;
+
+use crate::ffi::raw::RSTOrder;
+use crate::ffi::Transform;
+use crate::node::{HoudiniNode, NodeHandle, NodeType};
+use crate::transform::{multiply, Matrix4};
+use crate::Result;
+
+/// A single object in the [`ObjectTree`]
+#[derive(Debug, Clone)]
+pub struct ObjectEntry {
+    /// Handle to the object node.
+    pub handle: NodeHandle,
+    /// Object node name.
+    pub name: String,
+    /// Index of the parent object in [`ObjectTree::objects`].
+    pub parent: Option<usize>,
+    /// Indices of the child objects in [`ObjectTree::objects`].
+    pub children: Vec<usize>,
+    /// Transform relative to the parent object (or to the tree root for top-level objects).
+    pub local: Transform,
+    /// Composed matrix relative to the tree root.
+    pub world: Matrix4,
+    pub is_visible: bool,
+    pub is_instancer: bool,
+    pub is_instanced: bool,
+}
+
+/// Object hierarchy built from [`HoudiniNode::get_objects_info`].
+#[derive(Debug, Clone)]
+pub struct ObjectTree {
+    root: NodeHandle,
+    objects: Vec<ObjectEntry>,
+    index: HashMap<i32, usize>,
+}
+
+impl ObjectTree {
+    /// Build the object hierarchy of an Object asset or network node.
+    pub fn new(node: &HoudiniNode) -> Result<Self> {
+        debug_assert!(node.is_valid()?, "Invalid node: {}", node.path()?);
+        let session = &node.session;
+        // Must match the parent used by HoudiniNode::get_objects_info
+        let root = match node.info.node_type() {
+            NodeType::Obj => node.info.parent_id(),
+            _ => node.handle,
+        };
+        let infos = node.get_objects_info()?;
+        let index: HashMap<i32, usize> = infos
+            .iter()
+            .enumerate()
+            .map(|(i, info)| (info.node_id().0, i))
+            .collect();
+
+        let mut objects = Vec::with_capacity(infos.len());
+        for info in infos.iter() {
+            let handle = info.node_id();
+            let node_info = handle.info(session)?;
+            // Objects can be nested inside subnets which are not part of the composed list,
+            // walk up the hierarchy until we find an object from the list or hit the root.
+            let mut parent = None;
+            let mut current = node_info.parent_id();
+            while current.0 >= 0 && current != root {
+                if let Some(idx) = index.get(&current.0) {
+                    parent = Some(*idx);
+                    break;
+                }
+                current = current.info(session)?.parent_id();
+            }
+            let relative_to = match parent {
+                Some(idx) => Some(infos[idx].node_id()),
+                None => (root.info(session)?.node_type() == NodeType::Obj).then_some(root),
+            };
+            let local =
+                crate::ffi::get_object_transform(session, handle, relative_to, RSTOrder::Default)
+                    .map(|inner| Transform { inner })?;
+            objects.push(ObjectEntry {
+                handle,
+                name: node_info.name()?,
+                parent,
+                children: vec![],
+                world: local.to_matrix(),
+                local,
+                is_visible: info.is_visible(),
+                is_instancer: info.is_instancer(),
+                is_instanced: info.is_instanced(),
+            });
+        }
+
+        for i in 0..objects.len() {
+            if let Some(parent) = objects[i].parent {
+                objects[parent].children.push(i);
+            }
+        }
+
+        let mut tree = ObjectTree {
+            root,
+            objects,
+            index,
+        };
+        tree.update_world_matrices();
+        Ok(tree)
+    }
+
+    // Parents are not guaranteed to come before children in the composed list,
+    // so traverse from the roots down.
+    fn update_world_matrices(&mut self) {
+        let mut stack: Vec<usize> = self.roots().collect();
+        while let Some(idx) = stack.pop() {
+            let local = self.objects[idx].local.to_matrix();
+            self.objects[idx].world = match self.objects[idx].parent {
+                Some(parent) => multiply(&self.objects[parent].world, &local),
+                None => local,
+            };
+            stack.extend(self.objects[idx].children.iter().copied());
+        }
+    }
+
+    /// Handle of the network node which world matrices are relative to.
+    pub fn root(&self) -> NodeHandle {
+        self.root
+    }
+
+    /// All objects in the tree in the order returned by the Engine.
+    pub fn objects(&self) -> &[ObjectEntry] {
+        &self.objects
+    }
+
+    /// Indices of the top-level objects.
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, obj)| obj.parent.is_none().then_some(i))
+    }
+
+    /// Find an object by its node handle.
+    pub fn get(&self, handle: NodeHandle) -> Option<&ObjectEntry> {
+        self.index.get(&handle.0).map(|idx| &self.objects[*idx])
+    }
+
+    /// Iterate over the children of an object.
+    pub fn children<'a>(&'a self, entry: &'a ObjectEntry) -> impl Iterator<Item = &'a ObjectEntry> {
+        entry.children.iter().map(|idx| &self.objects[*idx])
+    }
+
+    /// Composed matrix of an object relative to the tree root.
+    pub fn world_matrix(&self, handle: NodeHandle) -> Option<Matrix4> {
+        self.get(handle).map(|obj| obj.world)
+    }
+
+    /// Iterate over objects depth-first, parents before children.
+    pub fn iter_depth_first(&self) -> impl Iterator<Item = &ObjectEntry> + '_ {
+        let mut stack: Vec<usize> = self.roots().collect();
+        stack.reverse();
+        std::iter::from_fn(move || {
+            let idx = stack.pop()?;
+            stack.extend(self.objects[idx].children.iter().rev().copied());
+            Some(&self.objects[idx])
+        })
+    }
+}
+
+impl HoudiniNode {
+    /// Build an [`ObjectTree`] from this Object asset or network.
+    pub fn object_tree(&self) -> Result<ObjectTree> {
+        ObjectTree::new(self)
+    }
+}
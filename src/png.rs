@@ -0,0 +1,456 @@
+//! Minimal 16 bit grayscale PNG codec for heightmaps, with a built-in zlib inflate
+//!
+//! The encoder writes uncompressed deflate blocks, which every reader accepts. The decoder only
+//! handles non-interlaced 16 bit grayscale images, the format of Houdini heightmaps.
+use crate::errors::{HapiError, Result};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+fn error(msg: impl AsRef<str>) -> HapiError {
+    HapiError::internal(format!("PNG error: {}", msg.as_ref()))
+}
+
+/// Decoded 16 bit grayscale image.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Png {
+    pub width: u32,
+    pub height: u32,
+    pub samples: Vec<u16>,
+}
+
+fn crc32(chunks: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for byte in chunks.iter().flat_map(|c| c.iter()) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn zlib_store(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(65535).peekable();
+    if blocks.peek().is_none() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+/// Encode 16 bit grayscale samples.
+pub(crate) fn encode(width: u32, height: u32, samples: &[u16]) -> Vec<u8> {
+    let stride = width as usize * 2;
+    assert_eq!(
+        samples.len(),
+        width as usize * height as usize,
+        "PNG data size"
+    );
+    let mut raw = Vec::with_capacity((stride + 1) * height as usize);
+    for row in samples.chunks(width.max(1) as usize).take(height as usize) {
+        raw.push(0);
+        raw.extend(row.iter().flat_map(|v| v.to_be_bytes()));
+    }
+    let mut header = width.to_be_bytes().to_vec();
+    header.extend(height.to_be_bytes());
+    header.extend([16, 0, 0, 0, 0]);
+
+    let mut out = SIGNATURE.to_vec();
+    for (kind, body) in [
+        (b"IHDR", header),
+        (b"IDAT", zlib_store(&raw)),
+        (b"IEND", vec![]),
+    ] {
+        out.extend((body.len() as u32).to_be_bytes());
+        out.extend(kind);
+        out.extend(&body);
+        out.extend(crc32(&[kind, &body]).to_be_bytes());
+    }
+    out
+}
+
+/// Decode a 16 bit grayscale PNG file.
+pub(crate) fn decode(bytes: &[u8]) -> Result<Png> {
+    if !bytes.starts_with(&SIGNATURE) {
+        return Err(error("not a PNG file"));
+    }
+    let mut pos = SIGNATURE.len();
+    let mut header = None;
+    let mut compressed = vec![];
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let body = bytes
+            .get(pos + 8..pos + 8 + len)
+            .ok_or_else(|| error("truncated chunk"))?;
+        match kind {
+            b"IHDR" if len == 13 => header = Some(body),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+        pos += 12 + len;
+    }
+    let header = header.ok_or_else(|| error("missing header"))?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap());
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    let (bit_depth, color_type) = (header[8], header[9]);
+    if (color_type, bit_depth) != (0, 16) {
+        return Err(error(format!(
+            "only 16 bit grayscale images are supported, found color type {color_type} with bit depth {bit_depth}"
+        )));
+    }
+    if header[12] != 0 {
+        return Err(error("interlaced images are not supported"));
+    }
+    let stride = (width as usize)
+        .checked_mul(2)
+        .ok_or_else(|| error("image too large"))?;
+    // Check the header against the data before allocating, inflate stops at the image size
+    let needed = (stride + 1)
+        .checked_mul(height as usize)
+        .ok_or_else(|| error("image too large"))?;
+    let raw = zlib_inflate(&compressed, needed)?;
+    if raw.len() < needed {
+        return Err(error("truncated image data"));
+    }
+    let mut samples = Vec::with_capacity(width as usize * height as usize);
+    let mut prev = vec![0u8; stride];
+    let mut row = vec![0u8; stride];
+    for line in raw.chunks_exact(stride + 1).take(height as usize) {
+        unfilter(line[0], &line[1..], &prev, 2, &mut row)?;
+        samples.extend(
+            row.chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]])),
+        );
+        std::mem::swap(&mut prev, &mut row);
+    }
+    Ok(Png {
+        width,
+        height,
+        samples,
+    })
+}
+
+fn unfilter(filter: u8, line: &[u8], prev: &[u8], bpp: usize, out: &mut [u8]) -> Result<()> {
+    for i in 0..line.len() {
+        let a = if i >= bpp { out[i - bpp] } else { 0 };
+        let b = prev[i];
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+        let predictor = match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            4 => {
+                let p = a as i16 + b as i16 - c as i16;
+                let (pa, pb, pc) = (
+                    (p - a as i16).abs(),
+                    (p - b as i16).abs(),
+                    (p - c as i16).abs(),
+                );
+                if pa <= pb && pa <= pc {
+                    a
+                } else if pb <= pc {
+                    b
+                } else {
+                    c
+                }
+            }
+            _ => return Err(error(format!("invalid filter {filter}"))),
+        };
+        out[i] = line[i].wrapping_add(predictor);
+    }
+    Ok(())
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| error("truncated deflate stream"))?;
+            value |= (((byte >> self.bit) & 1) as u32) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+// Canonical Huffman code stored as symbol counts per length and symbols sorted by code.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for len in 1..16 {
+            offsets[len] = offsets[len - 1] + counts[len - 1];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        Err(error("invalid Huffman code"))
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Decompress a zlib stream, failing if the output would exceed `limit` bytes.
+pub(crate) fn zlib_inflate(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    if data.len() < 2 || (data[0] & 0x0f) != 8 {
+        return Err(error("invalid zlib header"));
+    }
+    let mut reader = BitReader {
+        data,
+        pos: 2,
+        bit: 0,
+    };
+    let mut out = vec![];
+    loop {
+        let last = reader.bits(1)?;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let header = data
+                    .get(reader.pos..reader.pos + 4)
+                    .ok_or_else(|| error("truncated stored block"))?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let start = reader.pos + 4;
+                let block = data
+                    .get(start..start + len)
+                    .ok_or_else(|| error("truncated stored block"))?;
+                if out.len() + len > limit {
+                    return Err(error("decompressed data exceeds the image size"));
+                }
+                out.extend_from_slice(block);
+                reader.pos = start + len;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut reader, &literals, &distances, limit, &mut out)?;
+            }
+            2 => {
+                let hlit = reader.bits(5)? as usize + 257;
+                let hdist = reader.bits(5)? as usize + 1;
+                let hclen = reader.bits(4)? as usize + 4;
+                const ORDER: [usize; 19] = [
+                    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+                ];
+                let mut code_lengths = [0u8; 19];
+                for &i in ORDER.iter().take(hclen) {
+                    code_lengths[i] = reader.bits(3)? as u8;
+                }
+                let codes = Huffman::new(&code_lengths);
+                let mut lengths = vec![];
+                while lengths.len() < hlit + hdist {
+                    let symbol = codes.decode(&mut reader)?;
+                    let (value, repeat) = match symbol {
+                        0..=15 => (symbol as u8, 1),
+                        16 => {
+                            let prev = *lengths.last().ok_or_else(|| error("invalid lengths"))?;
+                            (prev, 3 + reader.bits(2)?)
+                        }
+                        17 => (0, 3 + reader.bits(3)?),
+                        _ => (0, 11 + reader.bits(7)?),
+                    };
+                    lengths.extend(std::iter::repeat_n(value, repeat as usize));
+                }
+                if lengths.len() != hlit + hdist {
+                    return Err(error("invalid lengths"));
+                }
+                let literals = Huffman::new(&lengths[..hlit]);
+                let distances = Huffman::new(&lengths[hlit..]);
+                inflate_block(&mut reader, &literals, &distances, limit, &mut out)?;
+            }
+            _ => return Err(error("invalid block type")),
+        }
+        if last == 1 {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    literals: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        if symbol != 256 && out.len() >= limit {
+            return Err(error("decompressed data exceeds the image size"));
+        }
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let i = symbol - 257;
+                let len = *LENGTH_BASE.get(i).ok_or_else(|| error("invalid length"))? as usize
+                    + reader.bits(LENGTH_EXTRA[i] as u32)? as usize;
+                let d = distances.decode(reader)? as usize;
+                let distance = *DIST_BASE.get(d).ok_or_else(|| error("invalid distance"))? as usize
+                    + reader.bits(DIST_EXTRA[d] as u32)? as usize;
+                if distance > out.len() {
+                    return Err(error("invalid distance"));
+                }
+                if out.len() + len > limit {
+                    return Err(error("decompressed data exceeds the image size"));
+                }
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_roundtrip_16bit() {
+        let samples: Vec<u16> = (0..12).map(|v| v * 5000).collect();
+        let png = decode(&encode(4, 3, &samples)).unwrap();
+        assert_eq!((png.width, png.height), (4, 3));
+        assert_eq!(png.samples, samples);
+    }
+
+    #[test]
+    fn png_reject_invalid_header() {
+        let png = encode(4, 3, &[0; 12]);
+        // IHDR data starts after the signature, chunk length and type
+        let patched = |offset: usize, bytes: &[u8]| {
+            let mut png = png.clone();
+            png[16 + offset..16 + offset + bytes.len()].copy_from_slice(bytes);
+            decode(&png)
+        };
+        assert!(patched(8, &[8]).is_err());
+        assert!(patched(8, &[16, 2]).is_err());
+        assert!(patched(8, &[1, 3]).is_err());
+        assert!(patched(4, &u32::MAX.to_be_bytes()).is_err());
+        assert!(patched(0, &u32::MAX.to_be_bytes()).is_err());
+        // Smaller or larger than the data
+        assert!(patched(4, &2u32.to_be_bytes()).is_err());
+        assert!(patched(4, &4u32.to_be_bytes()).is_err());
+        assert!(patched(8, &[16, 0]).is_ok());
+    }
+
+    #[test]
+    fn inflate_fixed_huffman() {
+        // zlib.compress(b"hello hello hello")
+        let stream = [
+            0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x3a, 0x2e,
+            0x06, 0x7d,
+        ];
+        assert_eq!(zlib_inflate(&stream, 17).unwrap(), b"hello hello hello");
+        assert!(zlib_inflate(&stream, 16).is_err());
+        assert!(zlib_inflate(&zlib_store(&[0; 100]), 99).is_err());
+    }
+
+    #[test]
+    fn unfilter_paeth_and_sub() {
+        let mut out = [0u8; 4];
+        unfilter(1, &[1, 1, 1, 1], &[0; 4], 1, &mut out).unwrap();
+        assert_eq!(out, [1, 2, 3, 4]);
+        let prev = out;
+        unfilter(4, &[0, 0, 0, 0], &prev, 1, &mut out).unwrap();
+        assert_eq!(out, prev);
+    }
+}
@@ -34,6 +34,7 @@ pub use crate::{
 
 pub type SessionState = State;
 
+use crate::material::RawImage;
 use crate::stringhandle::StringHandle;
 use crate::{ffi::raw, utils};
 
@@ -282,6 +283,7 @@ impl Session {
     }
 
     /// Create an input geometry node which can accept modifications
+    /// For a node which is deleted on drop, see [`crate::geometry::InputNode`].
     pub fn create_input_node(&self, name: &str) -> Result<crate::geometry::Geometry> {
         debug!("Creating input node: {}", name);
         debug_assert!(self.is_valid());
@@ -666,6 +668,19 @@ impl Session {
         crate::material::extract_image_to_memory(self, cop_node, buffer, image_planes, format)
     }
 
+    /// Render a COP node and extract an image plane as raw pixels
+    pub fn render_cop_to_pixels(
+        &self,
+        cop_node: impl Into<NodeHandle>,
+        image_planes: impl AsRef<str>,
+    ) -> Result<RawImage> {
+        debug!("Start rendering COP to pixels.");
+        let cop_node = cop_node.into();
+        debug_assert!(cop_node.is_valid(self)?);
+        crate::ffi::render_cop_to_image(self, cop_node)?;
+        crate::material::extract_image_pixels(self, cop_node, image_planes)
+    }
+
     pub fn get_supported_image_formats(&self) -> Result<Vec<ImageFileFormat<'_>>> {
         debug_assert!(self.is_valid());
         crate::ffi::get_supported_image_file_formats(self).map(|v| {
@@ -0,0 +1,474 @@
+//! Transform math without a session and conversions to math libraries
+//!
+//! [`Transform`] and [`TransformEuler`] can be composed into a 4x4 matrix in pure Rust,
+//! respecting the [`RSTOrder`] and [`XYZOrder`] of the transform.
+//!
+//! Matrices are represented as `[f32; 16]` in the same memory layout Houdini uses:
+//! row-major with row vectors, translation in elements `12..15`.
+//! This is identical to a *column-major* matrix with column vectors, which is what
+//! `glam`, `nalgebra` and `mint::ColumnMatrix4` use, so no transposition is needed.
+//!
+//! Conversions are available behind the optional `glam`, `nalgebra` and `mint` features.
+use crate::ffi::raw::{HAPI_Transform, RSTOrder, XYZOrder};
+use crate::ffi::{Transform, TransformEuler};
+
+/// 4x4 matrix in Houdini memory layout (translation at indices 12, 13, 14)
+pub type Matrix4 = [f32; 16];
+
+/// Identity matrix
+pub const IDENTITY: Matrix4 = [
+    1.0, 0.0, 0.0, 0.0, //
+    0.0, 1.0, 0.0, 0.0, //
+    0.0, 0.0, 1.0, 0.0, //
+    0.0, 0.0, 0.0, 1.0,
+];
+
+#[inline]
+fn at(m: &Matrix4, row: usize, col: usize) -> f32 {
+    // Column-major storage: element (row, col) lives at col * 4 + row.
+    m[col * 4 + row]
+}
+
+/// Multiply two matrices: the result applies `b` first, then `a` (`a * b` in column vector notation).
+pub fn multiply(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+    let mut out = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| at(a, row, k) * at(b, k, col)).sum();
+        }
+    }
+    out
+}
+
+/// Transform a point by a matrix
+pub fn transform_point(m: &Matrix4, p: [f32; 3]) -> [f32; 3] {
+    let w = at(m, 3, 0) * p[0] + at(m, 3, 1) * p[1] + at(m, 3, 2) * p[2] + at(m, 3, 3);
+    let w = if w != 0.0 { w } else { 1.0 };
+    [
+        (at(m, 0, 0) * p[0] + at(m, 0, 1) * p[1] + at(m, 0, 2) * p[2] + at(m, 0, 3)) / w,
+        (at(m, 1, 0) * p[0] + at(m, 1, 1) * p[1] + at(m, 1, 2) * p[2] + at(m, 1, 3)) / w,
+        (at(m, 2, 0) * p[0] + at(m, 2, 1) * p[1] + at(m, 2, 2) * p[2] + at(m, 2, 3)) / w,
+    ]
+}
+
+/// Transform a direction vector by a matrix, ignoring translation
+pub fn transform_vector(m: &Matrix4, v: [f32; 3]) -> [f32; 3] {
+    [
+        at(m, 0, 0) * v[0] + at(m, 0, 1) * v[1] + at(m, 0, 2) * v[2],
+        at(m, 1, 0) * v[0] + at(m, 1, 1) * v[1] + at(m, 1, 2) * v[2],
+        at(m, 2, 0) * v[0] + at(m, 2, 1) * v[1] + at(m, 2, 2) * v[2],
+    ]
+}
+
+/// Invert an affine matrix. Returns `None` if the matrix is singular relative to its scale.
+pub fn inverse_affine(m: &Matrix4) -> Option<Matrix4> {
+    let (a, b, c) = (at(m, 0, 0), at(m, 0, 1), at(m, 0, 2));
+    let (d, e, f) = (at(m, 1, 0), at(m, 1, 1), at(m, 1, 2));
+    let (g, h, i) = (at(m, 2, 0), at(m, 2, 1), at(m, 2, 2));
+    let det = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+    // The determinant is at most the product of the column lengths
+    let length = |x: f32, y: f32, z: f32| (x * x + y * y + z * z).sqrt();
+    let scale = length(a, d, g) * length(b, e, h) * length(c, f, i);
+    if det.abs() <= f32::EPSILON * scale || !det.is_normal() {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let r = [
+        [
+            (e * i - f * h) * inv_det,
+            (c * h - b * i) * inv_det,
+            (b * f - c * e) * inv_det,
+        ],
+        [
+            (f * g - d * i) * inv_det,
+            (a * i - c * g) * inv_det,
+            (c * d - a * f) * inv_det,
+        ],
+        [
+            (d * h - e * g) * inv_det,
+            (b * g - a * h) * inv_det,
+            (a * e - b * d) * inv_det,
+        ],
+    ];
+    let t = [at(m, 0, 3), at(m, 1, 3), at(m, 2, 3)];
+    let mut out = IDENTITY;
+    for row in 0..3 {
+        for col in 0..3 {
+            out[col * 4 + row] = r[row][col];
+        }
+        out[12 + row] = -(r[row][0] * t[0] + r[row][1] * t[1] + r[row][2] * t[2]);
+    }
+    Some(out)
+}
+
+fn translation_matrix(t: [f32; 3]) -> Matrix4 {
+    let mut m = IDENTITY;
+    m[12] = t[0];
+    m[13] = t[1];
+    m[14] = t[2];
+    m
+}
+
+// Scale followed by shear, the way Houdini treats them as a single "S" component.
+fn scale_shear_matrix(s: [f32; 3], shear: [f32; 3]) -> Matrix4 {
+    let [xy, xz, yz] = shear;
+    let shear = [
+        1.0, 0.0, 0.0, 0.0, //
+        xy, 1.0, 0.0, 0.0, //
+        xz, yz, 1.0, 0.0, //
+        0.0, 0.0, 0.0, 1.0,
+    ];
+    let mut scale = IDENTITY;
+    scale[0] = s[0];
+    scale[5] = s[1];
+    scale[10] = s[2];
+    multiply(&shear, &scale)
+}
+
+/// Rotation matrix from a quaternion in HAPI order `[x, y, z, w]`
+pub fn quat_to_matrix(q: [f32; 4]) -> Matrix4 {
+    let len = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    let [x, y, z, w] = if len > 0.0 {
+        [q[0] / len, q[1] / len, q[2] / len, q[3] / len]
+    } else {
+        [0.0, 0.0, 0.0, 1.0]
+    };
+    let (xx, yy, zz) = (x * x, y * y, z * z);
+    let (xy, xz, yz) = (x * y, x * z, y * z);
+    let (wx, wy, wz) = (w * x, w * y, w * z);
+    [
+        1.0 - 2.0 * (yy + zz),
+        2.0 * (xy + wz),
+        2.0 * (xz - wy),
+        0.0,
+        2.0 * (xy - wz),
+        1.0 - 2.0 * (xx + zz),
+        2.0 * (yz + wx),
+        0.0,
+        2.0 * (xz + wy),
+        2.0 * (yz - wx),
+        1.0 - 2.0 * (xx + yy),
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    ]
+}
+
+/// Quaternion `[x, y, z, w]` from the rotation part of a matrix without scale
+pub fn matrix_to_quat(m: &Matrix4) -> [f32; 4] {
+    let (m00, m11, m22) = (at(m, 0, 0), at(m, 1, 1), at(m, 2, 2));
+    let trace = m00 + m11 + m22;
+    if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [
+            (at(m, 2, 1) - at(m, 1, 2)) / s,
+            (at(m, 0, 2) - at(m, 2, 0)) / s,
+            (at(m, 1, 0) - at(m, 0, 1)) / s,
+            0.25 * s,
+        ]
+    } else if m00 > m11 && m00 > m22 {
+        let s = (1.0 + m00 - m11 - m22).sqrt() * 2.0;
+        [
+            0.25 * s,
+            (at(m, 0, 1) + at(m, 1, 0)) / s,
+            (at(m, 0, 2) + at(m, 2, 0)) / s,
+            (at(m, 2, 1) - at(m, 1, 2)) / s,
+        ]
+    } else if m11 > m22 {
+        let s = (1.0 + m11 - m00 - m22).sqrt() * 2.0;
+        [
+            (at(m, 0, 1) + at(m, 1, 0)) / s,
+            0.25 * s,
+            (at(m, 1, 2) + at(m, 2, 1)) / s,
+            (at(m, 0, 2) - at(m, 2, 0)) / s,
+        ]
+    } else {
+        let s = (1.0 + m22 - m00 - m11).sqrt() * 2.0;
+        [
+            (at(m, 0, 2) + at(m, 2, 0)) / s,
+            (at(m, 1, 2) + at(m, 2, 1)) / s,
+            0.25 * s,
+            (at(m, 1, 0) - at(m, 0, 1)) / s,
+        ]
+    }
+}
+
+fn axis_rotation(axis: usize, degrees: f32) -> Matrix4 {
+    let (s, c) = degrees.to_radians().sin_cos();
+    let mut m = IDENTITY;
+    let (i, j) = match axis {
+        0 => (1, 2),
+        1 => (2, 0),
+        _ => (0, 1),
+    };
+    m[i * 4 + i] = c;
+    m[j * 4 + j] = c;
+    m[i * 4 + j] = s;
+    m[j * 4 + i] = -s;
+    m
+}
+
+/// Rotation matrix from Euler angles in degrees, applied in the given order.
+pub fn euler_to_matrix(degrees: [f32; 3], order: XYZOrder) -> Matrix4 {
+    let axes = match order {
+        XYZOrder::Xyz => [0, 1, 2],
+        XYZOrder::Xzy => [0, 2, 1],
+        XYZOrder::Yxz => [1, 0, 2],
+        XYZOrder::Yzx => [1, 2, 0],
+        XYZOrder::Zxy => [2, 0, 1],
+        XYZOrder::Zyx => [2, 1, 0],
+    };
+    axes.iter().fold(IDENTITY, |acc, &axis| {
+        multiply(&axis_rotation(axis, degrees[axis]), &acc)
+    })
+}
+
+// Compose scale (with shear), rotation and translation in the order they are applied.
+fn compose(order: RSTOrder, s: &Matrix4, r: &Matrix4, t: &Matrix4) -> Matrix4 {
+    let steps = match order {
+        RSTOrder::Trs => [t, r, s],
+        RSTOrder::Tsr => [t, s, r],
+        RSTOrder::Rts => [r, t, s],
+        RSTOrder::Rst => [r, s, t],
+        RSTOrder::Str => [s, t, r],
+        RSTOrder::Srt => [s, r, t],
+    };
+    steps
+        .iter()
+        .fold(IDENTITY, |acc, step| multiply(step, &acc))
+}
+
+impl Transform {
+    /// Compose the transform into a matrix in pure Rust, without a session round trip.
+    /// See also [`Transform::convert_to_matrix`].
+    pub fn to_matrix(&self) -> Matrix4 {
+        let t = &self.inner;
+        compose(
+            t.rstOrder,
+            &scale_shear_matrix(t.scale, t.shear),
+            &quat_to_matrix(t.rotationQuaternion),
+            &translation_matrix(t.position),
+        )
+    }
+
+    /// Decompose a matrix into a transform with [`RSTOrder::Srt`] order, no session required.
+    /// Shear is not extracted.
+    pub fn from_matrix_srt(matrix: &Matrix4) -> Self {
+        let column = |c: usize| [matrix[c * 4], matrix[c * 4 + 1], matrix[c * 4 + 2]];
+        let length = |v: [f32; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+        let (x, y, z) = (column(0), column(1), column(2));
+        let det = x[0] * (y[1] * z[2] - z[1] * y[2]) - y[0] * (x[1] * z[2] - z[1] * x[2])
+            + z[0] * (x[1] * y[2] - y[1] * x[2]);
+        let sign = if det < 0.0 { -1.0 } else { 1.0 };
+        let scale = [length(x) * sign, length(y), length(z)];
+        let mut rotation = IDENTITY;
+        for (c, s) in scale.iter().enumerate() {
+            let inv = if *s != 0.0 { 1.0 / s } else { 0.0 };
+            for r in 0..3 {
+                rotation[c * 4 + r] = matrix[c * 4 + r] * inv;
+            }
+        }
+        Transform {
+            inner: HAPI_Transform {
+                position: [matrix[12], matrix[13], matrix[14]],
+                rotationQuaternion: matrix_to_quat(&rotation),
+                scale,
+                shear: [0.0; 3],
+                rstOrder: RSTOrder::Srt,
+            },
+        }
+    }
+}
+
+impl TransformEuler {
+    /// Compose the transform into a matrix in pure Rust, without a session round trip.
+    /// See also [`TransformEuler::convert_to_matrix`].
+    pub fn to_matrix(&self) -> Matrix4 {
+        let t = &self.inner;
+        compose(
+            t.rstOrder,
+            &scale_shear_matrix(t.scale, t.shear),
+            &euler_to_matrix(t.rotationEuler, t.rotationOrder),
+            &translation_matrix(t.position),
+        )
+    }
+
+    /// Convert to a quaternion based [`Transform`], keeping position, scale, shear and RST order.
+    pub fn to_transform(&self) -> Transform {
+        let t = &self.inner;
+        Transform {
+            inner: HAPI_Transform {
+                position: t.position,
+                rotationQuaternion: matrix_to_quat(&euler_to_matrix(
+                    t.rotationEuler,
+                    t.rotationOrder,
+                )),
+                scale: t.scale,
+                shear: t.shear,
+                rstOrder: t.rstOrder,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "glam")]
+mod glam_impl {
+    use super::*;
+
+    impl From<&Transform> for glam::Mat4 {
+        fn from(t: &Transform) -> Self {
+            glam::Mat4::from_cols_array(&t.to_matrix())
+        }
+    }
+
+    impl From<&TransformEuler> for glam::Mat4 {
+        fn from(t: &TransformEuler) -> Self {
+            glam::Mat4::from_cols_array(&t.to_matrix())
+        }
+    }
+
+    impl From<glam::Mat4> for Transform {
+        fn from(m: glam::Mat4) -> Self {
+            Transform::from_matrix_srt(&m.to_cols_array())
+        }
+    }
+
+    impl From<&Transform> for glam::Quat {
+        fn from(t: &Transform) -> Self {
+            glam::Quat::from_array(t.rotation())
+        }
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_impl {
+    use super::*;
+
+    impl From<&Transform> for nalgebra::Matrix4<f32> {
+        fn from(t: &Transform) -> Self {
+            nalgebra::Matrix4::from_column_slice(&t.to_matrix())
+        }
+    }
+
+    impl From<&TransformEuler> for nalgebra::Matrix4<f32> {
+        fn from(t: &TransformEuler) -> Self {
+            nalgebra::Matrix4::from_column_slice(&t.to_matrix())
+        }
+    }
+
+    impl From<nalgebra::Matrix4<f32>> for Transform {
+        fn from(m: nalgebra::Matrix4<f32>) -> Self {
+            let mut array = [0.0; 16];
+            array.copy_from_slice(m.as_slice());
+            Transform::from_matrix_srt(&array)
+        }
+    }
+
+    impl From<&Transform> for nalgebra::UnitQuaternion<f32> {
+        fn from(t: &Transform) -> Self {
+            let [x, y, z, w] = t.rotation();
+            nalgebra::UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(w, x, y, z))
+        }
+    }
+}
+
+#[cfg(feature = "mint")]
+mod mint_impl {
+    use super::*;
+
+    fn to_mint(m: Matrix4) -> mint::ColumnMatrix4<f32> {
+        mint::ColumnMatrix4::from(m)
+    }
+
+    impl From<&Transform> for mint::ColumnMatrix4<f32> {
+        fn from(t: &Transform) -> Self {
+            to_mint(t.to_matrix())
+        }
+    }
+
+    impl From<&TransformEuler> for mint::ColumnMatrix4<f32> {
+        fn from(t: &TransformEuler) -> Self {
+            to_mint(t.to_matrix())
+        }
+    }
+
+    impl From<mint::ColumnMatrix4<f32>> for Transform {
+        fn from(m: mint::ColumnMatrix4<f32>) -> Self {
+            let array: [f32; 16] = m.into();
+            Transform::from_matrix_srt(&array)
+        }
+    }
+
+    impl From<&Transform> for mint::Quaternion<f32> {
+        fn from(t: &Transform) -> Self {
+            let [x, y, z, w] = t.rotation();
+            mint::Quaternion {
+                v: mint::Vector3 { x, y, z },
+                s: w,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: &[f32], b: &[f32]) -> bool {
+        a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    #[test]
+    fn euler_rotation_matches_quaternion() {
+        let quat = [
+            0.0,
+            (45f32.to_radians() / 2.0).sin(),
+            0.0,
+            (45f32.to_radians() / 2.0).cos(),
+        ];
+        let a = quat_to_matrix(quat);
+        let b = euler_to_matrix([0.0, 45.0, 0.0], XYZOrder::Xyz);
+        assert!(approx(&a, &b));
+        assert!(approx(&matrix_to_quat(&a), &quat));
+    }
+
+    #[test]
+    fn compose_respects_rst_order() {
+        let s = scale_shear_matrix([2.0, 2.0, 2.0], [0.0; 3]);
+        let r = IDENTITY;
+        let t = translation_matrix([1.0, 0.0, 0.0]);
+        // Scale first, then translate: translation is not scaled.
+        let srt = compose(RSTOrder::Srt, &s, &r, &t);
+        assert!(approx(
+            &transform_point(&srt, [1.0, 0.0, 0.0]),
+            &[3.0, 0.0, 0.0]
+        ));
+        // Translate first, then scale: translation is scaled.
+        let trs = compose(RSTOrder::Trs, &s, &r, &t);
+        assert!(approx(
+            &transform_point(&trs, [1.0, 0.0, 0.0]),
+            &[4.0, 0.0, 0.0]
+        ));
+    }
+
+    #[test]
+    fn affine_inverse() {
+        let m = multiply(
+            &translation_matrix([1.0, 2.0, 3.0]),
+            &euler_to_matrix([10.0, 20.0, 30.0], XYZOrder::Zxy),
+        );
+        let inv = inverse_affine(&m).unwrap();
+        assert!(approx(&multiply(&m, &inv), &IDENTITY));
+
+        // Voxel sized scales are not singular
+        let mut small = IDENTITY;
+        (small[0], small[5], small[10]) = (0.001, 0.001, 0.001);
+        let inv = inverse_affine(&small).unwrap();
+        assert!(approx(&multiply(&small, &inv), &IDENTITY));
+        small[10] = 0.0;
+        assert!(inverse_affine(&small).is_none());
+        assert!(inverse_affine(&[0.0; 16]).is_none());
+    }
+}
@@ -0,0 +1,344 @@
+//! Dense volume grids with world-space sampling
+use super::{VolumeBounds, VolumeStorage};
+use crate::errors::{HapiError, Result};
+use crate::ffi::raw::HAPI_VolumeTileInfo;
+use crate::ffi::{Transform, VolumeInfo, VolumeTileInfo};
+use crate::geometry::Geometry;
+use crate::transform::{inverse_affine, transform_point, transform_vector, Matrix4};
+
+/// A volume read into one contiguous buffer.
+///
+/// Values are stored x-fastest: the tuple of voxel `(x, y, z)` starts at
+/// `((z * res_y + y) * res_x + x) * tuple_size`. Voxel indices are relative to the volume
+/// minimum index, and [`DenseVolume::voxel_to_world`] maps voxel centers to world space.
+/// Its inverse is computed once, when the matrix is set.
+#[derive(Debug, Clone)]
+pub struct DenseVolume<T> {
+    pub resolution: [usize; 3],
+    pub tuple_size: usize,
+    /// Minimum voxel index of the volume in Houdini.
+    pub min_index: [i32; 3],
+    /// Volume transform from [`VolumeInfo`], the scale is half the voxel size.
+    pub transform: Transform,
+    pub(crate) voxel_to_world: Matrix4,
+    // None if voxel_to_world is singular
+    pub(crate) world_to_voxel: Option<Matrix4>,
+    pub data: Vec<T>,
+}
+
+// Voxel centers are spaced by the voxel size along the rotated axes, with the middle of
+// the index range at `center`.
+fn voxel_to_world(transform: &Transform, resolution: [usize; 3], center: [f32; 3]) -> Matrix4 {
+    let scale = transform.scale().map(|s| s * 2.0);
+    let mut matrix = transform
+        .clone()
+        .with_position([0.0; 3])
+        .with_scale(scale)
+        .to_matrix();
+    let middle = resolution.map(|r| (r as f32 - 1.0) * 0.5);
+    let offset = transform_vector(&matrix, middle);
+    for i in 0..3 {
+        matrix[12 + i] = center[i] - offset[i];
+    }
+    matrix
+}
+
+impl<T: VolumeStorage + Default> DenseVolume<T> {
+    /// Create a volume filled with default values. Voxel centers are placed around the
+    /// transform position.
+    pub fn new(resolution: [usize; 3], tuple_size: usize, transform: Transform) -> Self {
+        let voxel_to_world = voxel_to_world(&transform, resolution, transform.position());
+        let min_index = resolution.map(|r| -(r as i32) / 2);
+        DenseVolume {
+            resolution,
+            tuple_size,
+            min_index,
+            transform,
+            world_to_voxel: inverse_affine(&voxel_to_world),
+            voxel_to_world,
+            data: vec![T::default(); resolution.iter().product::<usize>() * tuple_size],
+        }
+    }
+}
+
+impl<T: VolumeStorage> DenseVolume<T> {
+    /// Offset of the first value of voxel `(x, y, z)` in [`DenseVolume::data`].
+    pub fn offset(&self, x: usize, y: usize, z: usize) -> usize {
+        let [rx, ry, _] = self.resolution;
+        ((z * ry + y) * rx + x) * self.tuple_size
+    }
+
+    /// Values of a single voxel.
+    pub fn voxel(&self, x: usize, y: usize, z: usize) -> &[T] {
+        let offset = self.offset(x, y, z);
+        &self.data[offset..offset + self.tuple_size]
+    }
+
+    pub fn voxel_mut(&mut self, x: usize, y: usize, z: usize) -> &mut [T] {
+        let offset = self.offset(x, y, z);
+        &mut self.data[offset..offset + self.tuple_size]
+    }
+
+    /// Matrix from voxel indices to world space.
+    pub fn voxel_to_world(&self) -> &Matrix4 {
+        &self.voxel_to_world
+    }
+
+    /// Replace the voxel to world matrix, e.g. to move the volume.
+    pub fn set_voxel_to_world(&mut self, matrix: Matrix4) {
+        self.world_to_voxel = inverse_affine(&matrix);
+        self.voxel_to_world = matrix;
+    }
+
+    /// World position of a voxel center.
+    pub fn voxel_position(&self, x: usize, y: usize, z: usize) -> [f32; 3] {
+        transform_point(&self.voxel_to_world, [x as f32, y as f32, z as f32])
+    }
+
+    /// Continuous voxel coordinates of a world position, voxel centers are at integers.
+    /// `None` if the voxel to world matrix is singular.
+    pub fn world_to_voxel(&self, position: [f32; 3]) -> Option<[f32; 3]> {
+        self.world_to_voxel
+            .as_ref()
+            .map(|inverse| transform_point(inverse, position))
+    }
+}
+
+impl DenseVolume<f32> {
+    /// Trilinear sample of a tuple component at a world position.
+    /// Positions outside of the volume are clamped to the border voxels.
+    /// Returns NaN for empty volumes and singular transforms.
+    pub fn sample_component(&self, position: [f32; 3], component: usize) -> f32 {
+        if self.resolution.contains(&0) {
+            return f32::NAN;
+        }
+        let Some(p) = self.world_to_voxel(position) else {
+            return f32::NAN;
+        };
+        let mut base = [0usize; 3];
+        let mut frac = [0f32; 3];
+        for i in 0..3 {
+            let max = self.resolution[i].saturating_sub(1) as f32;
+            let c = p[i].clamp(0.0, max);
+            base[i] = (c.floor() as usize).min(self.resolution[i].saturating_sub(2));
+            frac[i] = (c - base[i] as f32).clamp(0.0, 1.0);
+        }
+        let value = |dx: usize, dy: usize, dz: usize| {
+            let x = (base[0] + dx).min(self.resolution[0] - 1);
+            let y = (base[1] + dy).min(self.resolution[1] - 1);
+            let z = (base[2] + dz).min(self.resolution[2] - 1);
+            self.data[self.offset(x, y, z) + component]
+        };
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let x00 = lerp(value(0, 0, 0), value(1, 0, 0), frac[0]);
+        let x10 = lerp(value(0, 1, 0), value(1, 1, 0), frac[0]);
+        let x01 = lerp(value(0, 0, 1), value(1, 0, 1), frac[0]);
+        let x11 = lerp(value(0, 1, 1), value(1, 1, 1), frac[0]);
+        lerp(lerp(x00, x10, frac[1]), lerp(x01, x11, frac[1]), frac[2])
+    }
+
+    /// Trilinear sample of a scalar volume at a world position.
+    pub fn sample(&self, position: [f32; 3]) -> f32 {
+        self.sample_component(position, 0)
+    }
+
+    /// Trilinear sample of a vector volume, such as velocity, at a world position.
+    pub fn sample_vector(&self, position: [f32; 3]) -> [f32; 3] {
+        debug_assert_eq!(self.tuple_size, 3, "Not a vector volume");
+        std::array::from_fn(|i| self.sample_component(position, i))
+    }
+}
+
+fn tile_info(x: i32, y: i32, z: i32) -> VolumeTileInfo {
+    VolumeTileInfo {
+        inner: HAPI_VolumeTileInfo {
+            minX: x,
+            minY: y,
+            minZ: z,
+            isValid: 1,
+        },
+    }
+}
+
+impl Geometry {
+    /// Read a whole volume into a [`DenseVolume`].
+    pub fn read_dense_volume<T: VolumeStorage + Default>(
+        &self,
+        part: i32,
+    ) -> Result<DenseVolume<T>> {
+        debug_assert!(
+            self.node.get_info()?.total_cook_count() > 0,
+            "Node not cooked"
+        );
+        let info = self.volume_info(part)?;
+        let bounds: VolumeBounds = self.volume_bounds(part)?;
+        let resolution = [info.x_length(), info.y_length(), info.z_length()].map(|v| v as usize);
+        let min_index = [info.min_x(), info.min_y(), info.min_z()];
+        let tuple_size = info.tuple_size() as usize;
+        let tile_size = info.tile_size() as usize;
+        let transform = info.transform();
+        let center = [bounds.x_center, bounds.y_center, bounds.z_center];
+        let voxel_to_world = voxel_to_world(&transform, resolution, center);
+        let mut volume = DenseVolume {
+            resolution,
+            tuple_size,
+            min_index,
+            world_to_voxel: inverse_affine(&voxel_to_world),
+            voxel_to_world,
+            transform,
+            data: vec![T::default(); resolution.iter().product::<usize>() * tuple_size],
+        };
+        let mut values = vec![T::default(); tile_size.pow(3) * tuple_size];
+        let mut tile = crate::ffi::get_volume_first_tile_info(&self.node, part)?;
+        while tile.isValid != 0 {
+            T::read_tile(&self.node, part, T::default(), &mut values, &tile)?;
+            let start = [tile.minX, tile.minY, tile.minZ];
+            let data = &mut volume.data;
+            for_tile_voxels(
+                resolution,
+                min_index,
+                tuple_size,
+                start,
+                tile_size,
+                |dense, tiled| {
+                    data[dense..dense + tuple_size]
+                        .copy_from_slice(&values[tiled..tiled + tuple_size])
+                },
+            );
+            crate::ffi::get_volume_next_tile_info(&self.node, part, &mut tile)?;
+        }
+        Ok(volume)
+    }
+
+    /// Write a dense volume to an input geometry part, which must have [`PartType::Volume`]
+    /// part info set. Call [`Geometry::commit`] afterwards.
+    ///
+    /// [`PartType::Volume`]: crate::geometry::PartType::Volume
+    pub fn write_dense_volume<T: VolumeStorage + Default>(
+        &self,
+        part: i32,
+        volume: &DenseVolume<T>,
+    ) -> Result<()> {
+        if volume.data.len() != volume.resolution.iter().product::<usize>() * volume.tuple_size {
+            return Err(HapiError::internal("Dense volume data size mismatch"));
+        }
+        const TILE: usize = 8;
+        let [rx, ry, rz] = volume.resolution;
+        let info = VolumeInfo::default()
+            .with_x_length(rx as i32)
+            .with_y_length(ry as i32)
+            .with_z_length(rz as i32)
+            .with_min_x(volume.min_index[0])
+            .with_min_y(volume.min_index[1])
+            .with_min_z(volume.min_index[2])
+            .with_tuple_size(volume.tuple_size as i32)
+            .with_storage(T::storage())
+            .with_tile_size(TILE as i32)
+            .with_transform(volume.transform.clone());
+        self.set_volume_info(part, &info)?;
+        let mut values = vec![T::default(); TILE.pow(3) * volume.tuple_size];
+        for z in (0..rz).step_by(TILE) {
+            for y in (0..ry).step_by(TILE) {
+                for x in (0..rx).step_by(TILE) {
+                    let start = [
+                        volume.min_index[0] + x as i32,
+                        volume.min_index[1] + y as i32,
+                        volume.min_index[2] + z as i32,
+                    ];
+                    values.fill(T::default());
+                    let n = volume.tuple_size;
+                    for_tile_voxels(
+                        volume.resolution,
+                        volume.min_index,
+                        n,
+                        start,
+                        TILE,
+                        |dense, tiled| {
+                            values[tiled..tiled + n].copy_from_slice(&volume.data[dense..dense + n])
+                        },
+                    );
+                    let tile = tile_info(start[0], start[1], start[2]);
+                    self.write_volume_tile(part, &tile, &values)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Call `f` with the offsets into the dense data and into the tile buffer for each voxel
+// of the tile starting at absolute index `start` that lies inside the volume.
+fn for_tile_voxels(
+    resolution: [usize; 3],
+    min_index: [i32; 3],
+    tuple_size: usize,
+    start: [i32; 3],
+    tile_size: usize,
+    mut f: impl FnMut(usize, usize),
+) {
+    for tz in 0..tile_size {
+        for ty in 0..tile_size {
+            for tx in 0..tile_size {
+                let index = [tx, ty, tz];
+                let mut voxel = [0usize; 3];
+                let inside = (0..3).all(|i| {
+                    let v = start[i] - min_index[i] + index[i] as i32;
+                    voxel[i] = v.max(0) as usize;
+                    v >= 0 && voxel[i] < resolution[i]
+                });
+                if inside {
+                    let [rx, ry, _] = resolution;
+                    let dense = ((voxel[2] * ry + voxel[1]) * rx + voxel[0]) * tuple_size;
+                    let tiled = ((tz * tile_size + ty) * tile_size + tx) * tuple_size;
+                    f(dense, tiled);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dense_volume_sampling() {
+        let transform = Transform::default()
+            .with_position([10.0, 0.0, 0.0])
+            .with_scale([0.5; 3]);
+        let mut volume = DenseVolume::<f32>::new([3, 3, 3], 1, transform);
+        assert_eq!(volume.voxel_position(1, 1, 1), [10.0, 0.0, 0.0]);
+        assert_eq!(volume.voxel_position(0, 0, 0), [9.0, -1.0, -1.0]);
+        for z in 0..3 {
+            for y in 0..3 {
+                for x in 0..3 {
+                    volume.voxel_mut(x, y, z)[0] = x as f32;
+                }
+            }
+        }
+        assert!((volume.sample([10.25, 0.0, 0.3]) - 1.25).abs() < 1e-5);
+        // Clamped outside of the volume
+        assert_eq!(volume.sample([20.0, 0.0, 0.0]), 2.0);
+        assert_eq!(
+            volume.world_to_voxel([9.0, 1.0, 0.0]),
+            Some([0.0, 2.0, 1.0])
+        );
+
+        // Small voxels still have an inverse
+        let tiny = Transform::default().with_scale([0.001; 3]);
+        let mut tiny = DenseVolume::<f32>::new([2, 1, 1], 1, tiny);
+        tiny.voxel_mut(1, 0, 0)[0] = 1.0;
+        assert!((tiny.sample([0.0, 0.0, 0.0]) - 0.5).abs() < 1e-3);
+        let empty = DenseVolume::<f32>::new([0, 4, 4], 1, Transform::default());
+        assert!(empty.sample([0.0; 3]).is_nan());
+
+        let mut velocity = DenseVolume::<f32>::new([2, 2, 2], 3, Transform::default());
+        velocity
+            .voxel_mut(1, 0, 0)
+            .copy_from_slice(&[2.0, 4.0, 6.0]);
+        let p = velocity.voxel_position(0, 0, 0);
+        let q = velocity.voxel_position(1, 0, 0);
+        let middle = std::array::from_fn(|i| (p[i] + q[i]) * 0.5);
+        assert_eq!(velocity.sample_vector(middle), [1.0, 2.0, 3.0]);
+    }
+}
@@ -0,0 +1,457 @@
+//! Pure Rust writers for NRRD, raw + JSON sidecar and OpenVDB volume files
+//!
+//! All formats store voxels in index space and carry [`DenseVolume::voxel_to_world`], so
+//! the volume lines up with the Houdini scene.
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use super::DenseVolume;
+use crate::errors::{HapiError, Result};
+use crate::geofile::json::{self, Value};
+
+fn floats(values: &[f32]) -> Value {
+    Value::FloatArray(values.iter().map(|v| *v as f64).collect())
+}
+
+fn ints<T: Copy + Into<i64>>(values: &[T]) -> Value {
+    Value::IntArray(values.iter().map(|v| (*v).into()).collect())
+}
+
+impl DenseVolume<f32> {
+    fn check_data_size(&self) -> Result<()> {
+        if self.data.len() != self.resolution.iter().product::<usize>() * self.tuple_size {
+            return Err(HapiError::internal("Dense volume data size mismatch"));
+        }
+        Ok(())
+    }
+
+    fn data_le_bytes(&self) -> Vec<u8> {
+        self.data.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// Encode the volume as an NRRD file with raw little-endian float data.
+    /// Tuple components become the fastest axis of a 4D image.
+    pub fn to_nrrd(&self) -> Result<Vec<u8>> {
+        self.check_data_size()?;
+        let m = &self.voxel_to_world;
+        let axis = |i: usize| format!("({},{},{})", m[i * 4], m[i * 4 + 1], m[i * 4 + 2]);
+        let [rx, ry, rz] = self.resolution;
+        let mut header = String::from("NRRD0004\n# Written by hapi-rs\ntype: float\n");
+        if self.tuple_size == 1 {
+            writeln!(header, "dimension: 3\nsizes: {rx} {ry} {rz}").unwrap();
+            header.push_str("kinds: domain domain domain\n");
+        } else {
+            let kind = match self.tuple_size {
+                3 => "3-vector",
+                _ => "vector",
+            };
+            let n = self.tuple_size;
+            writeln!(header, "dimension: 4\nsizes: {n} {rx} {ry} {rz}").unwrap();
+            writeln!(header, "kinds: {kind} domain domain domain").unwrap();
+        }
+        let none = if self.tuple_size == 1 { "" } else { "none " };
+        writeln!(
+            header,
+            "space dimension: 3\nspace directions: {none}{} {} {}",
+            axis(0),
+            axis(1),
+            axis(2)
+        )
+        .unwrap();
+        writeln!(header, "space origin: ({},{},{})", m[12], m[13], m[14]).unwrap();
+        header.push_str("endian: little\nencoding: raw\n\n");
+        let mut out = header.into_bytes();
+        out.extend(self.data_le_bytes());
+        Ok(out)
+    }
+
+    /// Write an NRRD file, see [`DenseVolume::to_nrrd`].
+    pub fn write_nrrd(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_nrrd()?)?;
+        Ok(())
+    }
+
+    /// JSON description of the data written by [`DenseVolume::write_raw`].
+    pub fn raw_sidecar(&self) -> String {
+        let transform = Value::Object(vec![
+            ("position".into(), floats(&self.transform.position())),
+            ("rotation".into(), floats(&self.transform.rotation())),
+            ("scale".into(), floats(&self.transform.scale())),
+        ]);
+        let resolution = self.resolution.map(|r| r as i64);
+        let sidecar = Value::Object(vec![
+            ("type".into(), Value::from("float32")),
+            ("endian".into(), Value::from("little")),
+            ("order".into(), Value::from("x-fastest")),
+            ("resolution".into(), ints(&resolution)),
+            ("tuple_size".into(), Value::Int(self.tuple_size as i64)),
+            ("min_index".into(), ints(&self.min_index)),
+            ("voxel_to_world".into(), floats(&self.voxel_to_world)),
+            ("transform".into(), transform),
+        ]);
+        json::to_string(&sidecar)
+    }
+
+    /// Write raw little-endian floats to `path` and a JSON sidecar describing the layout and
+    /// transform next to it, with a `.json` extension.
+    pub fn write_raw(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.check_data_size()?;
+        let path = path.as_ref();
+        std::fs::write(path, self.data_le_bytes())?;
+        std::fs::write(path.with_extension("json"), self.raw_sidecar())?;
+        Ok(())
+    }
+
+    /// Encode the volume as an uncompressed OpenVDB file with a single float grid, or a
+    /// vec3s grid for 3 component volumes. Voxels equal to `background` are left inactive.
+    pub fn to_vdb(&self, grid_name: &str, background: &[f32]) -> Result<Vec<u8>> {
+        self.check_data_size()?;
+        let grid_type = match self.tuple_size {
+            1 => "Tree_float_5_4_3",
+            3 => "Tree_vec3s_5_4_3",
+            n => {
+                return Err(HapiError::internal(format!(
+                    "VDB export supports 1 or 3 components, volume has {n}"
+                )))
+            }
+        };
+        if background.len() != self.tuple_size {
+            return Err(HapiError::internal(
+                "Background must have one value per component",
+            ));
+        }
+        Ok(vdb::write(self, grid_name, grid_type, background))
+    }
+
+    /// Write an OpenVDB file, see [`DenseVolume::to_vdb`].
+    pub fn write_vdb(
+        &self,
+        path: impl AsRef<Path>,
+        grid_name: &str,
+        background: &[f32],
+    ) -> Result<()> {
+        std::fs::write(path, self.to_vdb(grid_name, background)?)?;
+        Ok(())
+    }
+}
+
+mod vdb {
+    //! OpenVDB file version 224 without compression. The tree is the standard 5-4-3
+    //! configuration; nodes are indexed z-fastest and voxel coordinates are the dense
+    //! volume indices, so the grid transform is the voxel-to-world matrix.
+    use super::*;
+
+    const MAGIC: i64 = 0x5644_4220;
+    const FILE_VERSION: u32 = 224;
+    const LIBRARY_VERSION: [u32; 2] = [9, 0];
+    // All node values follow, no selection mask.
+    const NO_MASK_AND_ALL_VALS: u8 = 6;
+
+    // log2 of the node dimension and of the voxels spanned by a child.
+    const LEAF: (u32, u32) = (3, 0);
+    const INTERNAL1: (u32, u32) = (4, 3);
+    const INTERNAL2: (u32, u32) = (5, 7);
+
+    struct Leaf {
+        mask: Vec<u64>,
+        values: Vec<f32>,
+    }
+
+    type Internal1 = BTreeMap<[i32; 3], Leaf>;
+    type Internal2 = BTreeMap<[i32; 3], Internal1>;
+
+    fn origin(coord: [i32; 3], log2_total: u32) -> [i32; 3] {
+        coord.map(|c| c & !((1 << log2_total) - 1))
+    }
+
+    fn offset(coord: [i32; 3], (log2_dim, log2_child): (u32, u32)) -> usize {
+        let local =
+            coord.map(|c| ((c & ((1 << (log2_dim + log2_child)) - 1)) >> log2_child) as usize);
+        (local[0] << (2 * log2_dim)) | (local[1] << log2_dim) | local[2]
+    }
+
+    struct Writer {
+        out: Vec<u8>,
+    }
+
+    impl Writer {
+        fn bytes(&mut self, bytes: &[u8]) {
+            self.out.extend_from_slice(bytes);
+        }
+        fn u32(&mut self, v: u32) {
+            self.bytes(&v.to_le_bytes());
+        }
+        fn i64(&mut self, v: i64) {
+            self.bytes(&v.to_le_bytes());
+        }
+        fn string(&mut self, s: &str) {
+            self.u32(s.len() as u32);
+            self.bytes(s.as_bytes());
+        }
+        fn coord(&mut self, c: [i32; 3]) {
+            c.iter().for_each(|v| self.bytes(&v.to_le_bytes()));
+        }
+        fn mask(&mut self, words: &[u64]) {
+            words.iter().for_each(|w| self.bytes(&w.to_le_bytes()));
+        }
+        fn values(&mut self, values: &[f32]) {
+            self.bytes(&[NO_MASK_AND_ALL_VALS]);
+            values.iter().for_each(|v| self.bytes(&v.to_le_bytes()));
+        }
+        // Internal node: child mask, empty value mask and background tile values.
+        fn internal<T>(
+            &mut self,
+            children: &BTreeMap<[i32; 3], T>,
+            node: (u32, u32),
+            background: &[f32],
+        ) {
+            let size = 1usize << (3 * node.0);
+            let mut child_mask = vec![0u64; size / 64];
+            let mut values: Vec<f32> = background.repeat(size);
+            for coord in children.keys() {
+                let i = offset(*coord, node);
+                child_mask[i / 64] |= 1 << (i % 64);
+                let n = background.len();
+                values[i * n..(i + 1) * n].fill(0.0);
+            }
+            self.mask(&child_mask);
+            self.mask(&vec![0u64; size / 64]);
+            self.values(&values);
+        }
+    }
+
+    // Collect leaves holding at least one voxel that differs from the background.
+    fn build_tree(volume: &DenseVolume<f32>, background: &[f32]) -> BTreeMap<[i32; 3], Internal2> {
+        let n = volume.tuple_size;
+        let size = 1usize << (3 * LEAF.0);
+        let mut root: BTreeMap<[i32; 3], Internal2> = BTreeMap::new();
+        let [rx, ry, rz] = volume.resolution;
+        for lx in (0..rx).step_by(8) {
+            for ly in (0..ry).step_by(8) {
+                for lz in (0..rz).step_by(8) {
+                    let leaf_origin = [lx, ly, lz].map(|v| v as i32);
+                    let mut leaf = Leaf {
+                        mask: vec![0; size / 64],
+                        values: background.repeat(size),
+                    };
+                    for x in lx..(lx + 8).min(rx) {
+                        for y in ly..(ly + 8).min(ry) {
+                            for z in lz..(lz + 8).min(rz) {
+                                let voxel = volume.voxel(x, y, z);
+                                if voxel == background {
+                                    continue;
+                                }
+                                let i = offset([x, y, z].map(|v| v as i32), LEAF);
+                                leaf.mask[i / 64] |= 1 << (i % 64);
+                                leaf.values[i * n..(i + 1) * n].copy_from_slice(voxel);
+                            }
+                        }
+                    }
+                    if leaf.mask.iter().any(|w| *w != 0) {
+                        root.entry(origin(leaf_origin, INTERNAL2.0 + INTERNAL2.1))
+                            .or_default()
+                            .entry(origin(leaf_origin, INTERNAL1.0 + INTERNAL1.1))
+                            .or_default()
+                            .insert(leaf_origin, leaf);
+                    }
+                }
+            }
+        }
+        root
+    }
+
+    // Stable identifier derived from the contents, formatted like a UUID.
+    fn uuid(data: &[u8]) -> String {
+        let mut hash = [0xcbf2_9ce4_8422_2325u64, 0x8422_2325_cbf2_9ce4];
+        for (i, b) in data.iter().enumerate() {
+            let h = &mut hash[i % 2];
+            *h = (*h ^ *b as u64).wrapping_mul(0x100_0000_01b3);
+        }
+        let hex = format!("{:016x}{:016x}", hash[0], hash[1]);
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+
+    pub(super) fn write(
+        volume: &DenseVolume<f32>,
+        name: &str,
+        grid_type: &str,
+        background: &[f32],
+    ) -> Vec<u8> {
+        let tree = build_tree(volume, background);
+        let mut w = Writer { out: vec![] };
+        w.i64(MAGIC);
+        w.u32(FILE_VERSION);
+        LIBRARY_VERSION.iter().for_each(|v| w.u32(*v));
+        // Grid offsets are present
+        w.bytes(&[1]);
+        let data: Vec<u8> = volume.data.iter().flat_map(|v| v.to_le_bytes()).collect();
+        w.bytes(uuid(&data).as_bytes());
+        // No file metadata, one grid
+        w.u32(0);
+        w.u32(1);
+        w.string(name);
+        w.string(grid_type);
+        // Instance parent name
+        w.string("");
+        let stream_pos = w.out.len();
+        (0..3).for_each(|_| w.i64(0));
+        let grid_pos = w.out.len();
+        // Compression flags, no grid metadata
+        w.u32(0);
+        w.u32(0);
+        w.string("AffineMap");
+        volume
+            .voxel_to_world
+            .iter()
+            .for_each(|v| w.bytes(&(*v as f64).to_le_bytes()));
+        // Topology: buffer count, root background, tile and child counts
+        w.u32(1);
+        w.bytes(
+            &background
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect::<Vec<_>>(),
+        );
+        w.u32(0);
+        w.u32(tree.len() as u32);
+        for (origin, internal2) in &tree {
+            w.coord(*origin);
+            w.internal(internal2, INTERNAL2, background);
+            for internal1 in internal2.values() {
+                w.internal(internal1, INTERNAL1, background);
+                for leaf in internal1.values() {
+                    w.mask(&leaf.mask);
+                }
+            }
+        }
+        let block_pos = w.out.len();
+        for leaf in tree
+            .values()
+            .flat_map(|n| n.values())
+            .flat_map(|n| n.values())
+        {
+            w.mask(&leaf.mask);
+            w.values(&leaf.values);
+        }
+        let end_pos = w.out.len();
+        for (i, pos) in [grid_pos, block_pos, end_pos].into_iter().enumerate() {
+            let at = stream_pos + i * 8;
+            w.out[at..at + 8].copy_from_slice(&(pos as i64).to_le_bytes());
+        }
+        w.out
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn node_offsets() {
+            assert_eq!(offset([1, 2, 3], LEAF), (1 << 6) | (2 << 3) | 3);
+            assert_eq!(offset([9, 0, 17], INTERNAL1), (1 << 8) | 2);
+            assert_eq!(offset([4096 + 128, 0, 0], INTERNAL2), 1 << 10);
+            assert_eq!(origin([130, 7, 4097], 7), [128, 0, 4096]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::raw::{HAPI_Transform, RSTOrder};
+    use crate::ffi::Transform;
+
+    fn volume(tuple_size: usize) -> DenseVolume<f32> {
+        let transform = Transform {
+            inner: HAPI_Transform {
+                position: [1.0, 2.0, 3.0],
+                rotationQuaternion: [0.0, 0.0, 0.0, 1.0],
+                scale: [0.25; 3],
+                shear: [0.0; 3],
+                rstOrder: RSTOrder::Srt,
+            },
+        };
+        let mut m = crate::transform::IDENTITY;
+        m[0] = 0.5;
+        m[5] = 0.5;
+        m[10] = 0.5;
+        m[12..15].copy_from_slice(&[1.0, 2.0, 3.0]);
+        let resolution = [10, 2, 3];
+        let mut data = vec![0.0; 60 * tuple_size];
+        data[(59 * tuple_size)..].fill(2.0);
+        DenseVolume {
+            resolution,
+            tuple_size,
+            min_index: [-5, -1, -1],
+            transform,
+            voxel_to_world: m,
+            world_to_voxel: crate::transform::inverse_affine(&m),
+            data,
+        }
+    }
+
+    #[test]
+    fn nrrd_header() {
+        let bytes = volume(3).to_nrrd().unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+        let (header, _) = text.split_once("\n\n").unwrap();
+        assert!(header.contains("dimension: 4\nsizes: 3 10 2 3\n"));
+        assert!(header.contains("space directions: none (0.5,0,0) (0,0.5,0) (0,0,0.5)"));
+        assert!(header.contains("space origin: (1,2,3)"));
+        assert_eq!(bytes.len(), header.len() + 2 + 180 * 4);
+        assert_eq!(bytes[bytes.len() - 4..], 2f32.to_le_bytes());
+    }
+
+    #[test]
+    fn raw_sidecar() {
+        let sidecar = json::parse(&volume(1).raw_sidecar()).unwrap();
+        assert_eq!(
+            sidecar.get("resolution").unwrap().to_i64_vec(),
+            Some(vec![10, 2, 3])
+        );
+        assert_eq!(
+            sidecar.get("min_index").unwrap().to_i64_vec(),
+            Some(vec![-5, -1, -1])
+        );
+        let matrix = sidecar.get("voxel_to_world").unwrap().to_f64_vec().unwrap();
+        assert_eq!(matrix[12..15], [1.0, 2.0, 3.0]);
+        let transform = sidecar.get("transform").unwrap();
+        assert_eq!(
+            transform.get("scale").unwrap().to_f64_vec(),
+            Some(vec![0.25; 3])
+        );
+    }
+
+    #[test]
+    fn vdb_layout() {
+        let volume = volume(1);
+        assert!(volume.to_vdb("density", &[0.0, 0.0]).is_err());
+        let bytes = volume.to_vdb("density", &[0.0]).unwrap();
+        let i64_at = |at: usize| i64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        assert_eq!(i64_at(0), 0x5644_4220);
+        // Magic, versions, offsets flag and uuid
+        let mut at = 8 + 12 + 1 + 36;
+        // File metadata count and grid count
+        at += 8;
+        let name_len = u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        assert_eq!(&bytes[at + 4..at + 4 + name_len], b"density");
+        at += 4 + name_len + 4 + "Tree_float_5_4_3".len() + 4;
+        let (grid, block, end) = (i64_at(at), i64_at(at + 8), i64_at(at + 16));
+        assert_eq!(grid as usize, at + 24);
+        assert_eq!(end as usize, bytes.len());
+        // A single leaf holds the only non-background voxel (9, 1, 2)
+        let leaf = 8 * 8 + 1 + 512 * 4;
+        assert_eq!((end - block) as usize, leaf);
+        let bit = (1 << 6) | (1 << 3) | 2;
+        let word = i64_at(block as usize + (bit / 64) * 8) as u64;
+        assert_eq!(word, 1 << (bit % 64));
+    }
+}
@@ -0,0 +1,192 @@
+//! Procedural volume writes from a closure
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::sync_channel;
+
+use super::{VolumeStorage, VoxelValue};
+use crate::errors::{HapiError, Result};
+use crate::ffi::raw::HAPI_VolumeTileInfo;
+use crate::ffi::VolumeInfo;
+use crate::geometry::Geometry;
+
+// Tile layout of a volume in absolute voxel indices.
+struct TileGrid {
+    min: [i32; 3],
+    length: [i32; 3],
+    tile_size: i32,
+}
+
+impl TileGrid {
+    fn new(info: &VolumeInfo) -> Self {
+        TileGrid {
+            min: [info.min_x(), info.min_y(), info.min_z()],
+            length: [info.x_length(), info.y_length(), info.z_length()],
+            tile_size: info.tile_size().max(1),
+        }
+    }
+
+    fn tiles(&self) -> Vec<[i32; 3]> {
+        let step = self.tile_size as usize;
+        let mut tiles = vec![];
+        for z in (0..self.length[2]).step_by(step) {
+            for y in (0..self.length[1]).step_by(step) {
+                for x in (0..self.length[0]).step_by(step) {
+                    tiles.push([self.min[0] + x, self.min[1] + y, self.min[2] + z]);
+                }
+            }
+        }
+        tiles
+    }
+
+    // Evaluate `f` for every voxel of a tile inside the volume, with indices relative to the
+    // volume minimum. Returns `None` if all voxels have the background value.
+    fn fill<V: VoxelValue>(
+        &self,
+        start: [i32; 3],
+        f: &impl Fn([i32; 3]) -> V,
+    ) -> Option<Vec<V::Component>> {
+        let background = V::load(&vec![V::Component::default(); V::TUPLE_SIZE]);
+        let ts = self.tile_size;
+        let mut values = vec![V::Component::default(); (ts * ts * ts) as usize * V::TUPLE_SIZE];
+        let mut constant = true;
+        for tz in 0..ts {
+            for ty in 0..ts {
+                for tx in 0..ts {
+                    let ijk = [
+                        start[0] - self.min[0] + tx,
+                        start[1] - self.min[1] + ty,
+                        start[2] - self.min[2] + tz,
+                    ];
+                    if (0..3).any(|i| ijk[i] >= self.length[i]) {
+                        continue;
+                    }
+                    let value = f(ijk);
+                    if value != background {
+                        constant = false;
+                        let offset = (((tz * ts + ty) * ts + tx) as usize) * V::TUPLE_SIZE;
+                        value.store(&mut values[offset..offset + V::TUPLE_SIZE]);
+                    }
+                }
+            }
+        }
+        (!constant).then_some(values)
+    }
+}
+
+fn tile_info(start: [i32; 3]) -> HAPI_VolumeTileInfo {
+    HAPI_VolumeTileInfo {
+        minX: start[0],
+        minY: start[1],
+        minZ: start[2],
+        isValid: 1,
+    }
+}
+
+impl Geometry {
+    fn volume_tile_grid<V: VoxelValue>(&self, part: i32) -> Result<TileGrid> {
+        let info = self.volume_info(part)?;
+        if info.tuple_size() as usize != V::TUPLE_SIZE {
+            return Err(HapiError::internal(format!(
+                "Volume tuple size is {}, voxel values have {}",
+                info.tuple_size(),
+                V::TUPLE_SIZE
+            )));
+        }
+        Ok(TileGrid::new(&info))
+    }
+
+    /// Write a volume by evaluating `f` for every voxel. The closure gets the voxel index
+    /// relative to the volume minimum index. The volume info must already be set on the part.
+    ///
+    /// Tiles where every voxel has the background (zero) value are not sent.
+    /// Returns the number of tiles written. Call [`Geometry::commit`] afterwards.
+    pub fn write_volume_fn<V: VoxelValue>(
+        &self,
+        part: i32,
+        f: impl Fn([i32; 3]) -> V,
+    ) -> Result<usize> {
+        let grid = self.volume_tile_grid::<V>(part)?;
+        let mut written = 0;
+        for start in grid.tiles() {
+            if let Some(values) = grid.fill(start, &f) {
+                V::Component::write_tile(&self.node, part, &values, &tile_info(start))?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Same as [`Geometry::write_volume_fn`] but tiles are evaluated on up to `threads`
+    /// threads while the calling thread sends finished tiles to the session.
+    pub fn write_volume_fn_parallel<V>(
+        &self,
+        part: i32,
+        threads: usize,
+        f: impl Fn([i32; 3]) -> V + Sync,
+    ) -> Result<usize>
+    where
+        V: VoxelValue,
+        V::Component: Send,
+    {
+        let grid = self.volume_tile_grid::<V>(part)?;
+        let tiles = grid.tiles();
+        let next = AtomicUsize::new(0);
+        let threads = threads.clamp(1, tiles.len().max(1));
+        // Bounded so that memory stays proportional to the number of threads
+        let (sender, receiver) = sync_channel(threads * 2);
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                let sender = sender.clone();
+                let (grid, tiles, next, f) = (&grid, &tiles, &next, &f);
+                scope.spawn(move || {
+                    while let Some(&start) = tiles.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        if let Some(values) = grid.fill(start, f) {
+                            if sender.send((start, values)).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                });
+            }
+            drop(sender);
+            let mut written = 0;
+            for (start, values) in receiver {
+                V::Component::write_tile(&self.node, part, &values, &tile_info(start))?;
+                written += 1;
+            }
+            Ok(written)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_fill_skips_background() {
+        let grid = TileGrid {
+            min: [-4, 0, 0],
+            length: [10, 8, 8],
+            tile_size: 8,
+        };
+        let tiles = grid.tiles();
+        assert_eq!(tiles, [[-4, 0, 0], [4, 0, 0]]);
+        let f = |ijk: [i32; 3]| {
+            if ijk[0] >= 8 {
+                [ijk[1] as f32, 1.0, 0.0]
+            } else {
+                [0.0; 3]
+            }
+        };
+        assert!(grid.fill(tiles[0], &f).is_none());
+        let values = grid.fill(tiles[1], &f).unwrap();
+        assert_eq!(values.len(), 512 * 3);
+        // Voxel ijk = (9, 2, 0) is x = 1, y = 2 in the second tile
+        assert_eq!(
+            &values[(2 * 8 + 1) * 3..(2 * 8 + 1) * 3 + 3],
+            &[2.0, 1.0, 0.0]
+        );
+        // Outside of the volume
+        assert_eq!(&values[2 * 3..3 * 3], &[0.0; 3]);
+    }
+}
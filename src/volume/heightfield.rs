@@ -0,0 +1,367 @@
+//! Heightfield layers as 2D grids with heightmap image import and export
+use std::ffi::CString;
+use std::path::Path;
+
+use crate::errors::{HapiError, Result};
+use crate::geometry::{Geometry, HeightFieldSampling, HeightfieldNodes, PartType};
+use crate::node::{HoudiniNode, NodeHandle};
+
+/// World-space height range mapped to the full range of 16 bit heightmaps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeightRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl HeightRange {
+    fn quantize(&self, height: f32) -> u16 {
+        let span = self.max - self.min;
+        if span <= 0.0 {
+            return 0;
+        }
+        (((height - self.min) / span).clamp(0.0, 1.0) * 65535.0).round() as u16
+    }
+
+    fn dequantize(&self, value: u16) -> f32 {
+        self.min + (value as f32 / 65535.0) * (self.max - self.min)
+    }
+}
+
+/// Height and mask layers of a heightfield in world units.
+///
+/// Layers are row-major grids of `x_size * y_size` values indexed with `y * x_size + x`,
+/// the same layout Houdini uses for heightfield volumes. Heightmap images store row `y`
+/// of the grid in image row `y`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightfield {
+    pub x_size: usize,
+    pub y_size: usize,
+    pub voxel_size: f32,
+    pub height: Vec<f32>,
+    pub mask: Option<Vec<f32>>,
+}
+
+impl Heightfield {
+    /// Flat heightfield without a mask layer.
+    pub fn new(x_size: usize, y_size: usize, voxel_size: f32) -> Self {
+        Heightfield {
+            x_size,
+            y_size,
+            voxel_size,
+            height: vec![0.0; x_size * y_size],
+            mask: None,
+        }
+    }
+
+    /// Heightfield from a row-major grid of heights.
+    pub fn from_heights(
+        x_size: usize,
+        y_size: usize,
+        voxel_size: f32,
+        height: Vec<f32>,
+    ) -> Result<Self> {
+        if height.len() != x_size * y_size {
+            return Err(HapiError::internal(format!(
+                "Heightfield of {x_size}x{y_size} needs {} values, got {}",
+                x_size * y_size,
+                height.len()
+            )));
+        }
+        Ok(Heightfield {
+            x_size,
+            y_size,
+            voxel_size,
+            height,
+            mask: None,
+        })
+    }
+
+    /// Set the mask layer, which must have the same size as the height layer.
+    pub fn with_mask(mut self, mask: Vec<f32>) -> Result<Self> {
+        if mask.len() != self.height.len() {
+            return Err(HapiError::internal(format!(
+                "Heightfield mask has {} values, expected {}",
+                mask.len(),
+                self.height.len()
+            )));
+        }
+        self.mask = Some(mask);
+        Ok(self)
+    }
+
+    pub fn height_at(&self, x: usize, y: usize) -> f32 {
+        self.height[y * self.x_size + x]
+    }
+
+    pub fn set_height(&mut self, x: usize, y: usize, value: f32) {
+        self.height[y * self.x_size + x] = value;
+    }
+
+    /// Lowest and highest height.
+    pub fn height_range(&self) -> HeightRange {
+        let (min, max) = self
+            .height
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(lo, hi), &h| (lo.min(h), hi.max(h)));
+        if self.height.is_empty() {
+            HeightRange { min: 0.0, max: 0.0 }
+        } else {
+            HeightRange { min, max }
+        }
+    }
+
+    fn quantized(&self, range: &HeightRange) -> Vec<u16> {
+        self.height.iter().map(|&h| range.quantize(h)).collect()
+    }
+
+    fn from_quantized(
+        x_size: usize,
+        y_size: usize,
+        voxel_size: f32,
+        range: &HeightRange,
+        values: impl Iterator<Item = u16>,
+    ) -> Result<Self> {
+        let height = values.map(|v| range.dequantize(v)).collect();
+        Self::from_heights(x_size, y_size, voxel_size, height)
+    }
+
+    /// Encode heights as a 16 bit grayscale PNG. Heights are mapped from `range`, or the
+    /// height range of the data if `None`. Returns the image and the range used.
+    pub fn to_png16(&self, range: Option<HeightRange>) -> (Vec<u8>, HeightRange) {
+        let range = range.unwrap_or_else(|| self.height_range());
+        let png = crate::png::encode(
+            self.x_size as u32,
+            self.y_size as u32,
+            &self.quantized(&range),
+        );
+        (png, range)
+    }
+
+    /// Decode a 16 bit grayscale PNG heightmap, mapping values to world heights in `range`.
+    pub fn from_png16(bytes: &[u8], voxel_size: f32, range: HeightRange) -> Result<Self> {
+        let png = crate::png::decode(bytes)?;
+        Self::from_quantized(
+            png.width as usize,
+            png.height as usize,
+            voxel_size,
+            &range,
+            png.samples.into_iter(),
+        )
+    }
+
+    /// Write a 16 bit grayscale PNG heightmap, see [`Heightfield::to_png16`].
+    pub fn write_png16(
+        &self,
+        path: impl AsRef<Path>,
+        range: Option<HeightRange>,
+    ) -> Result<HeightRange> {
+        let (png, range) = self.to_png16(range);
+        std::fs::write(path, png)?;
+        Ok(range)
+    }
+
+    /// Read a 16 bit grayscale PNG heightmap, see [`Heightfield::from_png16`].
+    pub fn read_png16(path: impl AsRef<Path>, voxel_size: f32, range: HeightRange) -> Result<Self> {
+        Self::from_png16(&std::fs::read(path)?, voxel_size, range)
+    }
+
+    /// Write a raw little-endian 16 bit heightmap, returns the height range used.
+    pub fn write_r16(
+        &self,
+        path: impl AsRef<Path>,
+        range: Option<HeightRange>,
+    ) -> Result<HeightRange> {
+        let range = range.unwrap_or_else(|| self.height_range());
+        let data: Vec<u8> = self
+            .quantized(&range)
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        std::fs::write(path, data)?;
+        Ok(range)
+    }
+
+    /// Read a raw little-endian 16 bit heightmap of the given size.
+    pub fn read_r16(
+        path: impl AsRef<Path>,
+        x_size: usize,
+        y_size: usize,
+        voxel_size: f32,
+        range: HeightRange,
+    ) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        let values = data
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]));
+        Self::from_quantized(x_size, y_size, voxel_size, &range, values)
+    }
+
+    /// Write a raw little-endian 32 bit float heightmap in world units.
+    pub fn write_r32(&self, path: impl AsRef<Path>) -> Result<()> {
+        let data: Vec<u8> = self.height.iter().flat_map(|v| v.to_le_bytes()).collect();
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// Read a raw little-endian 32 bit float heightmap of the given size.
+    pub fn read_r32(
+        path: impl AsRef<Path>,
+        x_size: usize,
+        y_size: usize,
+        voxel_size: f32,
+    ) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        let height = data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        Self::from_heights(x_size, y_size, voxel_size, height)
+    }
+}
+
+impl Geometry {
+    /// Read the `height` and `mask` layers of a cooked heightfield.
+    pub fn read_heightfield(&self) -> Result<Heightfield> {
+        debug_assert!(
+            self.node.get_info()?.total_cook_count() > 0,
+            "Node not cooked"
+        );
+        let mut heightfield: Option<Heightfield> = None;
+        let mut mask = None;
+        for part in self.partitions()? {
+            if part.part_type() != PartType::Volume {
+                continue;
+            }
+            let info = self.volume_info(part.part_id())?;
+            let (x_size, y_size) = (info.x_length() as usize, info.y_length() as usize);
+            let mut values = vec![0.0; x_size * y_size];
+            match info.name(&self.node.session)?.as_str() {
+                "height" => {
+                    crate::ffi::get_heightfield_data(&self.node, part.part_id(), &mut values)?;
+                    let voxel_size = info.transform().scale()[0] * 2.0;
+                    heightfield = Some(Heightfield::from_heights(
+                        x_size, y_size, voxel_size, values,
+                    )?);
+                }
+                "mask" => {
+                    crate::ffi::get_heightfield_data(&self.node, part.part_id(), &mut values)?;
+                    mask = Some(values);
+                }
+                _ => {}
+            }
+        }
+        let heightfield =
+            heightfield.ok_or_else(|| HapiError::internal("Geometry has no height layer"))?;
+        match mask {
+            Some(mask) => heightfield.with_mask(mask),
+            None => Ok(heightfield),
+        }
+    }
+
+    /// Create heightfield input nodes sized for `heightfield` and upload its layers.
+    pub fn create_heightfield(
+        &self,
+        parent: impl Into<Option<NodeHandle>>,
+        volume_name: &str,
+        heightfield: &Heightfield,
+        sampling: HeightFieldSampling,
+    ) -> Result<HeightfieldNodes> {
+        let nodes = self.create_heightfield_input(
+            parent,
+            volume_name,
+            heightfield.x_size as i32,
+            heightfield.y_size as i32,
+            heightfield.voxel_size,
+            sampling,
+        )?;
+        nodes.write(heightfield)?;
+        Ok(nodes)
+    }
+}
+
+fn write_layer(
+    node: &HoudiniNode,
+    name: &str,
+    heightfield: &Heightfield,
+    values: &[f32],
+) -> Result<()> {
+    node.cook_blocking()?;
+    let geo = node
+        .geometry()?
+        .ok_or_else(|| HapiError::internal("Heightfield layer is not a SOP node"))?;
+    let info = geo.volume_info(0)?;
+    if info.x_length() as usize != heightfield.x_size
+        || info.y_length() as usize != heightfield.y_size
+    {
+        return Err(HapiError::internal(format!(
+            "Heightfield input is {}x{}, data is {}x{}",
+            info.x_length(),
+            info.y_length(),
+            heightfield.x_size,
+            heightfield.y_size
+        )));
+    }
+    geo.set_volume_info(0, &info)?;
+    crate::ffi::set_heightfield_data(&geo.node, 0, &CString::new(name)?, values)?;
+    geo.commit()
+}
+
+impl HeightfieldNodes {
+    /// Upload the height and mask layers and cook the heightfield.
+    pub fn write(&self, heightfield: &Heightfield) -> Result<()> {
+        write_layer(&self.height, "height", heightfield, &heightfield.height)?;
+        if let Some(mask) = &heightfield.mask {
+            write_layer(&self.mask, "mask", heightfield, mask)?;
+        }
+        self.heightfield.cook_blocking()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn height_quantization() {
+        let range = HeightRange {
+            min: -10.0,
+            max: 10.0,
+        };
+        assert_eq!(range.quantize(-10.0), 0);
+        assert_eq!(range.quantize(10.0), 65535);
+        assert_eq!(range.quantize(100.0), 65535);
+        assert!((range.dequantize(range.quantize(3.3)) - 3.3).abs() < 1e-3);
+    }
+
+    #[test]
+    fn heightmap_png_and_raw_roundtrip() {
+        let heights: Vec<f32> = (0..12).map(|v| v as f32 * 0.5 - 2.0).collect();
+        let hf = Heightfield::from_heights(4, 3, 2.0, heights).unwrap();
+        assert_eq!(hf.height_at(1, 2), 2.5);
+        assert!(hf.clone().with_mask(vec![1.0; 11]).is_err());
+        let (png, range) = hf.to_png16(None);
+        assert_eq!(
+            range,
+            HeightRange {
+                min: -2.0,
+                max: 3.5
+            }
+        );
+        let back = Heightfield::from_png16(&png, 2.0, range).unwrap();
+        assert_eq!((back.x_size, back.y_size), (4, 3));
+        for (a, b) in back.height.iter().zip(&hf.height) {
+            assert!((a - b).abs() < 1e-3);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let r32 = dir.path().join("height.r32");
+        hf.write_r32(&r32).unwrap();
+        assert_eq!(Heightfield::read_r32(&r32, 4, 3, 2.0).unwrap(), hf);
+        let r16 = dir.path().join("height.r16");
+        let range = hf.write_r16(&r16, None).unwrap();
+        let back = Heightfield::read_r16(&r16, 4, 3, 2.0, range).unwrap();
+        assert!((back.height_at(3, 2) - 3.5).abs() < 1e-3);
+        assert!(Heightfield::read_r16(&r16, 5, 3, 2.0, range).is_err());
+    }
+}
@@ -1,8 +1,17 @@
 //! Volume and Heightfield APIs
-use crate::ffi::{raw::HAPI_VolumeTileInfo, VolumeTileInfo};
+use crate::ffi::{enums::StorageType, raw::HAPI_VolumeTileInfo, VolumeTileInfo};
 use crate::node::HoudiniNode;
 use crate::Result;
 
+mod dense;
+mod export;
+mod generate;
+mod heightfield;
+mod voxel;
+pub use dense::DenseVolume;
+pub use heightfield::{HeightRange, Heightfield};
+pub use voxel::VoxelValue;
+
 /// Volume primitive dimensions returned from [`crate::geometry::Geometry::volume_bounds()`]
 #[derive(Debug, Clone, Default)]
 pub struct VolumeBounds {
@@ -18,6 +27,8 @@ pub struct VolumeBounds {
 }
 
 pub trait VolumeStorage: Sized + Copy {
+    fn storage() -> StorageType;
+
     fn read_tile(
         node: &HoudiniNode,
         part: i32,
@@ -53,6 +64,10 @@ pub trait VolumeStorage: Sized + Copy {
 }
 
 impl VolumeStorage for i32 {
+    fn storage() -> StorageType {
+        StorageType::Int
+    }
+
     fn read_tile(
         node: &HoudiniNode,
         part: i32,
@@ -96,6 +111,10 @@ impl VolumeStorage for i32 {
 }
 
 impl VolumeStorage for f32 {
+    fn storage() -> StorageType {
+        StorageType::Float
+    }
+
     fn read_tile(
         node: &HoudiniNode,
         part: i32,
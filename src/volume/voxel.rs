@@ -0,0 +1,144 @@
+//! Typed voxel values for scalar and vector volumes
+use super::VolumeStorage;
+use crate::errors::{HapiError, Result};
+use crate::ffi::VolumeTileInfo;
+use crate::geometry::Geometry;
+
+/// Value of a single voxel: a scalar or a fixed size tuple such as `[f32; 3]` for vector
+/// volumes.
+pub trait VoxelValue: Copy + PartialEq {
+    type Component: VolumeStorage + Default;
+    const TUPLE_SIZE: usize;
+    fn store(&self, out: &mut [Self::Component]);
+    fn load(values: &[Self::Component]) -> Self;
+}
+
+impl<T: VolumeStorage + Default + PartialEq> VoxelValue for T {
+    type Component = T;
+    const TUPLE_SIZE: usize = 1;
+    fn store(&self, out: &mut [T]) {
+        out[0] = *self;
+    }
+    fn load(values: &[T]) -> Self {
+        values[0]
+    }
+}
+
+impl<T: VolumeStorage + Default + PartialEq, const N: usize> VoxelValue for [T; N] {
+    type Component = T;
+    const TUPLE_SIZE: usize = N;
+    fn store(&self, out: &mut [T]) {
+        out[..N].copy_from_slice(self);
+    }
+    fn load(values: &[T]) -> Self {
+        std::array::from_fn(|i| values[i])
+    }
+}
+
+// Split interleaved tuple components into voxel values.
+fn load_values<V: VoxelValue>(components: &[V::Component], values: &mut [V]) {
+    for (value, tuple) in values
+        .iter_mut()
+        .zip(components.chunks_exact(V::TUPLE_SIZE))
+    {
+        *value = V::load(tuple);
+    }
+}
+
+fn store_values<V: VoxelValue>(values: &[V]) -> Vec<V::Component> {
+    let mut components = vec![V::Component::default(); values.len() * V::TUPLE_SIZE];
+    for (value, tuple) in values
+        .iter()
+        .zip(components.chunks_exact_mut(V::TUPLE_SIZE))
+    {
+        value.store(tuple);
+    }
+    components
+}
+
+impl Geometry {
+    fn check_voxel_tuple_size<V: VoxelValue>(&self, part: i32) -> Result<()> {
+        let tuple_size = self.volume_info(part)?.tuple_size();
+        if tuple_size as usize != V::TUPLE_SIZE {
+            return Err(HapiError::internal(format!(
+                "Volume tuple size is {tuple_size}, voxel values have {}",
+                V::TUPLE_SIZE
+            )));
+        }
+        Ok(())
+    }
+
+    /// Read a tile as typed voxel values, e.g. `[f32; 3]` for a velocity volume.
+    /// `values` must hold `tile_size³` voxels. Returns an error if the volume tuple size
+    /// doesn't match `V`.
+    pub fn read_volume_tile_values<V: VoxelValue>(
+        &self,
+        part: i32,
+        fill: V,
+        tile: &VolumeTileInfo,
+        values: &mut [V],
+    ) -> Result<()> {
+        self.check_voxel_tuple_size::<V>(part)?;
+        let mut fill_value = vec![V::Component::default(); V::TUPLE_SIZE];
+        fill.store(&mut fill_value);
+        let mut components = vec![V::Component::default(); values.len() * V::TUPLE_SIZE];
+        // HAPI takes a single fill value for all components
+        self.read_volume_tile(part, fill_value[0], tile, &mut components)?;
+        load_values(&components, values);
+        Ok(())
+    }
+
+    /// Write a tile of typed voxel values. See [`Geometry::read_volume_tile_values`].
+    pub fn write_volume_tile_values<V: VoxelValue>(
+        &self,
+        part: i32,
+        tile: &VolumeTileInfo,
+        values: &[V],
+    ) -> Result<()> {
+        self.check_voxel_tuple_size::<V>(part)?;
+        self.write_volume_tile(part, tile, &store_values(values))
+    }
+
+    /// Read a single typed voxel value.
+    pub fn read_volume_voxel_value<V: VoxelValue>(
+        &self,
+        part: i32,
+        x_index: i32,
+        y_index: i32,
+        z_index: i32,
+    ) -> Result<V> {
+        self.check_voxel_tuple_size::<V>(part)?;
+        let mut components = vec![V::Component::default(); V::TUPLE_SIZE];
+        self.read_volume_voxel(part, x_index, y_index, z_index, &mut components)?;
+        Ok(V::load(&components))
+    }
+
+    /// Write a single typed voxel value.
+    pub fn write_volume_voxel_value<V: VoxelValue>(
+        &self,
+        part: i32,
+        x_index: i32,
+        y_index: i32,
+        z_index: i32,
+        value: V,
+    ) -> Result<()> {
+        self.check_voxel_tuple_size::<V>(part)?;
+        self.write_volume_voxel(part, x_index, y_index, z_index, &store_values(&[value]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vector_values_roundtrip() {
+        let values = [[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0]];
+        let components = store_values(&values);
+        assert_eq!(components, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let mut loaded = [[0.0f32; 3]; 2];
+        load_values(&components, &mut loaded);
+        assert_eq!(loaded, values);
+        assert_eq!(store_values(&[7i32]), [7]);
+    }
+}
@@ -5,7 +5,7 @@ use once_cell::unsync::Lazy;
 use hapi_rs::{
     attribute::*,
     geometry::*,
-    node::CookResult,
+    node::{CookResult, TransformEuler},
     session::{quick_session, Session, SessionOptions},
     Result,
 };
@@ -708,3 +708,576 @@ fn geometry_test_get_set_dictionary_array_attribute() {
             .expect("Dictionary array attribute set");
     })
 }
+
+#[test]
+fn geometry_managed_input_node() {
+    SESSION.with(|session| {
+        let obj = session.create_node("Object/geo").unwrap();
+        let null = session
+            .node_builder("null")
+            .with_parent(&obj)
+            .create()
+            .unwrap();
+        let mut input = InputNode::new(session, "managed").unwrap();
+        assert!(input.is_dirty());
+        _create_triangle(input.edit());
+        input.connect_to(&null, 0).unwrap();
+        input.commit().unwrap();
+        assert!(!input.is_dirty());
+        let handle = input.node().handle;
+        assert_eq!(null.input_node(0).unwrap().unwrap().handle, handle);
+        null.disconnect_input(0).unwrap();
+        input.commit().unwrap();
+        assert_eq!(null.input_node(0).unwrap().unwrap().handle, handle);
+        input
+            .set_object_transform(&TransformEuler::default().with_position([0.0, 1.0, 0.0]))
+            .unwrap();
+        drop(input);
+        assert!(!handle.is_valid(session).unwrap());
+    })
+}
+
+#[test]
+fn geometry_set_mesh() {
+    use hapi_rs::mesh::{AttributeValues, Mesh};
+    SESSION.with(|session| {
+        let geo = session.create_input_node("mesh").unwrap();
+        let mesh = Mesh::new(
+            vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+            vec![3, 3],
+            vec![0, 1, 2, 0, 2, 3],
+        )
+        .with_normals(AttributeOwner::Point, vec![[0.0, 0.0, 1.0]; 4])
+        .with_uvs(AttributeOwner::Vertex, vec![[0.0, 0.0, 0.0]; 6])
+        .with_attribute(
+            "name",
+            AttributeOwner::Prim,
+            1,
+            AttributeValues::String(vec!["a".to_string(), "b".to_string()]),
+        )
+        .with_group("first", GroupType::Prim, vec![0]);
+        geo.set_mesh(&mesh).unwrap();
+        geo.node.cook_blocking().unwrap();
+        let part = geo.part_info(0).unwrap().unwrap();
+        assert_eq!(part.point_count(), 4);
+        assert_eq!(part.face_count(), 2);
+        assert_eq!(geo.vertex_list(Some(&part)).unwrap(), mesh.indices);
+        assert!(geo
+            .get_attribute(0, AttributeOwner::Vertex, "uv")
+            .unwrap()
+            .is_some());
+        let membership = geo
+            .get_group_membership(Some(&part), GroupType::Prim, "first")
+            .unwrap();
+        assert_eq!(membership, [1, 0]);
+        let bad = Mesh::new(vec![[0.0; 3]], vec![3], vec![0, 1, 2]);
+        assert!(geo.set_mesh(&bad).is_err());
+        geo.node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_read_mesh() {
+    use hapi_rs::mesh::{ComputeNormals, MeshReadOptions};
+    SESSION.with(|session| {
+        let geo = _load_test_geometry(session).unwrap();
+        let part = geo.part_info(0).unwrap().unwrap();
+        let mesh = geo
+            .read_mesh(Some(&part), &MeshReadOptions::default())
+            .unwrap();
+        assert_eq!(mesh.point_count() as i32, part.point_count());
+        assert_eq!(mesh.face_count() as i32, part.face_count());
+        mesh.validate().unwrap();
+
+        let options = MeshReadOptions::render().with_compute_normals(ComputeNormals::Flat);
+        let mesh = geo.read_mesh(Some(&part), &options).unwrap();
+        assert!(mesh.face_counts.iter().all(|c| *c == 3));
+        let normals = mesh.normals.as_ref().expect("normals");
+        assert_eq!(normals.owner, AttributeOwner::Point);
+        assert_eq!(normals.values.len(), mesh.point_count());
+        assert!(mesh
+            .attributes
+            .iter()
+            .all(|a| a.owner != AttributeOwner::Vertex));
+        mesh.validate().unwrap();
+    })
+}
+
+#[test]
+fn geometry_geo_file_roundtrip() {
+    use hapi_rs::geofile::GeoFile;
+    SESSION.with(|session| {
+        let geo = _load_test_geometry(session).unwrap();
+        let part = geo.part_info(0).unwrap().unwrap();
+        let file = geo.read_geo_file().unwrap();
+        file.validate().unwrap();
+        assert_eq!(file.point_count as i32, part.point_count());
+        assert_eq!(file.primitive_count() as i32, part.face_count());
+        let text = file.to_json_string();
+        assert_eq!(GeoFile::from_json_str(&text).unwrap(), file);
+
+        let input = session.create_input_node("geo_file").unwrap();
+        input.load_geo_file(&file).unwrap();
+        input.node.cook_blocking().unwrap();
+        let loaded = input.part_info(0).unwrap().unwrap();
+        assert_eq!(loaded.point_count(), part.point_count());
+        assert_eq!(loaded.vertex_count(), part.vertex_count());
+        input.node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_bgeo_file_decode() {
+    use hapi_rs::geofile::GeoFile;
+    SESSION.with(|session| {
+        let geo = _load_test_geometry(session).unwrap();
+        let ascii = geo.read_geo_file().unwrap();
+        let bytes = geo.save_to_memory(GeoFormat::Bgeo).unwrap();
+        let bytes: Vec<u8> = bytes.into_iter().map(|b| b as u8).collect();
+        let binary = GeoFile::from_bytes(&bytes).unwrap();
+        binary.validate().unwrap();
+        assert_eq!(binary.point_count, ascii.point_count);
+        assert_eq!(binary.vertices, ascii.vertices);
+        assert_eq!(binary.primitives, ascii.primitives);
+
+        let tmp = std::env::temp_dir().join("hapi_bgeo_decode.bgeo.sc");
+        geo.save_to_file(&tmp.to_string_lossy()).unwrap();
+        let compressed = GeoFile::read_file(&tmp).unwrap();
+        assert_eq!(compressed.point_count, ascii.point_count);
+        assert_eq!(compressed.positions(), ascii.positions());
+        std::fs::remove_file(tmp).unwrap();
+    })
+}
+
+#[test]
+fn geometry_obj_ply_exchange() {
+    SESSION.with(|session| {
+        let geo = _load_test_geometry(session).unwrap();
+        let part = geo.part_info(0).unwrap().unwrap();
+        let dir = std::env::temp_dir();
+        let obj = dir.join("hapi_exchange.obj");
+        let ply = dir.join("hapi_exchange.ply");
+        geo.export_obj(Some(&part), &obj).unwrap();
+        geo.export_ply(Some(&part), &ply).unwrap();
+
+        let input = session.create_input_node("obj_import").unwrap();
+        input.import_obj(&obj).unwrap();
+        input.node.cook_blocking().unwrap();
+        let imported = input.part_info(0).unwrap().unwrap();
+        assert_eq!(imported.point_count(), part.point_count());
+        assert_eq!(imported.face_count(), part.face_count());
+
+        input.import_ply(&ply).unwrap();
+        input.node.cook_blocking().unwrap();
+        let imported = input.part_info(0).unwrap().unwrap();
+        assert_eq!(imported.face_count(), part.face_count());
+        input.node.delete().unwrap();
+        std::fs::remove_file(obj).unwrap();
+        std::fs::remove_file(ply).unwrap();
+    })
+}
+
+#[test]
+fn geometry_schema() {
+    SESSION.with(|session| {
+        let geo = session.create_input_node("schema").unwrap();
+        _create_triangle(&geo);
+        geo.add_group(0, GroupType::Point, "corners", Some(&[1, 0, 1]))
+            .unwrap();
+        geo.commit().unwrap();
+        geo.node.cook_blocking().unwrap();
+        let schema = geo.schema().unwrap();
+        assert_eq!(schema.parts.len(), 1);
+        let part = &schema.parts[0];
+        assert_eq!(part.point_count, 3);
+        assert_eq!(part.face_count, 1);
+        let p = part.attribute(AttributeOwner::Point, "P").unwrap();
+        assert_eq!(p.tuple_size, 3);
+        assert_eq!(p.storage, StorageType::Float);
+        assert_eq!(schema.point_groups, ["corners"]);
+        assert!(schema.to_string().contains("corners"));
+
+        let restored = GeometrySchema::from_json_str(&schema.to_json_string()).unwrap();
+        assert!(schema.diff(&restored).is_empty());
+        let mut expected = restored.clone();
+        expected.parts[0].point_count = 0;
+        expected.parts[0]
+            .attributes
+            .retain(|a| a.name == "P" && a.owner == AttributeOwner::Point);
+        assert!(schema.check_contract(&expected).is_ok());
+        expected.prim_groups.push("missing".to_string());
+        assert!(schema.check_contract(&expected).is_err());
+        geo.node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_attribute_tuples() {
+    SESSION.with(|session| {
+        let geo = session.create_input_node("tuples").unwrap();
+        _create_triangle(&geo);
+        let attr_p = geo.get_position_attribute(0).unwrap();
+        let flat = attr_p.get(0).unwrap();
+        let points = attr_p.get_tuples::<[f32; 3]>(0).unwrap();
+        assert_eq!(points.len(), 3);
+        assert_eq!(points.concat(), flat);
+        assert!(attr_p.get_tuples::<[f32; 2]>(0).is_err());
+
+        let mut staging = vec![[0.0f32; 3]; 4];
+        assert_eq!(attr_p.read_into_slice(0, &mut staging).unwrap(), 3);
+        assert_eq!(&staging[..3], &points[..]);
+        assert!(attr_p.read_into_slice(0, &mut staging[..2]).is_err());
+        geo.node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_attribute_chunked_reads() {
+    SESSION.with(|session| {
+        let geo = _load_test_geometry(session).unwrap();
+        let attr_p = geo.get_position_attribute(0).unwrap();
+        let all = attr_p.get(0).unwrap();
+        let mut joined = vec![];
+        for chunk in attr_p.chunks(0, 5).unwrap() {
+            let (start, values) = chunk.unwrap();
+            assert_eq!(start * 3, joined.len());
+            assert!(values.len() <= 15);
+            joined.extend(values);
+        }
+        assert_eq!(joined, all);
+        assert_eq!(attr_p.get_range(0, 1, 2).unwrap(), &all[3..9]);
+        let count = (all.len() / 3) as i32;
+        assert!(attr_p.get_range(0, 0, -1).is_err());
+        assert!(attr_p.get_range(0, -1, 2).is_err());
+        assert!(attr_p.get_range(0, count - 1, 2).is_err());
+        assert_eq!(attr_p.chunks(0, usize::MAX).unwrap().count(), 1);
+
+        let results = read_parallel(&[(&attr_p, 0), (&attr_p, 0)], 2).unwrap();
+        assert_eq!(results, [all.clone(), all]);
+    })
+}
+
+#[test]
+fn geometry_set_unique_attributes() {
+    SESSION.with(|session| {
+        let geo = session.create_input_node("unique").unwrap();
+        _create_triangle(&geo);
+        let info = AttributeInfo::default()
+            .with_count(3)
+            .with_tuple_size(2)
+            .with_owner(AttributeOwner::Point)
+            .with_storage(StorageType::Int);
+        let id = geo.add_numeric_attribute::<i32>("id", 0, info).unwrap();
+        assert!(id.set_unique(0, &[7]).is_err());
+        id.set_unique(0, &[7, 8]).unwrap();
+        let info = AttributeInfo::default()
+            .with_count(1)
+            .with_tuple_size(1)
+            .with_owner(AttributeOwner::Prim)
+            .with_storage(StorageType::String);
+        let name = geo.add_string_attribute("name", 0, info).unwrap();
+        name.set_unique(0, "piece").unwrap();
+        geo.commit().unwrap();
+        geo.node.cook_blocking().unwrap();
+        assert_eq!(id.get(0).unwrap(), [7, 8, 7, 8, 7, 8]);
+        let names = name.get(0).unwrap();
+        assert_eq!(names.iter_str().next(), Some("piece"));
+        geo.node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_indexed_string_attribute() {
+    SESSION.with(|session| {
+        let geo = session.create_input_node("indexed").unwrap();
+        _create_triangle(&geo);
+        let info = AttributeInfo::default()
+            .with_count(3)
+            .with_tuple_size(1)
+            .with_owner(AttributeOwner::Point)
+            .with_storage(StorageType::String);
+        let attr = geo.add_string_attribute("path", 0, info).unwrap();
+        assert!(attr.set_indexed(0, &["a", "b"], &[0, 2, 1]).is_err());
+        attr.set_indexed(0, &["/a", "/b"], &[1, 0, 1]).unwrap();
+        geo.commit().unwrap();
+        geo.node.cook_blocking().unwrap();
+        let (strings, indices) = attr.get_indexed(0).unwrap();
+        let values: Vec<&str> = indices.iter().map(|&i| strings[i as usize].as_str()).collect();
+        assert_eq!(values, ["/b", "/a", "/b"]);
+        assert_eq!(strings.len(), 2);
+        geo.node.delete().unwrap();
+    })
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn geometry_typed_dictionary_attributes() {
+    use serde_json::json;
+
+    SESSION.with(|session| {
+        let geo = _create_single_point_geo(session).expect("Sphere geometry");
+        let info = AttributeInfo::default()
+            .with_count(1)
+            .with_tuple_size(1)
+            .with_owner(AttributeOwner::Detail)
+            .with_storage(StorageType::Dictionary);
+        let attr = geo
+            .add_dictionary_attribute("meta", 0, info)
+            .expect("Dictionary attribute");
+        assert!(attr.set_values(0, &[json!([1, 2])]).is_err());
+        attr.set_values(0, &[json!({"lod": 2, "tags": ["a", "b"]})])
+            .unwrap();
+        geo.commit().unwrap();
+        geo.node.cook_blocking().unwrap();
+        let values = attr.get_values(0).unwrap();
+        assert_eq!(values[0]["lod"], 2);
+        assert_eq!(values[0]["tags"][1], "b");
+        geo.node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_heightfield_roundtrip() {
+    use hapi_rs::volume::Heightfield;
+
+    SESSION.with(|session| {
+        let geo = session.create_input_node("heightfield_owner").unwrap();
+        let mut heightfield = Heightfield::new(16, 8, 1.0);
+        for y in 0..8 {
+            for x in 0..16 {
+                heightfield.set_height(x, y, (x + y) as f32 * 0.25);
+            }
+        }
+        let heightfield = heightfield.with_mask(vec![0.5; 16 * 8]).unwrap();
+        let nodes = geo
+            .create_heightfield(None, "terrain", &heightfield, HeightFieldSampling::Center)
+            .unwrap();
+        let result = nodes.heightfield.geometry().unwrap().unwrap();
+        let read = result.read_heightfield().unwrap();
+        assert_eq!((read.x_size, read.y_size), (16, 8));
+        assert!((read.height_at(3, 5) - 2.0).abs() < 1e-4);
+        assert_eq!(read.mask.as_ref().unwrap()[7], 0.5);
+        nodes.heightfield.delete().unwrap();
+        geo.node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_dense_volume() {
+    use hapi_rs::volume::DenseVolume;
+
+    SESSION.with(|session| {
+        let node = session.create_node("Object/hapi_vol").unwrap();
+        node.cook_blocking().unwrap();
+        let source = node.geometry().unwrap().unwrap();
+        let info = source.volume_info(0).unwrap();
+        let volume: DenseVolume<f32> = source.read_dense_volume(0).unwrap();
+        assert_eq!(volume.resolution[0], info.x_length() as usize);
+        let center = volume.resolution.map(|r| r / 2);
+        let value = volume.voxel(center[0], center[1], center[2])[0];
+        let position = volume.voxel_position(center[0], center[1], center[2]);
+        assert!((volume.sample(position) - value).abs() < 1e-4);
+
+        let dest = session.create_input_node("dense_copy").unwrap();
+        dest.node.cook_blocking().unwrap();
+        dest.set_part_info(&source.part_info(0).unwrap().unwrap())
+            .unwrap();
+        dest.write_dense_volume(0, &volume).unwrap();
+        dest.commit().unwrap();
+        dest.node.cook_blocking().unwrap();
+        let copy: DenseVolume<f32> = dest.read_dense_volume(0).unwrap();
+        assert_eq!(copy.resolution, volume.resolution);
+        assert_eq!(copy.data, volume.data);
+        dest.node.delete().unwrap();
+        node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_write_volume_fn() {
+    use hapi_rs::volume::DenseVolume;
+
+    SESSION.with(|session| {
+        let node = session.create_node("Object/hapi_vol").unwrap();
+        node.cook_blocking().unwrap();
+        let source = node.geometry().unwrap().unwrap();
+        let info = source.volume_info(0).unwrap();
+        let dest = session.create_input_node("volume_fn").unwrap();
+        dest.node.cook_blocking().unwrap();
+        dest.set_part_info(&source.part_info(0).unwrap().unwrap())
+            .unwrap();
+        dest.set_volume_info(0, &info).unwrap();
+        let x_length = info.x_length();
+        let sphere = |ijk: [i32; 3]| if ijk[0] < x_length / 2 { 1.0f32 } else { 0.0 };
+        let serial = dest.write_volume_fn(0, sphere).unwrap();
+        let parallel = dest.write_volume_fn_parallel(0, 4, sphere).unwrap();
+        assert_eq!(serial, parallel);
+        assert!(dest.write_volume_fn(0, |_| [0.0f32; 3]).is_err());
+        dest.commit().unwrap();
+        dest.node.cook_blocking().unwrap();
+        let volume: DenseVolume<f32> = dest.read_dense_volume(0).unwrap();
+        assert_eq!(volume.voxel(0, 0, 0)[0], 1.0);
+        dest.node.delete().unwrap();
+        node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_vector_volume() {
+    use hapi_rs::geometry::VolumeVisualType;
+
+    SESSION.with(|session| {
+        let node = session.create_node("Object/hapi_vol").unwrap();
+        node.cook_blocking().unwrap();
+        let source = node.geometry().unwrap().unwrap();
+        let visual = source.volume_visual_info(0).unwrap();
+        assert!(!matches!(visual.visual_type(), VolumeVisualType::Invalid));
+        assert!(source.read_volume_voxel_value::<[f32; 3]>(0, 0, 0, 0).is_err());
+
+        let info = source.volume_info(0).unwrap().with_tuple_size(3);
+        let dest = session.create_input_node("vector_volume").unwrap();
+        dest.node.cook_blocking().unwrap();
+        dest.set_part_info(&source.part_info(0).unwrap().unwrap())
+            .unwrap();
+        dest.set_volume_info(0, &info).unwrap();
+        let (x, y, z) = (info.min_x(), info.min_y(), info.min_z());
+        dest.write_volume_voxel_value(0, x, y, z, [1.0f32, 2.0, 3.0])
+            .unwrap();
+        dest.commit().unwrap();
+        dest.node.cook_blocking().unwrap();
+        let velocity: [f32; 3] = dest.read_volume_voxel_value(0, x, y, z).unwrap();
+        assert_eq!(velocity, [1.0, 2.0, 3.0]);
+        dest.node.delete().unwrap();
+        node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_export_volume_files() {
+    use hapi_rs::volume::DenseVolume;
+
+    SESSION.with(|session| {
+        let node = session.create_node("Object/hapi_vol").unwrap();
+        node.cook_blocking().unwrap();
+        let geo = node.geometry().unwrap().unwrap();
+        let volume: DenseVolume<f32> = geo.read_dense_volume(0).unwrap();
+        let dir = std::env::temp_dir();
+        volume.write_nrrd(dir.join("hapi_vol.nrrd")).unwrap();
+        volume.write_raw(dir.join("hapi_vol.raw")).unwrap();
+        volume
+            .write_vdb(dir.join("hapi_vol.vdb"), "density", &[0.0])
+            .unwrap();
+        let raw = std::fs::read(dir.join("hapi_vol.raw")).unwrap();
+        assert_eq!(raw.len(), volume.data.len() * 4);
+        assert!(dir.join("hapi_vol.json").exists());
+        node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_curves_roundtrip() {
+    use hapi_rs::curve::{Curve, Curves};
+    use hapi_rs::mesh::AttributeValues;
+
+    SESSION.with(|session| {
+        let geo = session.create_input_node("curves").unwrap();
+        geo.node.cook_blocking().unwrap();
+        let square = vec![
+            [0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0],
+        ];
+        let curves = Curves::new(vec![
+            Curve::nurbs(4, square.clone())
+                .with_knots(vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0])
+                .with_attribute("id", AttributeOwner::Prim, 1, AttributeValues::Int(vec![7])),
+            Curve::nurbs(3, square[..3].to_vec())
+                .with_knots(vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0])
+                .with_attribute("id", AttributeOwner::Prim, 1, AttributeValues::Int(vec![8])),
+        ]);
+        geo.write_curves(&curves).unwrap();
+        geo.node.cook_blocking().unwrap();
+        let read = geo.read_curves(0).unwrap();
+        assert_eq!(read.curves.len(), 2);
+        assert_eq!(read.curves[0].points, square);
+        assert_eq!(read.curves[1].order, 3);
+        assert_eq!(read.curves[1].knots.len(), 6);
+        let id = read.curves[1]
+            .attributes
+            .iter()
+            .find(|a| a.name == "id")
+            .unwrap();
+        assert_eq!(id.values, AttributeValues::Int(vec![8]));
+        assert!(read.curves[0].sample(10).is_some());
+        let bad = Curves::new(vec![Curve::nurbs(4, square).with_knots(vec![0.0; 3])]);
+        assert!(geo.write_curves(&bad).is_err());
+        geo.node.delete().unwrap();
+    })
+}
+
+#[test]
+fn geometry_input_curve_edits() {
+    use hapi_rs::geometry::{InputCurve, InputCurvePoint};
+
+    SESSION.with(|session| {
+        let mut curve = InputCurve::new(session, "spline").unwrap();
+        for x in 0..4 {
+            curve.push_point(InputCurvePoint::new([x as f32, 0.0, 0.0]));
+        }
+        assert!(curve.is_dirty());
+        let cooked = curve.cooked_curves().unwrap();
+        assert!(!curve.is_dirty());
+        assert_eq!(cooked.curves.len(), 1);
+
+        curve.set_curve_type(CurveType::Linear);
+        curve.set_order(2);
+        curve.insert_point(
+            1,
+            InputCurvePoint::new([0.5, 1.0, 0.0])
+                .with_rotation([0.0, 0.0, 0.0, 1.0])
+                .with_scale([2.0; 3]),
+        );
+        let cooked = curve.cooked_curves().unwrap();
+        assert_eq!(cooked.curves[0].kind, CurveType::Linear);
+        assert_eq!(cooked.curves[0].points.len(), 5);
+        assert_eq!(curve.read_info().unwrap().order(), 2);
+    })
+}
+
+#[test]
+fn geometry_instance_tree() {
+    use hapi_rs::instance::InstanceSource;
+
+    SESSION.with(|session| {
+        let node = session.create_node("Object/hapi_geo").unwrap();
+        let opt =
+            CookOptions::default().with_packed_prim_instancing_mode(PackedPrimInstancingMode::Flat);
+        node.cook_with_options(&opt, true).unwrap();
+        let instancer = &node.geometry_output_nodes().unwrap()[1];
+        let part = instancer.part_info(0).unwrap().expect("part 0");
+        let ids = instancer.get_instanced_part_ids(Some(&part)).unwrap();
+        let instanced = instancer
+            .part_info(ids[0])
+            .unwrap()
+            .expect("instanced part");
+        let membership = instancer
+            .get_instance_part_group_membership(Some(&instanced), GroupType::Prim, "group_1")
+            .unwrap();
+        assert_eq!(membership.len() as i32, instanced.face_count());
+        assert!(membership.contains(&1));
+
+        let tree = node.instance_tree().unwrap();
+        assert_eq!(tree.len() as i32, part.instance_count() * ids.len() as i32);
+        assert!(tree.iter().all(|i| matches!(
+            i.source,
+            InstanceSource::Part { part_id, .. } if ids.contains(&part_id)
+        )));
+        node.delete().unwrap();
+    })
+}
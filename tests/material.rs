@@ -1,7 +1,9 @@
 use once_cell::sync::Lazy;
 
 use hapi_rs::{
+    enums::{ImageDataFormat, ImagePacking},
     geometry::Materials,
+    material::{PixelData, TextureImage, TextureTarget},
     session::{quick_session, Session, SessionOptions},
 };
 
@@ -47,3 +49,72 @@ fn image_extract_api() {
         panic!("Failed to extract material data")
     }
 }
+
+#[test]
+fn material_describe() {
+    let node = SESSION.create_node("Object/spaceship").unwrap();
+    node.cook_blocking().unwrap();
+    let geo = node.geometry().expect("geometry").unwrap();
+    let part = geo.part_info(0).unwrap().expect("part 0");
+    let faces = geo
+        .get_face_materials(Some(&part))
+        .unwrap()
+        .expect("materials");
+    assert_eq!(faces.indices.len(), part.face_count() as usize);
+    assert!(faces
+        .indices
+        .iter()
+        .all(|i| (-1..faces.materials.len() as i32).contains(i)));
+    let pbr = faces.materials[0].describe().unwrap();
+    assert!(!pbr.name.is_empty());
+    let texture = pbr.base_color_texture.expect("base color texture");
+    assert_eq!(texture.parm, "baseColorMap");
+    match texture.image {
+        Some(TextureImage::Memory { format, data }) => {
+            assert_eq!(format, "PNG");
+            assert!(data.starts_with(b"\x89PNG"));
+        }
+        other => panic!("Unexpected texture image {other:?}"),
+    }
+    let skipped = faces.describe(&TextureTarget::Skip).unwrap();
+    assert_eq!(skipped.len(), faces.materials.len());
+    assert!(skipped[0]
+        .base_color_texture
+        .as_ref()
+        .unwrap()
+        .image
+        .is_none());
+}
+
+#[test]
+fn image_extract_pixels() {
+    let node = SESSION.create_node("Object/spaceship").unwrap();
+    node.cook_blocking().unwrap();
+    let geo = node.geometry().expect("geometry").unwrap();
+    let Some(Materials::Single(mat)) = geo.get_materials(None).unwrap() else {
+        panic!("Failed to extract material data")
+    };
+    mat.render_texture("baseColorMap").unwrap();
+    let info = mat
+        .get_image_info()
+        .unwrap()
+        .with_x_res(64)
+        .with_y_res(32)
+        .with_data_format(ImageDataFormat::Int8)
+        .with_packing(ImagePacking::Rgba)
+        .with_interleaved(true);
+    mat.set_image_info(&info).unwrap();
+    let image = mat.extract_image_pixels("C").unwrap();
+    assert_eq!(
+        (image.width(), image.height(), image.channels()),
+        (64, 32, 4)
+    );
+    assert!(matches!(image.pixels(), PixelData::U8(v) if v.len() == 64 * 32 * 4));
+    assert_eq!(image.to_rgba8().len(), 64 * 32 * 4);
+
+    mat.render_texture("baseColorMap").unwrap();
+    mat.set_image_info(&info.with_data_format(ImageDataFormat::Float32))
+        .unwrap();
+    let image = mat.extract_image_pixels("C").unwrap();
+    assert!(matches!(image.pixels(), PixelData::F32(v) if v.len() == 64 * 32 * 4));
+}
@@ -208,3 +208,67 @@ fn node_get_set_preset() {
         assert_eq!(p.get(0).unwrap(), 1.0);
     }
 }
+
+#[test]
+fn node_object_tree() {
+    let asset = SESSION.create_node("Object/spaceship").unwrap();
+    asset.cook_blocking().unwrap();
+    let tree = asset.object_tree().unwrap();
+    assert_eq!(
+        tree.objects().len(),
+        asset.get_objects_info().unwrap().len()
+    );
+    assert!(tree.roots().count() > 0);
+    for obj in tree.iter_depth_first() {
+        if let Some(parent) = obj.parent {
+            assert!(tree.objects()[parent].children.contains(
+                &tree
+                    .objects()
+                    .iter()
+                    .position(|o| o.handle == obj.handle)
+                    .unwrap()
+            ));
+        }
+        let local = obj.local.to_matrix();
+        let engine = obj.local.convert_to_matrix(&SESSION).unwrap();
+        for (a, b) in local.iter().zip(engine.iter()) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+}
+
+#[test]
+fn node_handles() {
+    let asset = SESSION.create_node("Object/spaceship").unwrap();
+    let handles = asset.handles().unwrap();
+    assert_eq!(
+        handles.len() as i32,
+        asset.asset_info().unwrap().handle_count()
+    );
+    for handle in &handles {
+        assert!(!handle.type_name().unwrap().is_empty());
+        let bindings = handle.bindings().unwrap();
+        assert_eq!(bindings.len() as i32, handle.bindings_count());
+        for binding in bindings {
+            assert!(binding.asset_parm_id().0 >= 0);
+            binding.handle_parm_name().unwrap();
+        }
+    }
+}
+
+#[test]
+fn node_export_gltf() {
+    use hapi_rs::gltf::GltfExportOptions;
+    let asset = SESSION.create_node("Object/spaceship").unwrap();
+    asset.cook_blocking().unwrap();
+    let options = GltfExportOptions::default().with_textures(false);
+    let doc = asset.to_gltf(&options).unwrap();
+    assert!(doc.mesh_count() > 0);
+    assert!(doc.nodes().len() >= asset.get_objects_info().unwrap().len());
+    let glb = doc.to_glb();
+    assert_eq!(&glb[..4], b"glTF");
+    let path = std::env::temp_dir().join("hapi_spaceship.gltf");
+    asset.export_gltf(&path, &options).unwrap();
+    assert!(std::fs::metadata(&path).unwrap().len() > 0);
+    std::fs::remove_file(path).unwrap();
+}